@@ -0,0 +1,33 @@
+-- Subreddits registered for scheduled snapshots
+CREATE TYPE monitor_schedule AS ENUM ('hourly', 'daily');
+
+CREATE TABLE monitored_subreddits (
+       id BIGSERIAL PRIMARY KEY,
+       -- Google ID (`sub`) of the user who registered the monitor
+       owner TEXT NOT NULL,
+       subreddit TEXT NOT NULL,
+       schedule monitor_schedule NOT NULL,
+       -- JSON array of report types, eg. ["sentiment", "hate_speech"]
+       report_types JSONB NOT NULL,
+       last_run_at TIMESTAMPTZ,
+       created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX monitored_subreddits_owner_idx ON monitored_subreddits (owner);
+
+-- Aggregated metrics of a single snapshot
+CREATE TABLE subreddit_snapshots (
+       id BIGSERIAL PRIMARY KEY,
+       monitor_id BIGINT NOT NULL REFERENCES monitored_subreddits (id) ON DELETE CASCADE,
+       taken_at TIMESTAMPTZ NOT NULL,
+       -- Number of posts created since the previous snapshot
+       item_count INTEGER NOT NULL,
+       mean_score DOUBLE PRECISION NOT NULL,
+       sentiment_mean DOUBLE PRECISION,
+       negative_share DOUBLE PRECISION,
+       toxicity_rate DOUBLE PRECISION,
+       -- Full report summary, see `ReportSummary`
+       summary JSONB NOT NULL
+);
+
+CREATE INDEX subreddit_snapshots_monitor_idx ON subreddit_snapshots (monitor_id, taken_at);
@@ -0,0 +1,2 @@
+-- Newest post seen by the previous snapshot, see `FeedCursor`
+ALTER TABLE monitored_subreddits ADD COLUMN cursor JSONB;
@@ -0,0 +1,40 @@
+-- Snapshot metrics alert rules can watch
+CREATE TYPE alert_metric AS ENUM (
+       'item_count',
+       'mean_score',
+       'sentiment_mean',
+       'negative_share',
+       'toxicity_rate'
+);
+
+-- Rules evaluated after every snapshot of a monitor
+CREATE TABLE alert_rules (
+       id BIGSERIAL PRIMARY KEY,
+       monitor_id BIGINT NOT NULL REFERENCES monitored_subreddits (id) ON DELETE CASCADE,
+       metric alert_metric NOT NULL,
+       -- See `AlertCondition`
+       condition JSONB NOT NULL,
+       webhook_url TEXT NOT NULL,
+       -- Key used to sign webhook payloads
+       secret TEXT NOT NULL,
+       -- Did the condition hold after the last snapshot? Rules fire only when it starts holding.
+       firing BOOLEAN NOT NULL DEFAULT false,
+       created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX alert_rules_monitor_idx ON alert_rules (monitor_id);
+
+-- Every attempt to deliver a fired alert to its webhook
+CREATE TABLE alert_deliveries (
+       id BIGSERIAL PRIMARY KEY,
+       rule_id BIGINT NOT NULL REFERENCES alert_rules (id) ON DELETE CASCADE,
+       attempt INTEGER NOT NULL,
+       attempted_at TIMESTAMPTZ NOT NULL,
+       payload JSONB NOT NULL,
+       -- HTTP status of the webhook's response, NULL if there was no response
+       status_code INTEGER,
+       error TEXT,
+       delivered BOOLEAN NOT NULL
+);
+
+CREATE INDEX alert_deliveries_rule_idx ON alert_deliveries (rule_id, attempted_at);
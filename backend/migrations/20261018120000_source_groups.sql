@@ -0,0 +1,12 @@
+-- Named sets of subreddits, referenced as `g/<name>` wherever a data source is accepted
+CREATE TABLE source_groups (
+       id BIGSERIAL PRIMARY KEY,
+       -- Google ID (`sub`) of the user who defined the group
+       owner TEXT NOT NULL,
+       -- eg. polish-news
+       name TEXT NOT NULL,
+       -- JSON array of subreddit names, eg. ["Polska", "poland"]
+       subreddits JSONB NOT NULL,
+       created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+       UNIQUE (owner, name)
+);
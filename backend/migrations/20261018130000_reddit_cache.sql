@@ -0,0 +1,9 @@
+-- Reddit API responses cached between requests and server restarts
+CREATE TABLE reddit_cache (
+       -- Request path and query, eg. /r/Polska/new.json?limit=100
+       key TEXT PRIMARY KEY,
+       body JSONB NOT NULL,
+       expires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX reddit_cache_expires_at ON reddit_cache (expires_at);
@@ -0,0 +1,44 @@
+-- Every post and comment fetched from Reddit, to recompute reports and build datasets over time
+CREATE TABLE corpus_posts (
+       -- eg. t3_8z1v1z
+       fullname TEXT PRIMARY KEY,
+       subreddit TEXT NOT NULL,
+       author TEXT NOT NULL,
+       created_utc TIMESTAMPTZ NOT NULL,
+       score BIGINT NOT NULL,
+       num_comments BIGINT NOT NULL,
+       -- The whole post, as it was last seen
+       data JSONB NOT NULL,
+       first_seen_at TIMESTAMPTZ NOT NULL,
+       last_seen_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX corpus_posts_subreddit ON corpus_posts (lower(subreddit), created_utc);
+
+CREATE TABLE corpus_comments (
+       -- eg. t1_lm3k2x9
+       fullname TEXT PRIMARY KEY,
+       subreddit TEXT NOT NULL,
+       author TEXT NOT NULL,
+       -- Fullname of the post the comment is in
+       link_id TEXT NOT NULL,
+       created_utc TIMESTAMPTZ NOT NULL,
+       score BIGINT NOT NULL,
+       -- The whole comment without its replies, as it was last seen
+       data JSONB NOT NULL,
+       first_seen_at TIMESTAMPTZ NOT NULL,
+       last_seen_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX corpus_comments_subreddit ON corpus_comments (lower(subreddit), created_utc);
+CREATE INDEX corpus_comments_link_id ON corpus_comments (link_id);
+
+-- Score of a post or comment whenever it changed between fetches
+CREATE TABLE corpus_scores (
+       fullname TEXT NOT NULL,
+       seen_at TIMESTAMPTZ NOT NULL,
+       score BIGINT NOT NULL,
+       -- Only for posts
+       num_comments BIGINT,
+       PRIMARY KEY (fullname, seen_at)
+);
@@ -0,0 +1,12 @@
+-- Progress of archive dump imports into the corpus, to resume them after an interruption
+CREATE TABLE corpus_imports (
+       -- File name of the dump, eg. RS_2023-01.zst
+       source TEXT PRIMARY KEY,
+       -- Lines of the dump already processed
+       lines_done BIGINT NOT NULL DEFAULT 0,
+       -- Posts and comments stored so far
+       imported BIGINT NOT NULL DEFAULT 0,
+       started_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+       updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+       finished_at TIMESTAMPTZ
+);
@@ -0,0 +1,4 @@
+-- Snapshots that failed since the last successful one, retried with a growing delay
+ALTER TABLE monitored_subreddits ADD COLUMN failures INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE monitored_subreddits ADD COLUMN last_error TEXT;
+ALTER TABLE monitored_subreddits ADD COLUMN retry_at TIMESTAMPTZ;
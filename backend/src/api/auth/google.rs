@@ -1,6 +1,8 @@
 use super::error::AuthError;
 use crate::api::auth::jwt::decode_jwt;
 use crate::api::auth::middleware::jwt_from_header_or_uri;
+use crate::config::AuthConfig;
+use crate::AppState;
 use axum::async_trait;
 use axum::extract::FromRequestParts;
 use derive_getters::Getters;
@@ -39,14 +41,17 @@ pub struct GoogleUserInfo {
 /// With this trait implemented, the user info can be extracted by any Axum HTTP handler without
 /// jumping through extra hoops like obtaining an `Authorization` header and decoding the JWT.
 #[async_trait]
-impl<T> FromRequestParts<T> for GoogleUserInfo {
+impl FromRequestParts<AppState> for GoogleUserInfo {
     type Rejection = StatusCode;
 
-    async fn from_request_parts(parts: &mut Parts, _: &T) -> Result<Self, Self::Rejection> {
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
         log::warn!("Extracting GoogleUserInfo");
         let token = jwt_from_header_or_uri(&parts.headers, &parts.uri);
         let result = token
-            .and_then(|token| decode_jwt(token).ok())
+            .and_then(|token| decode_jwt(token, &state.config.auth).ok())
             .map(|claims| claims.claims.user_info);
 
         match result {
@@ -66,15 +71,12 @@ impl<T> FromRequestParts<T> for GoogleUserInfo {
 pub async fn fetch_google_access_token(
     auth_code: String,
     http: &Client,
+    config: &AuthConfig,
 ) -> Result<GoogleTokenResponse, AuthError> {
-    let client_id = dotenvy::var("GOOGLE_CLIENT_ID").expect("GOOGLE_CLIENT_ID should be set");
-    let client_secret =
-        dotenvy::var("GOOGLE_CLIENT_SECRET").expect("GOOGLE_CLIENT_SECRET should be set");
-
     let form_data = Form::new()
         .text("code", auth_code)
-        .text("client_id", client_id)
-        .text("client_secret", client_secret)
+        .text("client_id", config.google_client_id.clone())
+        .text("client_secret", config.google_client_secret.clone())
         .text("redirect_uri", "postmessage")
         .text("grant_type", "authorization_code");
 
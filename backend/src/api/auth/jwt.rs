@@ -4,6 +4,7 @@ use log_derive::logfn;
 use serde::{Deserialize, Serialize};
 
 use super::{error::AuthError, google::GoogleUserInfo};
+use crate::config::AuthConfig;
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Claims {
@@ -14,13 +15,11 @@ pub struct Claims {
     pub user_info: GoogleUserInfo,
 }
 
-/// Create a new JWT based on the secret defined in the environment.
-pub fn create_jwt(user_info: GoogleUserInfo) -> String {
-    let secret = dotenvy::var("JWT_SECRET").expect("JWT_SECRET should be set");
-
+/// Create a new JWT signed with the configured secret, valid for the configured lifetime.
+pub fn create_jwt(user_info: GoogleUserInfo, config: &AuthConfig) -> String {
     let claim = {
         let now = Utc::now();
-        let duration = Duration::days(30);
+        let duration = Duration::days(config.token_lifetime_days);
         let iat = now.timestamp() as usize;
         let exp = (now + duration).timestamp() as usize;
         Claims {
@@ -29,7 +28,7 @@ pub fn create_jwt(user_info: GoogleUserInfo) -> String {
             user_info,
         }
     };
-    let key = jsonwebtoken::EncodingKey::from_secret(secret.as_bytes());
+    let key = jsonwebtoken::EncodingKey::from_secret(config.jwt_secret.as_bytes());
 
     jsonwebtoken::encode(&Header::default(), &claim, &key)
         .expect("Failed to encode JWT, unrecoverable")
@@ -37,9 +36,8 @@ pub fn create_jwt(user_info: GoogleUserInfo) -> String {
 
 /// Decode given JWT, verifing it at the same time. Return the decoded token data.
 #[logfn(err = "ERROR", fmt = "Failed to decode JWT: {:?}")]
-pub fn decode_jwt(token: &str) -> Result<TokenData<Claims>, AuthError> {
-    let secret = dotenvy::var("JWT_SECRET").expect("JWT_SECRET should be set");
-    let key = &DecodingKey::from_secret(secret.as_bytes());
+pub fn decode_jwt(token: &str, config: &AuthConfig) -> Result<TokenData<Claims>, AuthError> {
+    let key = &DecodingKey::from_secret(config.jwt_secret.as_bytes());
 
     decode::<Claims>(token, key, &Validation::new(jsonwebtoken::Algorithm::HS256))
         .map_err(AuthError::JwtError)
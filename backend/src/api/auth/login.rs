@@ -24,12 +24,12 @@ pub async fn login(
     State(state): State<AppState>,
     Json(body): Json<LoginPayload>,
 ) -> Result<Json<LoginResponse>, AppError> {
-    let auth_data = fetch_google_access_token(body.code, &state.http).await?;
+    let auth_data = fetch_google_access_token(body.code, &state.http, &state.config.auth).await?;
 
     let user_info =
         fetch_google_user_info(auth_data.access_token().to_string(), &state.http).await?;
 
-    let jwt = create_jwt(user_info);
+    let jwt = create_jwt(user_info, &state.config.auth);
 
     Ok(Json(LoginResponse { jwt }))
 }
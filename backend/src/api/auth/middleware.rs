@@ -1,5 +1,10 @@
 use crate::api::auth::jwt::decode_jwt;
-use axum::{extract::Request, middleware::Next, response::Response};
+use crate::AppState;
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
 use http::{HeaderMap, StatusCode, Uri};
 use log_derive::logfn;
 
@@ -18,19 +23,24 @@ pub fn jwt_from_header_or_uri<'a>(headers: &'a HeaderMap, uri: &'a Uri) -> Optio
     err = "ERROR",
     fmt = "Authorization failed: {:?}. Invalid/missing 'Authorization' header, missing fallback auth query param or invalid JWT"
 )]
-pub async fn authorization(request: Request, next: Next) -> Result<Response, StatusCode> {
-    jwt_from_header_or_uri(request.headers(), request.uri())
+pub async fn authorization(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    let claims = jwt_from_header_or_uri(request.headers(), request.uri())
         .ok_or_else(|| {
             log::error!("JWT not found in the request's Authorization header or query params.");
             StatusCode::UNAUTHORIZED
         })
         .and_then(|jwt| {
-            decode_jwt(jwt).map_err(|_| {
+            decode_jwt(jwt, &state.config.auth).map_err(|_| {
                 log::error!("Failed to decode JWT");
                 StatusCode::UNAUTHORIZED
             })
         })?;
 
+    tracing::Span::current().record("user", claims.claims.user_info.sub());
     log::info!("Authorization successful");
     Ok(next.run(request).await)
 }
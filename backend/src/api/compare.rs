@@ -0,0 +1,100 @@
+use crate::api::auth::google::GoogleUserInfo;
+use crate::reddit_fetcher::feed_request::{
+    DataSource, FetcherFeedRequest, RMoodsReportType, RedditFeedKind, RequestSize,
+};
+use crate::reddit_fetcher::model::posts::Posts;
+use crate::reddit_fetcher::reddit::request::params::FeedSorting;
+use crate::report::compare::Comparison;
+use crate::report::{Report, ReportItem};
+use crate::source_group;
+use crate::{app_error::AppError, telemetry, AppState};
+use axum::{extract::State, Json};
+use log::info;
+use log_derive::logfn;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use tracing::instrument;
+
+/// How many communities can be compared at once.
+const MAX_COMPARED: usize = 5;
+/// Reddit requests spent on each community by default.
+const DEFAULT_REQUESTS_PER_SUBREDDIT: u16 = 5;
+/// Upper limit of the Reddit requests spent on each community.
+const MAX_REQUESTS_PER_SUBREDDIT: u16 = 25;
+
+#[derive(Deserialize, Debug)]
+pub struct ComparePayload {
+    /// Subreddit names without `r/`, eg. Polska, or source groups of the user, eg. g/polish-news
+    subreddits: Vec<String>,
+    report_types: Vec<RMoodsReportType>,
+    /// Every community gets the same budget, so the samples are comparable
+    requests_per_subreddit: Option<u16>,
+    #[serde(default)]
+    sorting: FeedSorting,
+    /// Fetch fresh data from Reddit even if there's a cached response
+    #[serde(default)]
+    bypass_cache: bool,
+}
+
+/// Compare two or more communities side by side.
+///
+/// Runs the same reports over every subreddit with equal budgets and returns the aligned metrics,
+/// their pairwise differences and bootstrap confidence intervals.
+#[utoipa::path(post, path = "/api/compare", responses(), params())]
+#[logfn(err = "ERROR", fmt = "'compare' failed: {:?}")]
+#[instrument(name = "report", skip_all, fields(job_id = telemetry::job_id()))]
+pub async fn compare(
+    State(mut state): State<AppState>,
+    user_info: Option<GoogleUserInfo>,
+    Json(body): Json<ComparePayload>,
+) -> Result<Json<Comparison>, AppError> {
+    if !(2..=MAX_COMPARED).contains(&body.subreddits.len()) {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            format!("Compare between 2 and {MAX_COMPARED} subreddits"),
+        ));
+    }
+    let requests_per_subreddit = body
+        .requests_per_subreddit
+        .unwrap_or(DEFAULT_REQUESTS_PER_SUBREDDIT)
+        .clamp(1, MAX_REQUESTS_PER_SUBREDDIT);
+
+    state.fetcher.bypass_cache(body.bypass_cache);
+
+    let share = 1.0 / body.subreddits.len() as f32;
+    let mut data_sources = vec![];
+    for name in &body.subreddits {
+        let source = DataSource {
+            name: name.clone(),
+            post_id: None,
+            share,
+        };
+        let owner = user_info.as_ref().map(|u| u.sub().as_str());
+        data_sources.push(source_group::resolve_source(&state.pool, owner, source).await?);
+    }
+
+    let request = FetcherFeedRequest {
+        resource_kind: RedditFeedKind::SubredditPosts,
+        report_types: body.report_types.clone(),
+        data_sources,
+        size: RequestSize::Custom(requests_per_subreddit * body.subreddits.len() as u16),
+        sorting: body.sorting,
+        since: None,
+        search: None,
+        user_filter: Default::default(),
+    };
+
+    let mut communities = vec![];
+    // Groups are labeled with their reference, eg. `g/polish-news`, not their subreddits
+    for (subreddit, request) in body.subreddits.iter().zip(request.per_source()) {
+        let (posts, _) = state.fetcher.fetch_feed::<Posts>(request).await?;
+        let items = posts.list.iter().map(ReportItem::from).collect::<Vec<_>>();
+        let report = Report::generate(&state.nlp, items, &body.report_types).await?;
+        communities.push((subreddit.clone(), report));
+    }
+
+    info!("Comparing {:?}", body.subreddits);
+    let comparison =
+        Comparison::from_reports(communities, &body.report_types, &mut rand::thread_rng());
+    Ok(Json(comparison))
+}
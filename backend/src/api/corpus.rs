@@ -0,0 +1,78 @@
+use crate::corpus::store::{self, ScoreSnapshot};
+use crate::reddit_fetcher::reddit::model::{RawComment, RawPost};
+use crate::{app_error::AppError, AppState};
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use log_derive::logfn;
+use serde::Deserialize;
+
+/// Items returned when no `limit` is given.
+const DEFAULT_LIMIT: i64 = 100;
+/// Upper limit of the items returned at once.
+const MAX_LIMIT: i64 = 1000;
+
+#[derive(Deserialize, Debug)]
+pub struct CorpusParams {
+    /// Only return items created after this time
+    since: Option<DateTime<Utc>>,
+    limit: Option<i64>,
+}
+
+impl CorpusParams {
+    fn limit(&self) -> i64 {
+        self.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT)
+    }
+}
+
+/// Get the stored posts of a subreddit, newest first.
+///
+/// The corpus contains every post ever fetched, not only the latest ~1000 Reddit lists.
+#[utoipa::path(get, path = "/api/corpus/r/{subreddit}/posts", responses(), params())]
+#[logfn(err = "ERROR", fmt = "'corpus_posts' failed: {:?}")]
+pub async fn corpus_posts(
+    State(state): State<AppState>,
+    Path(subreddit): Path<String>,
+    Query(params): Query<CorpusParams>,
+) -> Result<Json<Vec<RawPost>>, AppError> {
+    let posts = store::get_posts(&state.pool, &subreddit, params.since, params.limit()).await?;
+    Ok(Json(posts))
+}
+
+/// Get the stored comments of a subreddit, newest first.
+#[utoipa::path(
+    get,
+    path = "/api/corpus/r/{subreddit}/comments",
+    responses(),
+    params()
+)]
+#[logfn(err = "ERROR", fmt = "'corpus_comments' failed: {:?}")]
+pub async fn corpus_comments(
+    State(state): State<AppState>,
+    Path(subreddit): Path<String>,
+    Query(params): Query<CorpusParams>,
+) -> Result<Json<Vec<RawComment>>, AppError> {
+    let comments =
+        store::get_comments(&state.pool, &subreddit, params.since, params.limit()).await?;
+    Ok(Json(comments))
+}
+
+/// Get the score history of a post or comment, by its fullname, eg. t3_8z1v1z.
+///
+/// A point is recorded whenever the score changed between two fetches.
+#[utoipa::path(
+    get,
+    path = "/api/corpus/item/{fullname}/scores",
+    responses(),
+    params()
+)]
+#[logfn(err = "ERROR", fmt = "'score_history' failed: {:?}")]
+pub async fn score_history(
+    State(state): State<AppState>,
+    Path(fullname): Path<String>,
+) -> Result<Json<Vec<ScoreSnapshot>>, AppError> {
+    let history = store::get_score_history(&state.pool, &fullname).await?;
+    Ok(Json(history))
+}
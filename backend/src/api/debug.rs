@@ -1,14 +1,20 @@
 use super::AnyParams;
 use crate::reddit_fetcher::feed_request::{
-    DataSource, FetcherFeedRequest, RMoodsReportType, RedditFeedKind, RequestSize,
+    DataSource, FetcherFeedRequest, RMoodsReportType, RedditFeedKind, RequestSize, SearchQuery,
 };
+use crate::reddit_fetcher::model::items_info::ItemsInfo;
 use crate::reddit_fetcher::model::post_comments::PostComments;
+use crate::reddit_fetcher::model::post_detail::PostDetail;
 use crate::reddit_fetcher::model::posts::Posts;
+use crate::reddit_fetcher::model::search_results::{SearchResults, SITE_WIDE};
+use crate::reddit_fetcher::model::subreddit_comments::SubredditComments;
 use crate::reddit_fetcher::model::subreddit_info::SubredditAbout;
 use crate::reddit_fetcher::model::user_info::UserAbout;
 use crate::reddit_fetcher::model::user_posts::UserPosts;
-use crate::reddit_fetcher::reddit::request::params::FeedSorting;
-use crate::reddit_fetcher::reddit::request::{SubredditAboutRequest, UserAboutRequest};
+use crate::reddit_fetcher::reddit::request::params::{FeedSorting, FeedSortingTime};
+use crate::reddit_fetcher::reddit::request::{
+    PostDetailRequest, SubredditAboutRequest, UserAboutRequest,
+};
 use crate::{app_error::AppError, AppState};
 use axum::{
     extract::{Query, State},
@@ -81,6 +87,12 @@ pub async fn lorem(Query(params): Query<AnyParams>) -> Result<Json<Value>, AppEr
     .into())
 }
 
+/// Hits and misses of the Reddit response cache.
+#[utoipa::path(get, path = "/api/debug/cache-stats", responses(), params())]
+pub async fn cache_stats(State(state): State<AppState>) -> Result<Json<Value>, AppError> {
+    Ok(Json(json!(state.fetcher.cache_stats())))
+}
+
 #[utoipa::path(get, path = "/api/debug/subreddit_about", responses(), params())]
 pub async fn subreddit_about(
     State(mut state): State<AppState>,
@@ -92,6 +104,9 @@ pub async fn subreddit_about(
     let req = SubredditAboutRequest {
         subreddit: subreddit.to_string(),
     };
+    state
+        .fetcher
+        .bypass_cache(params.get("bypass_cache").is_some_and(|b| b == "true"));
     let about = state
         .fetcher
         .fetch_about::<SubredditAbout>(req)
@@ -115,6 +130,9 @@ pub async fn post_comments(
         }],
         size: RequestSize::Custom(10),
         sorting: FeedSorting::New,
+        since: None,
+        search: None,
+        user_filter: Default::default(),
     };
     let requests_to_make = u16::from(request.size.clone());
 
@@ -141,6 +159,31 @@ pub async fn post_comments(
     Ok(Json(data))
 }
 
+#[utoipa::path(get, path = "/api/debug/post_detail", responses(), params())]
+pub async fn post_detail(
+    State(mut state): State<AppState>,
+    Query(params): Query<AnyParams>,
+) -> Result<Json<PostDetail>, AppError> {
+    let request = PostDetailRequest {
+        subreddit: "interesting".to_string(),
+        post_id: "1g7e1g6".to_string(),
+        sorting: FeedSorting::Top(FeedSortingTime::All),
+        depth: params.get("depth").and_then(|d| d.parse().ok()),
+        limit: params.get("limit").and_then(|l| l.parse().ok()),
+        comment: params.get("comment").cloned(),
+    };
+
+    let data = state.fetcher.fetch_post_detail(request).await?;
+
+    info!(
+        "Returning post {} with {} top-level comments",
+        data.post.name(),
+        data.comments.len()
+    );
+
+    Ok(Json(data))
+}
+
 #[utoipa::path(get, path = "/api/debug/user_info", responses(), params())]
 pub async fn user_about(
     State(mut state): State<AppState>,
@@ -168,6 +211,9 @@ pub async fn subreddit_posts(State(mut state): State<AppState>) -> Result<Json<P
         }],
         size: RequestSize::Custom(30),
         sorting: FeedSorting::New,
+        since: None,
+        search: None,
+        user_filter: Default::default(),
     };
 
     let (data, _) = state.fetcher.fetch_feed::<Posts>(request).await.unwrap();
@@ -177,6 +223,35 @@ pub async fn subreddit_posts(State(mut state): State<AppState>) -> Result<Json<P
     Ok(Json(data))
 }
 
+#[utoipa::path(get, path = "/api/debug/subreddit_comments", responses(), params())]
+pub async fn subreddit_comments(
+    State(mut state): State<AppState>,
+) -> Result<Json<SubredditComments>, AppError> {
+    let request = FetcherFeedRequest {
+        resource_kind: RedditFeedKind::SubredditComments,
+        report_types: vec![RMoodsReportType::Sentiment],
+        data_sources: vec![DataSource {
+            name: "Polska".to_string(),
+            post_id: None,
+            share: 1.0,
+        }],
+        size: RequestSize::Custom(5),
+        sorting: FeedSorting::New,
+        since: None,
+        search: None,
+        user_filter: Default::default(),
+    };
+
+    let (data, _) = state
+        .fetcher
+        .fetch_feed::<SubredditComments>(request)
+        .await?;
+
+    debug!("Returning {} subreddit comments", data.list.len());
+
+    Ok(Json(data))
+}
+
 #[utoipa::path(get, path = "/api/debug/user_posts", responses(), params())]
 pub async fn user_posts(State(mut state): State<AppState>) -> Result<Json<UserPosts>, AppError> {
     let request = FetcherFeedRequest {
@@ -189,6 +264,9 @@ pub async fn user_posts(State(mut state): State<AppState>) -> Result<Json<UserPo
         }],
         size: RequestSize::Custom(10),
         sorting: Default::default(),
+        since: None,
+        search: None,
+        user_filter: Default::default(),
     };
 
     let (data, _) = state
@@ -202,3 +280,60 @@ pub async fn user_posts(State(mut state): State<AppState>) -> Result<Json<UserPo
 
     Ok(Json(data))
 }
+
+#[utoipa::path(get, path = "/api/debug/search", responses(), params())]
+pub async fn search(
+    State(mut state): State<AppState>,
+    Query(params): Query<AnyParams>,
+) -> Result<Json<SearchResults>, AppError> {
+    let query = params
+        .get("q")
+        .ok_or_else(|| AppError::new(StatusCode::BAD_REQUEST, "Missing `q` parameter"))?;
+    let request = FetcherFeedRequest {
+        resource_kind: RedditFeedKind::Search,
+        report_types: vec![RMoodsReportType::Sentiment],
+        data_sources: vec![DataSource {
+            name: params
+                .get("r")
+                .cloned()
+                .unwrap_or_else(|| SITE_WIDE.to_string()),
+            post_id: None,
+            share: 1.0,
+        }],
+        size: RequestSize::Custom(3),
+        sorting: Default::default(),
+        since: None,
+        search: Some(SearchQuery {
+            query: query.to_string(),
+            ..Default::default()
+        }),
+        user_filter: Default::default(),
+    };
+
+    let (data, _) = state.fetcher.fetch_feed::<SearchResults>(request).await?;
+
+    debug!("Returning {} search results", data.posts.len());
+
+    Ok(Json(data))
+}
+
+#[utoipa::path(get, path = "/api/debug/info", responses(), params())]
+pub async fn info(
+    State(mut state): State<AppState>,
+    Query(params): Query<AnyParams>,
+) -> Result<Json<ItemsInfo>, AppError> {
+    let ids = params
+        .get("ids")
+        .ok_or_else(|| AppError::new(StatusCode::BAD_REQUEST, "Missing `ids` parameter"))?;
+    let fullnames = ids.split(',').map(|id| id.to_string()).collect::<Vec<_>>();
+
+    let data = state.fetcher.fetch_info(&fullnames).await?;
+
+    debug!(
+        "Returning {} posts and {} comments",
+        data.posts.len(),
+        data.comments.len()
+    );
+
+    Ok(Json(data))
+}
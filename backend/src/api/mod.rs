@@ -1,10 +1,17 @@
 use crate::AppState;
-use axum::{routing::get, Router};
+use axum::{
+    routing::{delete, get, post},
+    Router,
+};
 use std::collections::HashMap;
 
 pub mod auth;
+pub mod compare;
+pub mod corpus;
 pub mod debug;
+pub mod monitor;
 pub mod report;
+pub mod source_group;
 
 /// A hashmap of any type of query parameters.
 type AnyParams = HashMap<String, String>;
@@ -14,11 +21,16 @@ pub fn router() -> Router<AppState> {
     Router::<AppState>::new()
         .route("/debug/timeout", get(debug::timeout))
         .route("/debug/lorem", get(debug::lorem))
+        .route("/debug/cache-stats", get(debug::cache_stats))
         .route("/debug/subreddit-about", get(debug::subreddit_about))
         .route("/debug/post-comments", get(debug::post_comments))
+        .route("/debug/post-detail", get(debug::post_detail))
         .route("/debug/user-about", get(debug::user_about))
         .route("/debug/subreddit-posts", get(debug::subreddit_posts))
+        .route("/debug/subreddit-comments", get(debug::subreddit_comments))
         .route("/debug/user-posts", get(debug::user_posts))
+        .route("/debug/search", get(debug::search))
+        .route("/debug/info", get(debug::info))
         .route("/report/sentiment", get(report::sentiment))
         .route("/report/language", get(report::language))
         .route("/report/sarcasm", get(report::sarcasm))
@@ -28,4 +40,39 @@ pub fn router() -> Router<AppState> {
         .route("/report/hate-speech", get(report::hate_speech))
         .route("/report/clickbait", get(report::clickbait))
         .route("/report/troll", get(report::troll))
+        .route("/report/user/:name", get(report::user))
+        .route("/report/export", post(report::export))
+        .route("/compare", post(compare::compare))
+        .route("/corpus/r/:subreddit/posts", get(corpus::corpus_posts))
+        .route(
+            "/corpus/r/:subreddit/comments",
+            get(corpus::corpus_comments),
+        )
+        .route("/corpus/item/:fullname/scores", get(corpus::score_history))
+        .route(
+            "/monitor",
+            get(monitor::list_monitors).post(monitor::create_monitor),
+        )
+        .route("/monitor/:id", delete(monitor::delete_monitor))
+        .route("/monitor/:id/series", get(monitor::monitor_series))
+        .route(
+            "/monitor/:id/alerts",
+            get(monitor::list_alert_rules).post(monitor::create_alert_rule),
+        )
+        .route(
+            "/monitor/:id/alerts/:rule_id",
+            delete(monitor::delete_alert_rule),
+        )
+        .route(
+            "/monitor/:id/alerts/:rule_id/deliveries",
+            get(monitor::alert_deliveries),
+        )
+        .route(
+            "/source-group",
+            get(source_group::list_source_groups).post(source_group::create_source_group),
+        )
+        .route(
+            "/source-group/:name",
+            delete(source_group::delete_source_group),
+        )
 }
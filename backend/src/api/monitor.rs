@@ -0,0 +1,265 @@
+use crate::api::auth::google::GoogleUserInfo;
+use crate::monitor::alert::{AlertCondition, AlertMetric, AlertRule};
+use crate::monitor::error::MonitorError;
+use crate::monitor::store::{self, AlertDelivery, Monitor, Schedule, Snapshot};
+use crate::monitor::webhook;
+use crate::reddit_fetcher::feed_request::{DataSource, RMoodsReportType};
+use crate::reddit_fetcher::model::subreddit_info::SubredditAbout;
+use crate::reddit_fetcher::reddit::request::SubredditAboutRequest;
+use crate::source_group;
+use crate::{app_error::AppError, AppState};
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use log_derive::logfn;
+use rand::{distributions::Alphanumeric, Rng};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Debug)]
+pub struct MonitorPayload {
+    /// Subreddit name without `r/`, eg. Polska, or a source group of the user, eg. g/polish-news
+    subreddit: String,
+    schedule: Schedule,
+    report_types: Vec<RMoodsReportType>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SeriesParams {
+    /// Only return snapshots taken at or after this time
+    from: Option<DateTime<Utc>>,
+    /// Only return snapshots taken at or before this time
+    to: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SeriesResponse {
+    monitor: Monitor,
+    series: Vec<Snapshot>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct AlertRulePayload {
+    metric: AlertMetric,
+    condition: AlertCondition,
+    /// Fired alerts are POSTed here, signed with the rule's secret
+    webhook_url: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct CreatedAlertRule {
+    #[serde(flatten)]
+    rule: AlertRule,
+    /// Key of the HMAC-SHA256 signature sent with every webhook payload.
+    /// Returned only once, when the rule is created.
+    secret: String,
+}
+
+/// Length of the generated webhook secrets.
+const SECRET_LENGTH: usize = 32;
+
+/// Get the monitor of the user, or fail with 404.
+async fn owned_monitor(
+    state: &AppState,
+    user_info: &GoogleUserInfo,
+    id: i64,
+) -> Result<Monitor, MonitorError> {
+    store::get_monitor(&state.pool, user_info.sub(), id)
+        .await?
+        .ok_or(MonitorError::MonitorNotFound(id))
+}
+
+/// Register a subreddit to be snapshotted on a schedule.
+#[utoipa::path(post, path = "/api/monitor", responses(), params())]
+#[logfn(err = "ERROR", fmt = "'create_monitor' failed: {:?}")]
+pub async fn create_monitor(
+    State(mut state): State<AppState>,
+    user_info: GoogleUserInfo,
+    Json(body): Json<MonitorPayload>,
+) -> Result<Json<Monitor>, AppError> {
+    let subreddit = if source_group::group_name(&body.subreddit).is_some() {
+        // The group is resolved once, later changes to it don't affect the monitor
+        let source = DataSource {
+            name: body.subreddit.clone(),
+            post_id: None,
+            share: 1.0,
+        };
+        source_group::resolve_source(&state.pool, Some(user_info.sub()), source)
+            .await?
+            .name
+    } else {
+        // Make sure the subreddit exists before we start snapshotting it
+        let req = SubredditAboutRequest {
+            subreddit: body.subreddit.clone(),
+        };
+        state.fetcher.fetch_about::<SubredditAbout>(req).await?;
+        body.subreddit
+    };
+
+    let monitor = store::insert_monitor(
+        &state.pool,
+        user_info.sub(),
+        &subreddit,
+        body.schedule,
+        &body.report_types,
+    )
+    .await
+    .map_err(MonitorError::from)?;
+
+    Ok(Json(monitor))
+}
+
+/// List the monitors registered by the user.
+#[utoipa::path(get, path = "/api/monitor", responses(), params())]
+#[logfn(err = "ERROR", fmt = "'list_monitors' failed: {:?}")]
+pub async fn list_monitors(
+    State(state): State<AppState>,
+    user_info: GoogleUserInfo,
+) -> Result<Json<Vec<Monitor>>, AppError> {
+    let monitors = store::list_monitors(&state.pool, user_info.sub())
+        .await
+        .map_err(MonitorError::from)?;
+    Ok(Json(monitors))
+}
+
+/// Stop monitoring a subreddit and delete its snapshots.
+#[utoipa::path(delete, path = "/api/monitor/{id}", responses(), params())]
+#[logfn(err = "ERROR", fmt = "'delete_monitor' failed: {:?}")]
+pub async fn delete_monitor(
+    State(state): State<AppState>,
+    user_info: GoogleUserInfo,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, AppError> {
+    let deleted = store::delete_monitor(&state.pool, user_info.sub(), id)
+        .await
+        .map_err(MonitorError::from)?;
+
+    if !deleted {
+        return Err(MonitorError::MonitorNotFound(id).into());
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Get the time series of sentiment, toxicity and activity of a monitored subreddit.
+#[utoipa::path(get, path = "/api/monitor/{id}/series", responses(), params())]
+#[logfn(err = "ERROR", fmt = "'monitor_series' failed: {:?}")]
+pub async fn monitor_series(
+    State(state): State<AppState>,
+    user_info: GoogleUserInfo,
+    Path(id): Path<i64>,
+    Query(params): Query<SeriesParams>,
+) -> Result<Json<SeriesResponse>, AppError> {
+    let monitor = owned_monitor(&state, &user_info, id).await?;
+
+    let series = store::snapshots(&state.pool, id, params.from, params.to)
+        .await
+        .map_err(MonitorError::from)?;
+
+    Ok(Json(SeriesResponse { monitor, series }))
+}
+
+/// Add an alert rule to a monitored subreddit.
+///
+/// The rule is evaluated after every snapshot. When its condition starts holding, the alert is
+/// pushed to the user's WebSocket connections and POSTed to the webhook.
+#[utoipa::path(post, path = "/api/monitor/{id}/alerts", responses(), params())]
+#[logfn(err = "ERROR", fmt = "'create_alert_rule' failed: {:?}")]
+pub async fn create_alert_rule(
+    State(state): State<AppState>,
+    user_info: GoogleUserInfo,
+    Path(id): Path<i64>,
+    Json(body): Json<AlertRulePayload>,
+) -> Result<Json<CreatedAlertRule>, AppError> {
+    let monitor = owned_monitor(&state, &user_info, id).await?;
+
+    body.condition.validate()?;
+    let webhook_url = webhook::check_url(&body.webhook_url)?;
+    webhook::check_host(&webhook_url).await?;
+
+    let secret = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(SECRET_LENGTH)
+        .map(char::from)
+        .collect::<String>();
+
+    let rule = store::insert_alert_rule(
+        &state.pool,
+        monitor.id,
+        body.metric,
+        &body.condition,
+        webhook_url.as_str(),
+        &secret,
+    )
+    .await
+    .map_err(MonitorError::from)?;
+
+    Ok(Json(CreatedAlertRule { rule, secret }))
+}
+
+/// List the alert rules of a monitored subreddit.
+#[utoipa::path(get, path = "/api/monitor/{id}/alerts", responses(), params())]
+#[logfn(err = "ERROR", fmt = "'list_alert_rules' failed: {:?}")]
+pub async fn list_alert_rules(
+    State(state): State<AppState>,
+    user_info: GoogleUserInfo,
+    Path(id): Path<i64>,
+) -> Result<Json<Vec<AlertRule>>, AppError> {
+    let monitor = owned_monitor(&state, &user_info, id).await?;
+    let rules = store::alert_rules(&state.pool, monitor.id)
+        .await
+        .map_err(MonitorError::from)?;
+    Ok(Json(rules))
+}
+
+/// Delete an alert rule and its delivery log.
+#[utoipa::path(
+    delete,
+    path = "/api/monitor/{id}/alerts/{rule_id}",
+    responses(),
+    params()
+)]
+#[logfn(err = "ERROR", fmt = "'delete_alert_rule' failed: {:?}")]
+pub async fn delete_alert_rule(
+    State(state): State<AppState>,
+    user_info: GoogleUserInfo,
+    Path((id, rule_id)): Path<(i64, i64)>,
+) -> Result<StatusCode, AppError> {
+    let monitor = owned_monitor(&state, &user_info, id).await?;
+    let deleted = store::delete_alert_rule(&state.pool, monitor.id, rule_id)
+        .await
+        .map_err(MonitorError::from)?;
+
+    if !deleted {
+        return Err(MonitorError::AlertRuleNotFound(rule_id).into());
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Get the delivery log of an alert rule's webhook, newest attempt first.
+#[utoipa::path(
+    get,
+    path = "/api/monitor/{id}/alerts/{rule_id}/deliveries",
+    responses(),
+    params()
+)]
+#[logfn(err = "ERROR", fmt = "'alert_deliveries' failed: {:?}")]
+pub async fn alert_deliveries(
+    State(state): State<AppState>,
+    user_info: GoogleUserInfo,
+    Path((id, rule_id)): Path<(i64, i64)>,
+) -> Result<Json<Vec<AlertDelivery>>, AppError> {
+    let monitor = owned_monitor(&state, &user_info, id).await?;
+    let rules = store::alert_rules(&state.pool, monitor.id)
+        .await
+        .map_err(MonitorError::from)?;
+    if !rules.iter().any(|r| r.id == rule_id) {
+        return Err(MonitorError::AlertRuleNotFound(rule_id).into());
+    }
+
+    let deliveries = store::alert_deliveries(&state.pool, rule_id)
+        .await
+        .map_err(MonitorError::from)?;
+    Ok(Json(deliveries))
+}
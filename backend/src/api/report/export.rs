@@ -0,0 +1,245 @@
+use crate::api::auth::google::GoogleUserInfo;
+use crate::reddit_fetcher::feed_request::{
+    DataSource, FetcherFeedRequest, RMoodsReportType, RedditFeedKind, RequestSize,
+};
+use crate::reddit_fetcher::fetcher::RMoodsFetcher;
+use crate::reddit_fetcher::model::posts::Posts;
+use crate::reddit_fetcher::reddit::request::params::FeedSorting;
+use crate::report::export::{ExportColumn, ExportFormat, Exporter};
+use crate::report::nlp::NlpClient;
+use crate::report::{Report, ReportItem};
+use crate::source_group;
+use crate::{app_error::AppError, telemetry, AppState};
+use axum::body::Bytes;
+use axum::{
+    body::Body,
+    extract::State,
+    response::{IntoResponse, Response},
+    Json,
+};
+use futures::{StreamExt, TryStreamExt};
+use http::header::{CONTENT_DISPOSITION, CONTENT_TYPE};
+use http::StatusCode;
+use log::error;
+use log_derive::logfn;
+use serde::Deserialize;
+use tracing::instrument;
+
+/// Reddit requests spent on the items by default.
+const DEFAULT_REQUESTS: u16 = 5;
+/// Upper limit of the Reddit requests spent on the items.
+const MAX_REQUESTS: u16 = 50;
+
+/// Fetches, analyzes and encodes the posts page by page, so the whole report is never held in memory.
+struct ExportJob {
+    fetcher: RMoodsFetcher,
+    nlp: NlpClient,
+    report_types: Vec<RMoodsReportType>,
+    /// `None` once the end of the file is encoded
+    exporter: Option<Exporter>,
+    /// Sources left to fetch, each with its share of the request budget
+    sources: std::vec::IntoIter<FetcherFeedRequest>,
+    /// The source being fetched, the cursor of its next page and the requests left in its share
+    current: Option<(FetcherFeedRequest, Option<String>, u16)>,
+}
+
+impl ExportJob {
+    fn new(
+        fetcher: RMoodsFetcher,
+        nlp: NlpClient,
+        request: &FetcherFeedRequest,
+        exporter: Exporter,
+    ) -> Self {
+        Self {
+            fetcher,
+            nlp,
+            report_types: request.report_types.clone(),
+            exporter: Some(exporter),
+            sources: request.per_source().into_iter(),
+            current: None,
+        }
+    }
+
+    /// Encode the next page of posts, `None` once the whole file is encoded.
+    async fn next_chunk(&mut self) -> Result<Option<Bytes>, AppError> {
+        loop {
+            let next_source = || {
+                self.sources.next().map(|request| {
+                    let requests = request.size.clone().into();
+                    (request, None, requests)
+                })
+            };
+            let Some((request, after, requests)) = self.current.take().or_else(next_source) else {
+                return match self.exporter.take() {
+                    Some(exporter) => Ok(Some(exporter.finish()?)),
+                    None => Ok(None),
+                };
+            };
+
+            let (posts, after) = self
+                .fetcher
+                .fetch_feed_page::<Posts>(&request, after)
+                .await?;
+            if requests > 1 && after.is_some() {
+                self.current = Some((request, after, requests - 1));
+            }
+
+            let items = posts.list.iter().map(ReportItem::from).collect::<Vec<_>>();
+            let report = Report::generate(&self.nlp, items, &self.report_types).await?;
+            let exporter = self
+                .exporter
+                .as_mut()
+                .expect("Encoded after the end of the file");
+            let bytes = exporter.encode(&report.items)?;
+            // Pages without any visible posts have nothing to send
+            if !bytes.is_empty() {
+                return Ok(Some(bytes));
+            }
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ExportPayload {
+    /// Subreddit names without `r/`, eg. Polska, or source groups of the user, eg. g/polish-news
+    subreddits: Vec<String>,
+    report_types: Vec<RMoodsReportType>,
+    #[serde(default)]
+    format: ExportFormat,
+    /// Columns in order, by default the item's fields and the output of every requested report
+    columns: Option<Vec<ExportColumn>>,
+    /// Reddit requests spent on the items, split evenly between the subreddits, at least one each
+    requests: Option<u16>,
+    #[serde(default)]
+    sorting: FeedSorting,
+    /// Fetch fresh data from Reddit even if there's a cached response
+    #[serde(default)]
+    bypass_cache: bool,
+}
+
+/// Download the analyzed posts of a report, one row per post, as CSV, NDJSON or Parquet.
+///
+/// The file is streamed: every page of posts is analyzed and encoded before the next one is fetched.
+#[utoipa::path(post, path = "/api/report/export", responses(), params())]
+#[logfn(err = "ERROR", fmt = "'export' failed: {:?}")]
+#[instrument(name = "report", skip_all, fields(job_id = telemetry::job_id()))]
+pub async fn export(
+    State(mut state): State<AppState>,
+    user_info: Option<GoogleUserInfo>,
+    Json(body): Json<ExportPayload>,
+) -> Result<Response, AppError> {
+    if body.subreddits.is_empty() {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            "Select at least one subreddit",
+        ));
+    }
+    let columns = body
+        .columns
+        .unwrap_or_else(|| ExportColumn::defaults(&body.report_types));
+    let exporter = Exporter::new(body.format, columns)?;
+
+    state.fetcher.bypass_cache(body.bypass_cache);
+
+    let share = 1.0 / body.subreddits.len() as f32;
+    let mut data_sources = vec![];
+    for name in &body.subreddits {
+        let source = DataSource {
+            name: name.clone(),
+            post_id: None,
+            share,
+        };
+        let owner = user_info.as_ref().map(|u| u.sub().as_str());
+        data_sources.push(source_group::resolve_source(&state.pool, owner, source).await?);
+    }
+
+    let request = FetcherFeedRequest {
+        resource_kind: RedditFeedKind::SubredditPosts,
+        report_types: body.report_types.clone(),
+        data_sources,
+        size: RequestSize::Custom(
+            body.requests
+                .unwrap_or(DEFAULT_REQUESTS)
+                .clamp(1, MAX_REQUESTS),
+        ),
+        sorting: body.sorting,
+        since: None,
+        search: None,
+        user_filter: Default::default(),
+    };
+    let mut job = ExportJob::new(state.fetcher, state.nlp, &request, exporter);
+
+    // The first page is fetched before the headers are sent, so eg. a missing subreddit is still a 404
+    let first = job.next_chunk().await?;
+    // Headers are sent by now, a failure can only cut the file short
+    let rest = futures::stream::try_unfold(job, |mut job| async move {
+        Ok(job.next_chunk().await?.map(|bytes| (bytes, job)))
+    });
+    let stream = futures::stream::iter(first.map(Ok))
+        .chain(rest)
+        .inspect_err(|e: &AppError| error!("Export failed mid-stream: {e}"));
+    let file_name = format!("report.{}", body.format.extension());
+    Ok((
+        [
+            (CONTENT_TYPE, body.format.content_type().to_string()),
+            (
+                CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{file_name}\""),
+            ),
+        ],
+        Body::from_stream(stream),
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reddit_fetcher::reddit::mock::{fixtures, MockReddit};
+
+    #[tokio::test]
+    async fn test_export_job_fetches_every_source() {
+        let server = MockReddit::new()
+            .route("/r/A/hot.json", fixtures::subreddit_posts())
+            .route("/r/B/hot.json", fixtures::subreddit_posts_page2())
+            .start()
+            .await;
+        let fetcher = RMoodsFetcher::with_connection(server.connection().await);
+        // Not called without report types
+        let nlp = NlpClient::new(reqwest::Client::new(), "http://127.0.0.1:1");
+
+        let source = |name: &str| DataSource {
+            name: name.to_string(),
+            post_id: None,
+            share: 0.5,
+        };
+        let request = FetcherFeedRequest {
+            resource_kind: RedditFeedKind::SubredditPosts,
+            report_types: vec![],
+            data_sources: vec![source("A"), source("B")],
+            size: RequestSize::Custom(2),
+            sorting: FeedSorting::default(),
+            since: None,
+            search: None,
+            user_filter: Default::default(),
+        };
+        let exporter = Exporter::new(ExportFormat::Ndjson, ExportColumn::defaults(&[])).unwrap();
+        let mut job = ExportJob::new(fetcher, nlp, &request, exporter);
+
+        let mut file = vec![];
+        while let Some(chunk) = job.next_chunk().await.unwrap() {
+            file.extend_from_slice(&chunk);
+        }
+        let file = String::from_utf8(file).unwrap();
+
+        // One request per source, the first page of `A` isn't followed
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[0].starts_with("/r/A/hot.json"));
+        assert!(requests[1].starts_with("/r/B/hot.json"));
+        assert!(file.contains("1g7dz2k"));
+        assert!(file.contains("1g7dw8m"));
+        // A row per post of both pages, except the removed one
+        assert_eq!(file.lines().count(), 4);
+    }
+}
@@ -1,6 +1,7 @@
 //! Routes for generating reports
 
 mod clickbait;
+pub mod export;
 mod hate_speech;
 mod keywords;
 mod language;
@@ -9,10 +10,12 @@ mod sarcasm;
 mod sentiment;
 mod spam;
 mod troll;
+pub mod user;
 
 // Re-exporting the functions to the top level
 // avoid having to use the module name to call the functions
 pub use clickbait::clickbait;
+pub use export::export;
 pub use hate_speech::hate_speech;
 pub use keywords::keywords;
 pub use language::language;
@@ -21,3 +24,4 @@ pub use sarcasm::sarcasm;
 pub use sentiment::sentiment;
 pub use spam::spam;
 pub use troll::troll;
+pub use user::user;
@@ -0,0 +1,77 @@
+use crate::reddit_fetcher::feed_request::{
+    DataSource, FetcherFeedRequest, RedditFeedKind, RequestSize, UserContentFilter,
+};
+use crate::reddit_fetcher::model::user_info::UserAbout;
+use crate::reddit_fetcher::model::user_posts::UserPosts;
+use crate::reddit_fetcher::reddit::request::params::FeedSorting;
+use crate::reddit_fetcher::reddit::request::UserAboutRequest;
+use crate::report::user::{UserReport, USER_REPORT_TYPES};
+use crate::report::{Report, ReportItem};
+use crate::{app_error::AppError, telemetry, AppState};
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use chrono::Utc;
+use log_derive::logfn;
+use serde::Deserialize;
+use tracing::instrument;
+
+/// Maximum number of Reddit requests spent on the user's posts and comments.
+const USER_REPORT_BUDGET: u16 = 10;
+
+#[derive(Deserialize, Debug)]
+pub struct UserReportParams {
+    /// Only analyze the user's comments or submitted posts, both by default
+    #[serde(default)]
+    content: UserContentFilter,
+    /// Fetch fresh data from Reddit even if there's a cached response
+    #[serde(default)]
+    bypass_cache: bool,
+}
+
+/// Profile a single Reddit account.
+///
+/// Combines the account's karma and age with the analysis of its latest posts and comments:
+/// sentiment, toxicity, languages, keywords, most active subreddits and posting hours.
+#[utoipa::path(get, path = "/api/report/user/{name}", responses(), params())]
+#[logfn(err = "ERROR", fmt = "'user' report failed: {:?}")]
+#[instrument(name = "report", skip_all, fields(job_id = telemetry::job_id()))]
+pub async fn user(
+    State(mut state): State<AppState>,
+    Path(name): Path<String>,
+    Query(params): Query<UserReportParams>,
+) -> Result<Json<UserReport>, AppError> {
+    state.fetcher.bypass_cache(params.bypass_cache);
+
+    let req = UserAboutRequest {
+        username: name.clone(),
+    };
+    let about = state.fetcher.fetch_about::<UserAbout>(req).await?;
+
+    let request = FetcherFeedRequest {
+        resource_kind: RedditFeedKind::UserPosts,
+        report_types: USER_REPORT_TYPES.to_vec(),
+        data_sources: vec![DataSource {
+            name,
+            post_id: None,
+            share: 1.0,
+        }],
+        size: RequestSize::Custom(USER_REPORT_BUDGET),
+        sorting: FeedSorting::New,
+        since: None,
+        search: None,
+        user_filter: params.content,
+    };
+    let (data, _) = state.fetcher.fetch_feed::<UserPosts>(request).await?;
+
+    let items = data
+        .posts
+        .iter()
+        .map(ReportItem::from)
+        .chain(data.comments.iter().map(ReportItem::from))
+        .collect::<Vec<_>>();
+    let report = Report::generate(&state.nlp, items, &USER_REPORT_TYPES).await?;
+
+    Ok(Json(UserReport::new(about.info(), report, Utc::now())))
+}
@@ -0,0 +1,69 @@
+use crate::api::auth::google::GoogleUserInfo;
+use crate::source_group::error::SourceGroupError;
+use crate::source_group::{self, store, SourceGroup};
+use crate::{app_error::AppError, AppState};
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use log_derive::logfn;
+use reqwest::StatusCode;
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug)]
+pub struct SourceGroupPayload {
+    /// Referenced as `g/<name>`, eg. polish-news
+    name: String,
+    /// Subreddit names without `r/`, eg. ["Polska", "poland"]
+    subreddits: Vec<String>,
+}
+
+/// Define a named group of subreddits.
+///
+/// The group can be used as `g/<name>` wherever a subreddit is accepted.
+#[utoipa::path(post, path = "/api/source-group", responses(), params())]
+#[logfn(err = "ERROR", fmt = "'create_source_group' failed: {:?}")]
+pub async fn create_source_group(
+    State(state): State<AppState>,
+    user_info: GoogleUserInfo,
+    Json(body): Json<SourceGroupPayload>,
+) -> Result<Json<SourceGroup>, AppError> {
+    source_group::validate_group_name(&body.name)?;
+    source_group::validate_subreddits(&body.subreddits)?;
+
+    let group =
+        store::insert_source_group(&state.pool, user_info.sub(), &body.name, &body.subreddits)
+            .await?;
+    Ok(Json(group))
+}
+
+/// List the source groups defined by the user.
+#[utoipa::path(get, path = "/api/source-group", responses(), params())]
+#[logfn(err = "ERROR", fmt = "'list_source_groups' failed: {:?}")]
+pub async fn list_source_groups(
+    State(state): State<AppState>,
+    user_info: GoogleUserInfo,
+) -> Result<Json<Vec<SourceGroup>>, AppError> {
+    let groups = store::list_source_groups(&state.pool, user_info.sub())
+        .await
+        .map_err(SourceGroupError::from)?;
+    Ok(Json(groups))
+}
+
+/// Delete a source group. Monitors created from it keep their subreddits.
+#[utoipa::path(delete, path = "/api/source-group/{name}", responses(), params())]
+#[logfn(err = "ERROR", fmt = "'delete_source_group' failed: {:?}")]
+pub async fn delete_source_group(
+    State(state): State<AppState>,
+    user_info: GoogleUserInfo,
+    Path(name): Path<String>,
+) -> Result<StatusCode, AppError> {
+    let deleted = store::delete_source_group(&state.pool, user_info.sub(), &name)
+        .await
+        .map_err(SourceGroupError::from)?;
+
+    if !deleted {
+        return Err(SourceGroupError::GroupNotFound(name).into());
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
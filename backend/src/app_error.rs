@@ -4,10 +4,16 @@ use reqwest::StatusCode;
 use serde_json::json;
 
 use crate::api::auth::error::AuthError;
+use crate::corpus::error::CorpusError;
+use crate::monitor::error::MonitorError;
+use crate::reddit_fetcher::fetcher_error::FetcherError;
 use crate::reddit_fetcher::reddit::error::RedditError;
+use crate::report::error::{ExportError, ReportError};
+use crate::source_group::error::SourceGroupError;
 
 /// Public-facing error kind. Contains an HTTP status code and a message describing the error.
-#[derive(Debug, Getters)]
+#[derive(Debug, Getters, thiserror::Error)]
+#[error("{message} ({code})")]
 pub struct AppError {
     code: StatusCode,
     message: String,
@@ -51,6 +57,71 @@ impl From<RedditError> for AppError {
     }
 }
 
+impl From<FetcherError> for AppError {
+    fn from(value: FetcherError) -> Self {
+        match value {
+            FetcherError::RedditApiError(e) => e.into(),
+            _ => AppError::internal_server_error(),
+        }
+    }
+}
+
+impl From<MonitorError> for AppError {
+    fn from(value: MonitorError) -> Self {
+        match value {
+            MonitorError::MonitorNotFound(_) | MonitorError::AlertRuleNotFound(_) => {
+                AppError::new(StatusCode::NOT_FOUND, value.to_string())
+            }
+            MonitorError::InvalidAlertRule(_) => {
+                AppError::new(StatusCode::BAD_REQUEST, value.to_string())
+            }
+            MonitorError::FetcherError(e) => e.into(),
+            _ => AppError::internal_server_error(),
+        }
+    }
+}
+
+impl From<SourceGroupError> for AppError {
+    fn from(value: SourceGroupError) -> Self {
+        match value {
+            SourceGroupError::GroupNotFound(_) => {
+                AppError::new(StatusCode::NOT_FOUND, value.to_string())
+            }
+            SourceGroupError::GroupExists(_) => {
+                AppError::new(StatusCode::CONFLICT, value.to_string())
+            }
+            SourceGroupError::InvalidGroup(_) => {
+                AppError::new(StatusCode::BAD_REQUEST, value.to_string())
+            }
+            _ => AppError::internal_server_error(),
+        }
+    }
+}
+
+impl From<CorpusError> for AppError {
+    fn from(_: CorpusError) -> Self {
+        AppError::internal_server_error()
+    }
+}
+
+impl From<ReportError> for AppError {
+    fn from(_: ReportError) -> Self {
+        AppError::new(
+            StatusCode::BAD_GATEWAY,
+            "The NLP service failed to analyze the data",
+        )
+    }
+}
+
+impl From<ExportError> for AppError {
+    fn from(value: ExportError) -> Self {
+        match value {
+            ExportError::NoColumns => AppError::new(StatusCode::BAD_REQUEST, value.to_string()),
+            _ => AppError::internal_server_error(),
+        }
+    }
+}
+
 impl From<AuthError> for AppError {
     fn from(value: AuthError) -> Self {
         type E = jsonwebtoken::errors::ErrorKind;
@@ -91,4 +162,11 @@ mod tests {
         assert_eq!(app_error.code, StatusCode::NOT_FOUND);
         assert_eq!(app_error.message, "Resource not found: 'r/Polska'");
     }
+
+    #[test]
+    fn test_app_error_from_monitor_error_not_found() {
+        let app_error: AppError = MonitorError::MonitorNotFound(7).into();
+        assert_eq!(app_error.code, StatusCode::NOT_FOUND);
+        assert_eq!(app_error.message, "Monitor not found: 7");
+    }
 }
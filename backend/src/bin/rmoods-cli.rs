@@ -0,0 +1,299 @@
+//! Runs a report from the terminal, without the server, Google auth or Postgres.
+//!
+//! ```sh
+//! cargo run --bin rmoods-cli -- r/Polska --report sentiment --report hate-speech --size 5 --sorting top-week
+//! cargo run --bin rmoods-cli -- u/spez --format csv --output spez.csv
+//! ```
+//! The Reddit and NLP settings are read from the config file and the environment, like the server does.
+
+use anyhow::Context;
+use clap::{Parser, ValueEnum};
+use log::info;
+use rmoods_backend::config::{ConfigSource, NlpConfig, RedditConfig};
+use rmoods_backend::reddit_fetcher::feed_request::{
+    DataSource, FetcherFeedRequest, RMoodsReportType, RedditFeedKind, RequestSize,
+};
+use rmoods_backend::reddit_fetcher::fetcher::RMoodsFetcher;
+use rmoods_backend::reddit_fetcher::model::posts::Posts;
+use rmoods_backend::reddit_fetcher::model::user_posts::UserPosts;
+use rmoods_backend::reddit_fetcher::reddit::request::params::{FeedSorting, FeedSortingTime};
+use rmoods_backend::report::export::{ExportColumn, ExportFormat, Exporter, EXPORT_CHUNK_ROWS};
+use rmoods_backend::report::nlp::NlpClient;
+use rmoods_backend::report::{Report, ReportItem};
+use serde::de::DeserializeOwned;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+/// Where the items of a report come from.
+#[derive(Debug, Clone, PartialEq)]
+enum Source {
+    /// Posts of a subreddit
+    Subreddit(String),
+    /// Posts and comments of a user
+    User(String),
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum OutputFormat {
+    /// The whole report: the analyzed items and their summary
+    Json,
+    Csv,
+    Ndjson,
+    Parquet,
+}
+
+#[derive(Parser, Debug)]
+#[command(about = "Run an RMoods report from the terminal")]
+struct Args {
+    /// r/{subreddit} or u/{user}, a bare name is a subreddit
+    #[arg(value_parser = parse_source)]
+    source: Source,
+    /// Reports to run, eg. sentiment or hate-speech
+    #[arg(short, long = "report", value_parser = parse_report_type, default_value = "sentiment")]
+    reports: Vec<RMoodsReportType>,
+    /// Reddit requests to spend: small, medium, large or a number
+    #[arg(long, value_parser = parse_size, default_value = "medium")]
+    size: RequestSize,
+    /// hot, new, rising, or top and controversial with a period, eg. top-week or controversial-all
+    #[arg(long, value_parser = parse_sorting, default_value = "hot")]
+    sorting: FeedSorting,
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Json)]
+    format: OutputFormat,
+    /// Columns of CSV, NDJSON and Parquet files, comma-separated, eg. id,score,sentiment
+    #[arg(long, value_delimiter = ',', value_parser = parse_column)]
+    columns: Option<Vec<ExportColumn>>,
+    /// Write to this file instead of stdout
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+fn parse_source(source: &str) -> Result<Source, String> {
+    let source = source.trim_start_matches('/');
+    let parsed = match source.split_once('/') {
+        Some(("r", name)) => Source::Subreddit(name.to_string()),
+        Some(("u" | "user", name)) => Source::User(name.to_string()),
+        None => Source::Subreddit(source.to_string()),
+        Some(_) => {
+            return Err(format!(
+                "expected r/{{subreddit}} or u/{{user}}, got {source}"
+            ))
+        }
+    };
+    match &parsed {
+        Source::Subreddit(name) | Source::User(name) if name.is_empty() => {
+            Err("the name is empty".to_string())
+        }
+        _ => Ok(parsed),
+    }
+}
+
+/// Parse a value by its serialized name, dashes standing for underscores.
+fn from_snake_case<T: DeserializeOwned>(value: &str) -> Result<T, String> {
+    serde_json::from_value(value.replace('-', "_").into()).map_err(|e| e.to_string())
+}
+
+fn parse_report_type(report_type: &str) -> Result<RMoodsReportType, String> {
+    from_snake_case(report_type)
+}
+
+fn parse_column(column: &str) -> Result<ExportColumn, String> {
+    from_snake_case(column)
+}
+
+fn parse_size(size: &str) -> Result<RequestSize, String> {
+    match size {
+        "small" => Ok(RequestSize::Small),
+        "medium" => Ok(RequestSize::Medium),
+        "large" => Ok(RequestSize::Large),
+        n => n
+            .parse()
+            .map(RequestSize::Custom)
+            .map_err(|_| format!("expected small, medium, large or a number, got {n}")),
+    }
+}
+
+fn parse_sorting(sorting: &str) -> Result<FeedSorting, String> {
+    let time = |time: &str| match time {
+        "hour" => Ok(FeedSortingTime::Hour),
+        "day" => Ok(FeedSortingTime::Day),
+        "week" => Ok(FeedSortingTime::Week),
+        "month" => Ok(FeedSortingTime::Month),
+        "year" => Ok(FeedSortingTime::Year),
+        "all" => Ok(FeedSortingTime::All),
+        _ => Err(format!(
+            "unknown period {time}, expected hour, day, week, month, year or all"
+        )),
+    };
+    match sorting.split_once('-') {
+        None if sorting == "hot" => Ok(FeedSorting::Hot),
+        None if sorting == "new" => Ok(FeedSorting::New),
+        None if sorting == "rising" => Ok(FeedSorting::Rising),
+        Some(("top", period)) => time(period).map(FeedSorting::Top),
+        Some(("controversial", period)) => time(period).map(FeedSorting::Controversial),
+        _ => Err(format!("unknown sorting {sorting}")),
+    }
+}
+
+/// Fetch the items of the source and analyze them.
+async fn generate(args: &Args) -> anyhow::Result<Report> {
+    let source = ConfigSource::load()?;
+    let reddit = source.section::<RedditConfig>()?;
+    let nlp = source.section::<NlpConfig>()?;
+
+    let http = reqwest::ClientBuilder::new().user_agent("RMoods").build()?;
+    let mut fetcher = RMoodsFetcher::new(http.clone(), &reddit).await?;
+    let nlp = NlpClient::new(http, nlp.url);
+
+    let (resource_kind, name) = match &args.source {
+        Source::Subreddit(name) => (RedditFeedKind::SubredditPosts, name),
+        Source::User(name) => (RedditFeedKind::UserPosts, name),
+    };
+    let request = FetcherFeedRequest {
+        resource_kind,
+        report_types: args.reports.clone(),
+        data_sources: vec![DataSource {
+            name: name.clone(),
+            post_id: None,
+            share: 1.0,
+        }],
+        size: args.size.clone(),
+        sorting: args.sorting,
+        since: None,
+        search: None,
+        user_filter: Default::default(),
+    };
+
+    let items = match args.source {
+        Source::Subreddit(_) => {
+            let (posts, _) = fetcher.fetch_feed::<Posts>(request).await?;
+            posts.list.iter().map(ReportItem::from).collect::<Vec<_>>()
+        }
+        Source::User(_) => {
+            let (data, _) = fetcher.fetch_feed::<UserPosts>(request).await?;
+            data.posts
+                .iter()
+                .map(ReportItem::from)
+                .chain(data.comments.iter().map(ReportItem::from))
+                .collect()
+        }
+    };
+    info!("Fetched {} items from {:?}", items.len(), args.source);
+
+    Ok(Report::generate(&nlp, items, &args.reports).await?)
+}
+
+/// Write the report in the requested format.
+fn write(report: Report, args: &Args, out: &mut impl Write) -> anyhow::Result<()> {
+    let format = match args.format {
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(&mut *out, &report)?;
+            writeln!(out)?;
+            return Ok(());
+        }
+        OutputFormat::Csv => ExportFormat::Csv,
+        OutputFormat::Ndjson => ExportFormat::Ndjson,
+        OutputFormat::Parquet => ExportFormat::Parquet,
+    };
+    let columns = args
+        .columns
+        .clone()
+        .unwrap_or_else(|| ExportColumn::defaults(&report.report_types));
+
+    let mut exporter = Exporter::new(format, columns)?;
+    for chunk in report.items.chunks(EXPORT_CHUNK_ROWS) {
+        out.write_all(&exporter.encode(chunk)?)?;
+    }
+    out.write_all(&exporter.finish()?)?;
+    Ok(())
+}
+
+async fn run(args: Args) -> anyhow::Result<()> {
+    let report = generate(&args).await?;
+
+    match &args.output {
+        Some(path) => {
+            let file =
+                File::create(path).with_context(|| format!("Can't create {}", path.display()))?;
+            let mut out = BufWriter::new(file);
+            write(report, &args, &mut out)?;
+            out.flush()?;
+        }
+        None => {
+            let mut out = BufWriter::new(std::io::stdout().lock());
+            write(report, &args, &mut out)?;
+            out.flush()?;
+        }
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() {
+    // Logs go to stderr, so they don't mix with a report written to stdout
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
+    // Cron jobs usually set the environment themselves, a missing .env is not worth a warning
+    dotenvy::dotenv().ok();
+
+    if let Err(e) = run(Args::parse()).await {
+        eprintln!("Error: {e:#}");
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_parse_source() {
+        assert_eq!(
+            parse_source("r/Polska"),
+            Ok(Source::Subreddit("Polska".to_string()))
+        );
+        assert_eq!(
+            parse_source("/u/spez"),
+            Ok(Source::User("spez".to_string()))
+        );
+        assert_eq!(
+            parse_source("Polska"),
+            Ok(Source::Subreddit("Polska".to_string()))
+        );
+        assert!(parse_source("x/Polska").is_err());
+        assert!(parse_source("r/").is_err());
+    }
+
+    #[test]
+    fn test_parse_sorting_and_size() {
+        assert!(matches!(
+            parse_sorting("top-week"),
+            Ok(FeedSorting::Top(FeedSortingTime::Week))
+        ));
+        assert!(matches!(parse_sorting("new"), Ok(FeedSorting::New)));
+        assert!(parse_sorting("top").is_err());
+        assert!(matches!(parse_size("12"), Ok(RequestSize::Custom(12))));
+        assert!(parse_size("huge").is_err());
+    }
+
+    #[test]
+    fn test_parse_report_types_and_columns() {
+        assert_eq!(
+            parse_report_type("hate-speech"),
+            Ok(RMoodsReportType::HateSpeech)
+        );
+        assert_eq!(parse_column("created_utc"), Ok(ExportColumn::CreatedUtc));
+        assert!(parse_column("upvotes").is_err());
+    }
+
+    #[test]
+    fn test_args() {
+        Args::command().debug_assert();
+        let args = Args::parse_from(["rmoods-cli", "u/spez", "-r", "spam", "--columns", "id,spam"]);
+        assert_eq!(args.reports, vec![RMoodsReportType::Spam]);
+        assert_eq!(
+            args.columns,
+            Some(vec![ExportColumn::Id, ExportColumn::Spam])
+        );
+    }
+}
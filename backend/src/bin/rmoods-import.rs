@@ -0,0 +1,76 @@
+//! Imports Reddit archive dumps (zstd-compressed NDJSON) into the corpus.
+//!
+//! ```sh
+//! cargo run --bin rmoods-import -- --subreddit Polska --since 2020-01-01 RS_2020-01.zst RC_2020-01.zst
+//! ```
+
+use chrono::NaiveDate;
+use clap::Parser;
+use log::{error, info, warn};
+use rmoods_backend::config::{ConfigSource, DatabaseConfig};
+use rmoods_backend::corpus::archive::ArchiveFilter;
+use rmoods_backend::corpus::import::{import_dump, ImportOptions, DEFAULT_BATCH_SIZE};
+use sqlx::postgres::PgPoolOptions;
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+#[command(about = "Import Reddit archive dumps (zstd-compressed NDJSON) into the RMoods corpus")]
+struct Args {
+    /// Dumps to import, eg. RS_2023-01.zst with posts or RC_2023-01.zst with comments
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+    /// Only import these subreddits, all of them by default
+    #[arg(short, long)]
+    subreddit: Vec<String>,
+    /// Only import items created on this day or later, eg. 2020-01-01
+    #[arg(long)]
+    since: Option<NaiveDate>,
+    /// Only import items created on this day or earlier, eg. 2020-12-31
+    #[arg(long)]
+    until: Option<NaiveDate>,
+    /// Items stored in a single transaction
+    #[arg(long, default_value_t = DEFAULT_BATCH_SIZE)]
+    batch_size: usize,
+    /// Import from the start, even the dumps that were imported before
+    #[arg(long)]
+    restart: bool,
+}
+
+async fn run(args: Args) -> anyhow::Result<()> {
+    let database = ConfigSource::load()?.section::<DatabaseConfig>()?;
+    let pool = PgPoolOptions::new()
+        .max_connections(2)
+        .connect(&database.url)
+        .await?;
+    sqlx::migrate!().run(&pool).await?;
+
+    let options = ImportOptions {
+        filter: ArchiveFilter::new(&args.subreddit, args.since, args.until),
+        batch_size: args.batch_size.max(1),
+        restart: args.restart,
+    };
+    for file in &args.files {
+        info!("Importing {}", file.display());
+        let summary = import_dump(&pool, file, options.clone()).await?;
+        info!(
+            "Imported {} items from {} lines of {}",
+            summary.imported,
+            summary.lines,
+            file.display()
+        );
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    if dotenvy::dotenv().is_err() {
+        warn!(".env not found. Environment variables will have to be defined outside of .env");
+    }
+
+    if let Err(e) = run(Args::parse()).await {
+        error!("{e}");
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,25 @@
+use std::path::PathBuf;
+use thiserror::Error;
+
+/// Represents any kind of error that can occur when loading the configuration.
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    /// The config file exists, or was explicitly given, but can't be read.
+    #[error("Failed to read the config file {path}: {source}")]
+    ReadError {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    /// The config file is not valid TOML, or a setting has the wrong type.
+    #[error("Invalid config file: {0}")]
+    ParseError(#[from] toml::de::Error),
+
+    /// An environment variable can't be parsed into its setting's type.
+    #[error("Invalid {name}: {message}")]
+    InvalidSetting { name: String, message: String },
+
+    /// Settings are missing or out of range, one problem per line.
+    #[error("Invalid configuration:\n{}", .0.join("\n"))]
+    Invalid(Vec<String>),
+}
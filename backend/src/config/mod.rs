@@ -0,0 +1,594 @@
+//! Configuration of the backend, loaded once at startup.
+//!
+//! Settings are read from an optional TOML file, `rmoods.toml` in the working directory
+//! or the file at `RMOODS_CONFIG`, and from the environment, which takes precedence.
+//! Every setting has an environment variable, see [SETTINGS]:
+//! ```toml
+//! [server]
+//! port = 8001
+//! cors_origins = ["https://rmoods.example.com"]
+//!
+//! [auth]
+//! token_lifetime_days = 7
+//! ```
+
+pub mod error;
+
+use crate::reddit_fetcher::reddit::cassette::{CassetteMode, DEFAULT_CASSETTE};
+use error::ConfigError;
+use http::HeaderValue;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::path::PathBuf;
+use toml::{Table, Value};
+
+/// Config file read when `RMOODS_CONFIG` is not set, it's fine if it doesn't exist.
+pub const DEFAULT_CONFIG_FILE: &str = "rmoods.toml";
+
+/// How the value of an environment variable is parsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Text,
+    Integer,
+    Boolean,
+    /// Comma-separated
+    List,
+}
+
+impl Kind {
+    fn parse(&self, value: &str) -> Result<Value, &'static str> {
+        match self {
+            Kind::Text => Ok(Value::String(value.to_string())),
+            Kind::Integer => value
+                .trim()
+                .parse()
+                .map(Value::Integer)
+                .map_err(|_| "an integer"),
+            Kind::Boolean => value
+                .trim()
+                .parse()
+                .map(Value::Boolean)
+                .map_err(|_| "true or false"),
+            Kind::List => Ok(Value::Array(
+                value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|v| !v.is_empty())
+                    .map(|v| Value::String(v.to_string()))
+                    .collect(),
+            )),
+        }
+    }
+}
+
+/// A setting and the environment variable that sets it.
+#[derive(Debug, Clone, Copy)]
+pub struct Setting {
+    pub env: &'static str,
+    /// `section.key` in the config file
+    pub path: &'static str,
+    kind: Kind,
+}
+
+const fn setting(env: &'static str, path: &'static str, kind: Kind) -> Setting {
+    Setting { env, path, kind }
+}
+
+/// Every setting that can be set by the environment.
+#[rustfmt::skip]
+pub const SETTINGS: &[Setting] = &[
+    setting("PORT", "server.port", Kind::Integer),
+    setting("CORS_ORIGINS", "server.cors_origins", Kind::List),
+    setting("LOG_LEVEL", "server.log_level", Kind::Text),
+    setting("LOG_FORMAT", "server.log_format", Kind::Text),
+    setting("OTLP_ENDPOINT", "server.otlp_endpoint", Kind::Text),
+    setting("DATABASE_URL", "database.url", Kind::Text),
+    setting("DATABASE_POOL_SIZE", "database.pool_size", Kind::Integer),
+    setting("CLIENT_ID", "reddit.client_id", Kind::Text),
+    setting("CLIENT_SECRET", "reddit.client_secret", Kind::Text),
+    setting("REDDIT_URL", "reddit.url", Kind::Text),
+    setting("REDDIT_CASSETTE_MODE", "reddit.cassette_mode", Kind::Text),
+    setting("REDDIT_CASSETTE", "reddit.cassette", Kind::Text),
+    setting("REDDIT_CACHE_SIZE", "reddit.cache_size", Kind::Integer),
+    setting("REDDIT_CACHE_DB", "reddit.cache_db", Kind::Boolean),
+    setting("REDDIT_CACHE_TTL_ABOUT", "reddit.cache_ttl_about", Kind::Integer),
+    setting("REDDIT_CACHE_TTL_NEW", "reddit.cache_ttl_new", Kind::Integer),
+    setting("REDDIT_CACHE_TTL_LISTING", "reddit.cache_ttl_listing", Kind::Integer),
+    setting("JWT_SECRET", "auth.jwt_secret", Kind::Text),
+    setting("TOKEN_LIFETIME_DAYS", "auth.token_lifetime_days", Kind::Integer),
+    setting("GOOGLE_CLIENT_ID", "auth.google_client_id", Kind::Text),
+    setting("GOOGLE_CLIENT_SECRET", "auth.google_client_secret", Kind::Text),
+    setting("NLP_URL", "nlp.url", Kind::Text),
+];
+
+/// Describe a setting by its environment variable and its place in the config file.
+fn describe(path: &str) -> String {
+    match SETTINGS.iter().find(|s| s.path == path) {
+        Some(setting) => format!("{} ({path})", setting.env),
+        None => path.to_string(),
+    }
+}
+
+fn require(problems: &mut Vec<String>, path: &str, value: &str) {
+    if value.trim().is_empty() {
+        problems.push(format!("{} is required", describe(path)));
+    }
+}
+
+fn check_url(problems: &mut Vec<String>, path: &str, url: &str) {
+    if let Err(e) = reqwest::Url::parse(url) {
+        problems.push(format!("{} is not a valid URL: {e}", describe(path)));
+    }
+}
+
+/// A section of the config file, eg. `[reddit]`.
+pub trait Section: DeserializeOwned + Default {
+    const NAME: &'static str;
+
+    /// Describe every missing or out of range setting.
+    fn problems(&self) -> Vec<String>;
+}
+
+/// How log lines are written to stdout.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human readable
+    #[default]
+    Text,
+    /// One JSON object per line, with the fields of every span it happened in
+    Json,
+}
+
+/// The HTTP server.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub port: u16,
+    /// Origins allowed to call the API from a browser, any origin if empty
+    pub cors_origins: Vec<String>,
+    /// Used when `RUST_LOG` is not set, eg. `info` or `rmoods_backend=debug`
+    pub log_level: String,
+    pub log_format: LogFormat,
+    /// OpenTelemetry collector receiving the traces over OTLP/HTTP, eg. `http://localhost:4318`.
+    /// Traces aren't exported if it's not set.
+    pub otlp_endpoint: Option<String>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            port: 8001,
+            cors_origins: vec![],
+            log_level: "debug".to_string(),
+            log_format: LogFormat::Text,
+            otlp_endpoint: None,
+        }
+    }
+}
+
+impl ServerConfig {
+    /// The allowed origins as header values. Invalid ones are reported by [Section::problems].
+    pub fn cors_origins(&self) -> Vec<HeaderValue> {
+        self.cors_origins
+            .iter()
+            .filter_map(|o| o.parse().ok())
+            .collect()
+    }
+}
+
+impl Section for ServerConfig {
+    const NAME: &'static str = "server";
+
+    fn problems(&self) -> Vec<String> {
+        let mut problems = vec![];
+        if self.port == 0 {
+            problems.push(format!("{} can't be 0", describe("server.port")));
+        }
+        for origin in &self.cors_origins {
+            let valid = HeaderValue::from_str(origin).is_ok()
+                && (origin.starts_with("http://") || origin.starts_with("https://"));
+            if !valid {
+                problems.push(format!(
+                    "{} has an invalid origin {origin:?}, expected eg. https://rmoods.example.com",
+                    describe("server.cors_origins")
+                ));
+            }
+        }
+        if let Some(endpoint) = &self.otlp_endpoint {
+            check_url(&mut problems, "server.otlp_endpoint", endpoint);
+        }
+        problems
+    }
+}
+
+/// The Postgres database.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    pub url: String,
+    /// Connections kept by the pool
+    pub pool_size: u32,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            pool_size: 10,
+        }
+    }
+}
+
+impl Section for DatabaseConfig {
+    const NAME: &'static str = "database";
+
+    fn problems(&self) -> Vec<String> {
+        let mut problems = vec![];
+        require(&mut problems, "database.url", &self.url);
+        if self.pool_size == 0 {
+            problems.push(format!("{} can't be 0", describe("database.pool_size")));
+        }
+        problems
+    }
+}
+
+/// The Reddit API, its cache and cassettes.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RedditConfig {
+    /// Credentials of the Reddit app, not needed when replaying a cassette
+    pub client_id: String,
+    pub client_secret: String,
+    /// Replaces Reddit's API, eg. with a mock server
+    pub url: Option<String>,
+    /// Record to or replay from [cassette](Self::cassette), see [Cassette](crate::reddit_fetcher::reddit::cassette::Cassette)
+    pub cassette_mode: Option<CassetteMode>,
+    pub cassette: PathBuf,
+    /// Responses kept in memory, `0` disables the cache
+    pub cache_size: usize,
+    /// Also store responses in Postgres
+    pub cache_db: bool,
+    /// Seconds subreddit and user info stays fresh
+    pub cache_ttl_about: i64,
+    /// Seconds feeds sorted by new stay fresh
+    pub cache_ttl_new: i64,
+    /// Seconds other listings stay fresh
+    pub cache_ttl_listing: i64,
+}
+
+impl Default for RedditConfig {
+    fn default() -> Self {
+        Self {
+            client_id: String::new(),
+            client_secret: String::new(),
+            url: None,
+            cassette_mode: None,
+            cassette: PathBuf::from(DEFAULT_CASSETTE),
+            cache_size: 1000,
+            cache_db: false,
+            cache_ttl_about: 24 * 60 * 60,
+            cache_ttl_new: 2 * 60,
+            cache_ttl_listing: 15 * 60,
+        }
+    }
+}
+
+impl Section for RedditConfig {
+    const NAME: &'static str = "reddit";
+
+    fn problems(&self) -> Vec<String> {
+        let mut problems = vec![];
+        if self.cassette_mode != Some(CassetteMode::Replay) {
+            require(&mut problems, "reddit.client_id", &self.client_id);
+            require(&mut problems, "reddit.client_secret", &self.client_secret);
+        }
+        if let Some(url) = &self.url {
+            check_url(&mut problems, "reddit.url", url);
+        }
+        for (path, ttl) in [
+            ("reddit.cache_ttl_about", self.cache_ttl_about),
+            ("reddit.cache_ttl_new", self.cache_ttl_new),
+            ("reddit.cache_ttl_listing", self.cache_ttl_listing),
+        ] {
+            if ttl < 0 {
+                problems.push(format!("{} can't be negative", describe(path)));
+            }
+        }
+        problems
+    }
+}
+
+/// Google sign-in and the JWTs issued after it.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+    pub jwt_secret: String,
+    /// Days a JWT stays valid
+    pub token_lifetime_days: i64,
+    pub google_client_id: String,
+    pub google_client_secret: String,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            jwt_secret: String::new(),
+            token_lifetime_days: 30,
+            google_client_id: String::new(),
+            google_client_secret: String::new(),
+        }
+    }
+}
+
+impl Section for AuthConfig {
+    const NAME: &'static str = "auth";
+
+    fn problems(&self) -> Vec<String> {
+        let mut problems = vec![];
+        require(&mut problems, "auth.jwt_secret", &self.jwt_secret);
+        require(
+            &mut problems,
+            "auth.google_client_id",
+            &self.google_client_id,
+        );
+        require(
+            &mut problems,
+            "auth.google_client_secret",
+            &self.google_client_secret,
+        );
+        if self.token_lifetime_days < 1 {
+            problems.push(format!(
+                "{} must be at least 1",
+                describe("auth.token_lifetime_days")
+            ));
+        }
+        problems
+    }
+}
+
+/// The NLP service.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NlpConfig {
+    pub url: String,
+}
+
+impl Default for NlpConfig {
+    fn default() -> Self {
+        Self {
+            url: "http://localhost:8002".to_string(),
+        }
+    }
+}
+
+impl Section for NlpConfig {
+    const NAME: &'static str = "nlp";
+
+    fn problems(&self) -> Vec<String> {
+        let mut problems = vec![];
+        check_url(&mut problems, "nlp.url", &self.url);
+        problems
+    }
+}
+
+/// Everything the server needs, see the [module docs](self).
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub database: DatabaseConfig,
+    pub reddit: RedditConfig,
+    pub auth: AuthConfig,
+    pub nlp: NlpConfig,
+}
+
+impl Config {
+    /// Load the config file and the environment and validate the result.
+    pub fn load() -> Result<Self, ConfigError> {
+        ConfigSource::load()?.config()
+    }
+
+    /// Describe every missing or out of range setting.
+    pub fn problems(&self) -> Vec<String> {
+        [
+            self.server.problems(),
+            self.database.problems(),
+            self.reddit.problems(),
+            self.auth.problems(),
+            self.nlp.problems(),
+        ]
+        .concat()
+    }
+}
+
+/// Settings of the config file overridden by the environment, not validated yet.
+///
+/// Command line tools take only the [Section]s they need from it, the server takes the whole [Config].
+#[derive(Debug, Clone, Default)]
+pub struct ConfigSource(Table);
+
+impl ConfigSource {
+    /// Read the config file at `RMOODS_CONFIG`, or [DEFAULT_CONFIG_FILE] if it exists, and the environment.
+    pub fn load() -> Result<Self, ConfigError> {
+        let (path, explicit) = match std::env::var("RMOODS_CONFIG") {
+            Ok(path) => (PathBuf::from(path), true),
+            Err(_) => (PathBuf::from(DEFAULT_CONFIG_FILE), false),
+        };
+        let file = match std::fs::read_to_string(&path) {
+            Ok(file) => Some(file),
+            Err(e) if !explicit && e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(source) => return Err(ConfigError::ReadError { path, source }),
+        };
+        Self::new(file.as_deref(), |name| std::env::var(name).ok())
+    }
+
+    /// Parse the contents of a config file, if any, and override them with the variables `env` returns.
+    pub fn new(
+        file: Option<&str>,
+        env: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self, ConfigError> {
+        let mut table = match file {
+            Some(file) => file.parse::<Table>()?,
+            None => Table::new(),
+        };
+
+        for setting in SETTINGS {
+            let Some(raw) = env(setting.env) else {
+                continue;
+            };
+            let value =
+                setting
+                    .kind
+                    .parse(&raw)
+                    .map_err(|expected| ConfigError::InvalidSetting {
+                        name: setting.env.to_string(),
+                        message: format!("expected {expected}, got {raw:?}"),
+                    })?;
+
+            let (section, key) = setting.path.split_once('.').expect("Paths are section.key");
+            let section = table
+                .entry(section)
+                .or_insert_with(|| Value::Table(Table::new()));
+            let Some(section) = section.as_table_mut() else {
+                return Err(ConfigError::InvalidSetting {
+                    name: setting.path.to_string(),
+                    message: "expected a table in the config file".to_string(),
+                });
+            };
+            section.insert(key.to_string(), value);
+        }
+        Ok(Self(table))
+    }
+
+    /// The whole configuration, validated.
+    pub fn config(&self) -> Result<Config, ConfigError> {
+        let config: Config = self.0.clone().try_into()?;
+        match config.problems() {
+            problems if problems.is_empty() => Ok(config),
+            problems => Err(ConfigError::Invalid(problems)),
+        }
+    }
+
+    /// A single section, validated, eg. [RedditConfig] for a tool that doesn't run the server.
+    pub fn section<T: Section>(&self) -> Result<T, ConfigError> {
+        let section: T = match self.0.get(T::NAME) {
+            Some(value) => value.clone().try_into()?,
+            None => T::default(),
+        };
+        match section.problems() {
+            problems if problems.is_empty() => Ok(section),
+            problems => Err(ConfigError::Invalid(problems)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn env(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect::<HashMap<_, _>>();
+        move |name| vars.get(name).cloned()
+    }
+
+    fn required() -> Vec<(&'static str, &'static str)> {
+        vec![
+            ("DATABASE_URL", "postgres://localhost/rmoods"),
+            ("CLIENT_ID", "id"),
+            ("CLIENT_SECRET", "secret"),
+            ("JWT_SECRET", "jwt"),
+            ("GOOGLE_CLIENT_ID", "google-id"),
+            ("GOOGLE_CLIENT_SECRET", "google-secret"),
+        ]
+    }
+
+    #[test]
+    fn test_defaults_from_the_environment() {
+        let config = ConfigSource::new(None, env(&required()))
+            .unwrap()
+            .config()
+            .unwrap();
+
+        assert_eq!(config.server.port, 8001);
+        assert_eq!(config.database.pool_size, 10);
+        assert_eq!(config.reddit.client_id, "id");
+        assert_eq!(config.auth.token_lifetime_days, 30);
+        assert_eq!(config.nlp.url, "http://localhost:8002");
+    }
+
+    #[test]
+    fn test_environment_overrides_the_file() {
+        let file = r#"
+            [server]
+            port = 9000
+            cors_origins = ["https://rmoods.example.com"]
+
+            [auth]
+            token_lifetime_days = 7
+        "#;
+        let mut vars = required();
+        vars.push(("PORT", "9001"));
+        vars.push(("LOG_FORMAT", "json"));
+        vars.push((
+            "CORS_ORIGINS",
+            "https://a.example.com, https://b.example.com",
+        ));
+        let config = ConfigSource::new(Some(file), env(&vars))
+            .unwrap()
+            .config()
+            .unwrap();
+
+        assert_eq!(config.server.port, 9001);
+        assert_eq!(config.server.cors_origins().len(), 2);
+        assert_eq!(config.server.log_format, LogFormat::Json);
+        assert_eq!(config.auth.token_lifetime_days, 7);
+    }
+
+    #[test]
+    fn test_reports_every_missing_setting() {
+        let Err(ConfigError::Invalid(problems)) =
+            ConfigSource::new(None, env(&[])).unwrap().config()
+        else {
+            panic!("Expected the missing settings to be reported");
+        };
+        assert_eq!(problems.len(), 6);
+        assert!(problems.contains(&"DATABASE_URL (database.url) is required".to_string()));
+    }
+
+    #[test]
+    fn test_invalid_values() {
+        let result = ConfigSource::new(None, env(&[("PORT", "eighty")]));
+        assert!(matches!(result, Err(ConfigError::InvalidSetting { .. })));
+
+        let result = ConfigSource::new(Some("[server]\nprot = 80"), env(&required()))
+            .unwrap()
+            .config();
+        assert!(matches!(result, Err(ConfigError::ParseError(_))));
+
+        let mut vars = required();
+        vars.push(("TOKEN_LIFETIME_DAYS", "0"));
+        vars.push(("CORS_ORIGINS", "rmoods.example.com"));
+        vars.push(("OTLP_ENDPOINT", "localhost 4318"));
+        let Err(ConfigError::Invalid(problems)) =
+            ConfigSource::new(None, env(&vars)).unwrap().config()
+        else {
+            panic!("Expected the invalid settings to be reported");
+        };
+        assert_eq!(problems.len(), 3);
+    }
+
+    #[test]
+    fn test_section_without_the_rest() {
+        let source = ConfigSource::new(None, env(&[("REDDIT_CASSETTE_MODE", "replay")])).unwrap();
+        let reddit = source.section::<RedditConfig>().unwrap();
+        assert_eq!(reddit.cassette_mode, Some(CassetteMode::Replay));
+
+        assert!(source.section::<DatabaseConfig>().is_err());
+        assert!(source.config().is_err());
+    }
+}
@@ -0,0 +1,246 @@
+//! Records of community Reddit archive dumps, eg. `RS_2023-01.zst` with posts or `RC_2023-01.zst` with comments.
+//!
+//! Every line of a dump is a JSON object shaped like the `data` of a Reddit API item,
+//! but fields were added and dropped over the years, so records are normalized before parsing.
+
+use crate::corpus::error::CorpusError;
+use crate::reddit_fetcher::reddit::model::{RawComment, RawPost};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde_json::{json, Map, Value};
+
+/// A post or comment of a dump.
+#[derive(Debug, Clone)]
+pub enum ArchiveRecord {
+    Post(Box<RawPost>),
+    Comment(Box<RawComment>),
+}
+
+impl ArchiveRecord {
+    /// Parse a record, filling in the fields older dumps lack.
+    ///
+    /// Posts are told apart from comments by their `title`.
+    pub fn from_json(record: Value) -> Result<Self, CorpusError> {
+        let Value::Object(mut record) = record else {
+            return Err(CorpusError::InvalidRecord(
+                "Expected a JSON object".to_string(),
+            ));
+        };
+        let id = record
+            .get("id")
+            .and_then(|id| id.as_str())
+            .ok_or_else(|| CorpusError::InvalidRecord("Missing `id`".to_string()))?
+            .to_string();
+        // Old dumps have the creation time as a string
+        if let Some(seconds) = record.get("created_utc").and_then(as_seconds) {
+            record.insert("created_utc".to_string(), json!(seconds));
+        }
+
+        if record.contains_key("title") {
+            fill_defaults(
+                &mut record,
+                [
+                    ("name", json!(format!("t3_{id}"))),
+                    ("selftext", json!("")),
+                    ("gilded", json!(0)),
+                    ("score", json!(0)),
+                    ("over_18", json!(false)),
+                    ("subreddit_id", json!("")),
+                    ("author", json!("[deleted]")),
+                    ("num_comments", json!(0)),
+                    ("url", json!("")),
+                    ("stickied", json!(false)),
+                ],
+            );
+            let post = serde_json::from_value(Value::Object(record))?;
+            Ok(Self::Post(Box::new(post)))
+        } else if record.contains_key("body") {
+            fill_defaults(
+                &mut record,
+                [
+                    ("name", json!(format!("t1_{id}"))),
+                    ("subreddit_id", json!("")),
+                    ("replies", json!("")),
+                    ("author", json!("[deleted]")),
+                    ("permalink", json!("")),
+                    ("depth", Value::Null),
+                    ("score", json!(0)),
+                ],
+            );
+            let comment = serde_json::from_value(Value::Object(record))?;
+            Ok(Self::Comment(Box::new(comment)))
+        } else {
+            Err(CorpusError::InvalidRecord(format!(
+                "{id} is neither a post nor a comment"
+            )))
+        }
+    }
+}
+
+/// When the record was archived, `retrieved_on` in older dumps and `retrieved_utc` in newer ones.
+///
+/// Its score and the other counts are the ones from that time, not from the time of the import.
+pub fn retrieved_at(record: &Value) -> Option<DateTime<Utc>> {
+    ["retrieved_utc", "retrieved_on"]
+        .iter()
+        .find_map(|key| as_seconds(&record[*key]))
+        .and_then(|seconds| DateTime::from_timestamp(seconds as i64, 0))
+}
+
+/// Set the fields that are missing or `null`.
+fn fill_defaults<const N: usize>(record: &mut Map<String, Value>, defaults: [(&str, Value); N]) {
+    for (key, default) in defaults {
+        match record.get(key) {
+            Some(value) if !value.is_null() => {}
+            _ => {
+                record.insert(key.to_string(), default);
+            }
+        }
+    }
+}
+
+/// UNIX timestamp stored as a number or a string.
+fn as_seconds(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+/// Which records of a dump are imported.
+///
+/// Checked on the raw JSON, so the records that are skipped are never fully parsed.
+#[derive(Debug, Clone, Default)]
+pub struct ArchiveFilter {
+    /// Lowercase subreddit names, all subreddits if empty
+    subreddits: Vec<String>,
+    /// Only records created at or after this time
+    since: Option<DateTime<Utc>>,
+    /// Only records created before this time
+    until: Option<DateTime<Utc>>,
+}
+
+impl ArchiveFilter {
+    /// Records of the subreddits, created from the start of `since` to the end of `until`.
+    pub fn new(subreddits: &[String], since: Option<NaiveDate>, until: Option<NaiveDate>) -> Self {
+        let start_of = |date: NaiveDate| date.and_hms_opt(0, 0, 0).unwrap().and_utc();
+        Self {
+            subreddits: subreddits.iter().map(|s| s.to_lowercase()).collect(),
+            since: since.map(start_of),
+            until: until.and_then(|d| d.succ_opt()).map(start_of),
+        }
+    }
+
+    pub fn matches(&self, record: &Value) -> bool {
+        let subreddit = record["subreddit"].as_str().unwrap_or_default();
+        if !self.subreddits.is_empty() && !self.subreddits.contains(&subreddit.to_lowercase()) {
+            return false;
+        }
+
+        if self.since.is_none() && self.until.is_none() {
+            return true;
+        }
+        let Some(created_utc) =
+            as_seconds(&record["created_utc"]).and_then(|s| DateTime::from_timestamp(s as i64, 0))
+        else {
+            return false;
+        };
+        self.since.is_none_or(|since| created_utc >= since)
+            && self.until.is_none_or(|until| created_utc < until)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn post() -> Value {
+        json!({
+            "id": "5gn8ru",
+            "subreddit": "Polska",
+            "title": "Lorem ipsum",
+            "selftext": "",
+            "author": "spez",
+            "created_utc": "1480961536",
+            "score": 12,
+            "num_comments": 3,
+            "url": "https://www.reddit.com/r/Polska/comments/5gn8ru/lorem_ipsum/",
+            "over_18": false,
+            "stickied": false,
+            "retrieved_on": 1481203210
+        })
+    }
+
+    fn comment() -> Value {
+        json!({
+            "id": "dat9a1x",
+            "subreddit": "Polska",
+            "body": "Dolor sit amet",
+            "author": "spez",
+            "created_utc": 1480961600,
+            "score": 4,
+            "link_id": "t3_5gn8ru",
+            "parent_id": "t3_5gn8ru",
+            "gilded": 0
+        })
+    }
+
+    fn date(date: &str) -> Option<NaiveDate> {
+        Some(date.parse().unwrap())
+    }
+
+    #[test]
+    fn test_parse_old_post() {
+        let ArchiveRecord::Post(post) = ArchiveRecord::from_json(post()).unwrap() else {
+            panic!("Expected a post");
+        };
+        assert_eq!(post.name(), "t3_5gn8ru");
+        assert_eq!(post.created_utc().timestamp(), 1480961536);
+        assert_eq!(*post.gilded(), 0);
+    }
+
+    #[test]
+    fn test_parse_comment() {
+        let ArchiveRecord::Comment(comment) = ArchiveRecord::from_json(comment()).unwrap() else {
+            panic!("Expected a comment");
+        };
+        assert_eq!(comment.name(), "t1_dat9a1x");
+        assert!(comment.is_top_level());
+        assert!(comment.replies.is_none());
+    }
+
+    #[test]
+    fn test_retrieved_at() {
+        assert_eq!(retrieved_at(&post()).unwrap().timestamp(), 1481203210);
+        let newer = json!({ "id": "abc", "retrieved_utc": 1690000000 });
+        assert_eq!(retrieved_at(&newer).unwrap().timestamp(), 1690000000);
+        assert_eq!(retrieved_at(&comment()), None);
+    }
+
+    #[test]
+    fn test_parse_unknown_record() {
+        let result = ArchiveRecord::from_json(json!({ "id": "abc", "subreddit": "Polska" }));
+        assert!(matches!(result, Err(CorpusError::InvalidRecord(_))));
+    }
+
+    #[test]
+    fn test_filter_by_subreddit() {
+        let filter = ArchiveFilter::new(&["polska".to_string()], None, None);
+        assert!(filter.matches(&post()));
+        assert!(!filter.matches(&json!({ "subreddit": "poland" })));
+        assert!(ArchiveFilter::default().matches(&json!({ "subreddit": "poland" })));
+    }
+
+    #[test]
+    fn test_filter_by_date_includes_whole_days() {
+        // The post was created on 2016-12-05
+        let on_the_day = ArchiveFilter::new(&[], date("2016-12-05"), date("2016-12-05"));
+        assert!(on_the_day.matches(&post()));
+        assert!(on_the_day.matches(&comment()));
+
+        let after = ArchiveFilter::new(&[], date("2016-12-06"), None);
+        assert!(!after.matches(&post()));
+        let before = ArchiveFilter::new(&[], None, date("2016-12-04"));
+        assert!(!before.matches(&post()));
+    }
+}
@@ -0,0 +1,21 @@
+use thiserror::Error;
+
+/// Represents any kind of error that can occur when storing, importing or reading the corpus.
+#[derive(Debug, Error)]
+pub enum CorpusError {
+    /// A query to the database failed.
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+
+    /// A stored item doesn't match our structs anymore, or an item can't be stored.
+    #[error("Invalid corpus item: {0}")]
+    InvalidItem(#[from] serde_json::Error),
+
+    /// A record of an archive dump is neither a post nor a comment.
+    #[error("Invalid archive record: {0}")]
+    InvalidRecord(String),
+
+    /// An archive dump can't be read.
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+}
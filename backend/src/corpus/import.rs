@@ -0,0 +1,314 @@
+//! Import of archive dumps into the corpus, see [archive](super::archive).
+//!
+//! Dumps are zstd-compressed NDJSON files of up to hundreds of gigabytes, so they are streamed:
+//! a blocking task decompresses and filters the lines, while the matching records are stored in batches.
+//! Every batch is stored together with the number of lines processed so far,
+//! so an interrupted import resumes where it stopped.
+
+use crate::corpus::archive::{self, ArchiveFilter, ArchiveRecord};
+use crate::corpus::error::CorpusError;
+use crate::corpus::{store, CorpusItems};
+use chrono::{DateTime, Utc};
+use log::{debug, info, warn};
+use serde_json::Value;
+use sqlx::PgPool;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Records stored in a single transaction by default.
+pub const DEFAULT_BATCH_SIZE: usize = 1000;
+/// Lines read before the progress is saved, even if few of them matched the filter.
+const MAX_BATCH_LINES: u64 = 100_000;
+/// Dumps are compressed with long distance matching, which needs a window of up to 2 GiB.
+const MAX_WINDOW_LOG: u32 = 31;
+
+/// Matching records of a part of a dump.
+#[derive(Debug, Default)]
+pub struct Batch {
+    pub items: CorpusItems,
+    /// When each post of `items` was archived, see [archive::retrieved_at]
+    pub posts_seen_at: Vec<DateTime<Utc>>,
+    /// When each comment of `items` was archived
+    pub comments_seen_at: Vec<DateTime<Utc>>,
+    /// Lines read, including the ones that didn't match or couldn't be parsed
+    pub lines: u64,
+    /// Lines that matched, but couldn't be parsed
+    pub invalid: u64,
+}
+
+/// Options of an import.
+#[derive(Debug, Clone)]
+pub struct ImportOptions {
+    pub filter: ArchiveFilter,
+    pub batch_size: usize,
+    /// Import the dump from the start, even if it was imported before
+    pub restart: bool,
+}
+
+impl Default for ImportOptions {
+    fn default() -> Self {
+        Self {
+            filter: ArchiveFilter::default(),
+            batch_size: DEFAULT_BATCH_SIZE,
+            restart: false,
+        }
+    }
+}
+
+/// Outcome of an import.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ImportSummary {
+    /// Lines processed, including the ones from earlier runs
+    pub lines: u64,
+    /// Posts and comments stored, including the ones from earlier runs
+    pub imported: u64,
+    /// Lines that couldn't be parsed in this run
+    pub invalid: u64,
+}
+
+/// Read the decompressed dump, passing batches of matching records to `on_batch`.
+///
+/// The first `skip` lines are skipped, they were processed before.
+/// Reading stops early when `on_batch` returns `false`.
+pub fn read_dump(
+    reader: impl BufRead,
+    filter: &ArchiveFilter,
+    skip: u64,
+    batch_size: usize,
+    mut on_batch: impl FnMut(Batch) -> bool,
+) -> Result<(), CorpusError> {
+    let mut batch = Batch::default();
+
+    for line in reader.lines().skip(skip as usize) {
+        let line = line?;
+        batch.lines += 1;
+
+        match serde_json::from_str::<Value>(&line) {
+            Ok(record) if filter.matches(&record) => {
+                // Records without the time they were archived count as seen when created
+                let retrieved_at = archive::retrieved_at(&record);
+                match ArchiveRecord::from_json(record) {
+                    Ok(ArchiveRecord::Post(post)) => {
+                        let seen_at = retrieved_at.unwrap_or(*post.created_utc());
+                        batch.posts_seen_at.push(seen_at);
+                        batch.items.posts.push(*post);
+                    }
+                    Ok(ArchiveRecord::Comment(comment)) => {
+                        let seen_at = retrieved_at.unwrap_or(*comment.created_utc());
+                        batch.comments_seen_at.push(seen_at);
+                        batch.items.comments.push(*comment);
+                    }
+                    Err(e) => {
+                        debug!("Skipping an invalid record: {e}");
+                        batch.invalid += 1;
+                    }
+                }
+            }
+            Ok(_) => {}
+            Err(e) => {
+                debug!("Skipping a malformed line: {e}");
+                batch.invalid += 1;
+            }
+        }
+
+        let full = batch.items.len() >= batch_size || batch.lines >= MAX_BATCH_LINES;
+        if full && !on_batch(std::mem::take(&mut batch)) {
+            return Ok(());
+        }
+    }
+
+    if batch.lines > 0 {
+        on_batch(batch);
+    }
+    Ok(())
+}
+
+/// Counts the bytes read from the compressed file, to report the progress.
+struct CountingReader<R> {
+    inner: R,
+    count: Arc<AtomicU64>,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count.fetch_add(n as u64, Ordering::Relaxed);
+        Ok(n)
+    }
+}
+
+/// Import the matching records of a zstd-compressed dump into the corpus.
+///
+/// The progress is kept under the file name of the dump. A finished dump is not imported again,
+/// unless [ImportOptions::restart] is set.
+pub async fn import_dump(
+    pool: &PgPool,
+    path: &Path,
+    options: ImportOptions,
+) -> Result<ImportSummary, CorpusError> {
+    let source = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.display().to_string());
+
+    if options.restart {
+        store::delete_import(pool, &source).await?;
+    }
+    let state = store::get_import(pool, &source).await?;
+    if let Some(state) = state.as_ref().filter(|s| s.finished_at.is_some()) {
+        info!(
+            "{source} was already imported, {} items. Use restart to import it again",
+            state.imported
+        );
+        return Ok(ImportSummary {
+            lines: state.lines_done as u64,
+            imported: state.imported as u64,
+            invalid: 0,
+        });
+    }
+
+    let mut summary = ImportSummary {
+        lines: state.as_ref().map_or(0, |s| s.lines_done as u64),
+        imported: state.as_ref().map_or(0, |s| s.imported as u64),
+        invalid: 0,
+    };
+    if summary.lines > 0 {
+        info!("Resuming {source} after {} lines", summary.lines);
+    }
+
+    let file = File::open(path)?;
+    let total_bytes = file.metadata()?.len().max(1);
+    let bytes_read = Arc::new(AtomicU64::new(0));
+    let counting = CountingReader {
+        inner: file,
+        count: bytes_read.clone(),
+    };
+    let mut decoder = zstd::Decoder::new(counting)?;
+    decoder.window_log_max(MAX_WINDOW_LOG)?;
+
+    let (tx, mut rx) = tokio::sync::mpsc::channel::<Batch>(4);
+    let skip = summary.lines;
+    let reader = tokio::task::spawn_blocking(move || {
+        read_dump(
+            BufReader::new(decoder),
+            &options.filter,
+            skip,
+            options.batch_size,
+            |batch| tx.blocking_send(batch).is_ok(),
+        )
+    });
+
+    while let Some(batch) = rx.recv().await {
+        summary.lines += batch.lines;
+        summary.imported += batch.items.len() as u64;
+        summary.invalid += batch.invalid;
+
+        let mut conn = pool.begin().await?;
+        store::upsert_posts(&mut conn, &batch.items.posts, &batch.posts_seen_at).await?;
+        store::upsert_comments(&mut conn, &batch.items.comments, &batch.comments_seen_at).await?;
+        store::save_import(
+            &mut conn,
+            &source,
+            summary.lines as i64,
+            summary.imported as i64,
+            false,
+        )
+        .await?;
+        conn.commit().await?;
+
+        let percent = bytes_read.load(Ordering::Relaxed) as f64 / total_bytes as f64 * 100.0;
+        info!(
+            "{source}: {percent:.1}%, {} lines, {} imported",
+            summary.lines, summary.imported
+        );
+    }
+    reader.await.expect("Dump reader panicked")?;
+
+    let mut conn = pool.acquire().await?;
+    store::save_import(
+        &mut conn,
+        &source,
+        summary.lines as i64,
+        summary.imported as i64,
+        true,
+    )
+    .await?;
+    if summary.invalid > 0 {
+        warn!("{source}: skipped {} invalid lines", summary.invalid);
+    }
+    info!(
+        "{source}: done, {} lines, {} imported",
+        summary.lines, summary.imported
+    );
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::io::Cursor;
+
+    fn dump() -> Vec<u8> {
+        let lines = [
+            json!({ "id": "a", "subreddit": "Polska", "title": "A", "created_utc": 1480961536,
+                    "retrieved_on": 1481203210 }),
+            json!({ "id": "b", "subreddit": "poland", "title": "B", "created_utc": 1480961537 }),
+            json!({ "id": "c", "subreddit": "Polska", "body": "C", "created_utc": 1480961538,
+                    "link_id": "t3_a", "parent_id": "t3_a" }),
+            json!({ "id": "d", "subreddit": "Polska" }),
+        ]
+        .map(|line| line.to_string());
+        let text = format!("{}\nnot json\n", lines.join("\n"));
+        zstd::encode_all(text.as_bytes(), 3).unwrap()
+    }
+
+    fn read(skip: u64, batch_size: usize) -> Vec<Batch> {
+        let decoder = zstd::Decoder::new(Cursor::new(dump())).unwrap();
+        let filter = ArchiveFilter::new(&["Polska".to_string()], None, None);
+        let mut batches = vec![];
+        read_dump(BufReader::new(decoder), &filter, skip, batch_size, |b| {
+            batches.push(b);
+            true
+        })
+        .unwrap();
+        batches
+    }
+
+    #[test]
+    fn test_read_dump_filters_and_batches() {
+        let batches = read(0, 1);
+
+        assert_eq!(batches.len(), 3);
+        assert_eq!(batches[0].items.posts[0].name(), "t3_a");
+        assert_eq!(batches[0].posts_seen_at[0].timestamp(), 1481203210);
+        assert_eq!(batches[1].items.comments[0].name(), "t1_c");
+        // Without `retrieved_on`, the comment was seen when created
+        assert_eq!(batches[1].comments_seen_at[0].timestamp(), 1480961538);
+        // The rest of the dump has no valid records, but the lines still count
+        assert!(batches[2].items.is_empty());
+        assert_eq!((batches[2].lines, batches[2].invalid), (2, 2));
+    }
+
+    #[test]
+    fn test_read_dump_reports_the_last_lines() {
+        let batches = read(0, DEFAULT_BATCH_SIZE);
+
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].items.len(), 2);
+        assert_eq!(batches[0].lines, 5);
+        assert_eq!(batches[0].invalid, 2);
+    }
+
+    #[test]
+    fn test_read_dump_resumes_after_skipped_lines() {
+        let batches = read(1, DEFAULT_BATCH_SIZE);
+
+        assert_eq!(batches[0].items.posts.len(), 0);
+        assert_eq!(batches[0].items.comments.len(), 1);
+        assert_eq!(batches[0].lines, 4);
+    }
+}
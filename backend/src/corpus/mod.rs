@@ -0,0 +1,168 @@
+//! Local corpus of every post and comment fetched from Reddit.
+//!
+//! Items are upserted by their fullname, remembering when they were first and last seen
+//! and how their score changed. Reports can be recomputed from the corpus without Reddit requests,
+//! and it grows past the ~1000 items Reddit lists for any feed.
+
+pub mod archive;
+pub mod error;
+pub mod import;
+pub mod store;
+
+use crate::corpus::error::CorpusError;
+use crate::reddit_fetcher::reddit::model::{RawComment, RawContainer, RawPost};
+use chrono::{DateTime, Utc};
+use log::{debug, warn};
+use sqlx::PgPool;
+use tracing::Instrument;
+
+/// Posts and comments to store in the corpus.
+#[derive(Debug, Default)]
+pub struct CorpusItems {
+    pub posts: Vec<RawPost>,
+    pub comments: Vec<RawComment>,
+}
+
+impl CorpusItems {
+    /// Collect the posts and comments of a response, including the replies of every comment.
+    pub fn from_container(container: &RawContainer) -> Self {
+        let mut items = Self::default();
+        items.collect(container);
+        items
+    }
+
+    fn collect(&mut self, container: &RawContainer) {
+        match container {
+            RawContainer::Listing(listing) => listing
+                .children
+                .iter()
+                .for_each(|child| self.collect(child)),
+            RawContainer::Post(post) => self.posts.push(*post.clone()),
+            RawContainer::Comment(comment) => {
+                if let Some(replies) = &comment.replies {
+                    self.collect(replies);
+                }
+                // Replies are stored on their own
+                let mut comment = *comment.clone();
+                comment.replies = None;
+                self.comments.push(comment);
+            }
+            _ => {}
+        }
+    }
+
+    /// Add the items of another response.
+    pub fn extend(&mut self, other: CorpusItems) {
+        self.posts.extend(other.posts);
+        self.comments.extend(other.comments);
+    }
+
+    pub fn len(&self) -> usize {
+        self.posts.len() + self.comments.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.posts.is_empty() && self.comments.is_empty()
+    }
+}
+
+impl From<&[RawComment]> for CorpusItems {
+    fn from(comments: &[RawComment]) -> Self {
+        let mut items = Self::default();
+        for comment in comments {
+            items.collect(&RawContainer::Comment(Box::new(comment.clone())));
+        }
+        items
+    }
+}
+
+/// The corpus, see the [module docs](self).
+#[derive(Debug, Clone)]
+pub struct Corpus {
+    pool: PgPool,
+}
+
+impl Corpus {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Upsert the items, seen at `seen_at`, in a single transaction.
+    pub async fn store(
+        &self,
+        items: &CorpusItems,
+        seen_at: DateTime<Utc>,
+    ) -> Result<(), CorpusError> {
+        let mut tx = self.pool.begin().await?;
+        store::upsert_items(&mut tx, items, seen_at).await?;
+        tx.commit().await?;
+
+        debug!(
+            "Stored {} posts and {} comments in the corpus",
+            items.posts.len(),
+            items.comments.len()
+        );
+        Ok(())
+    }
+
+    /// Store the items seen at `seen_at` in the background, so fetching doesn't wait for the database.
+    /// Failures are only logged, the corpus is not essential to serve a request.
+    pub fn store_in_background(&self, items: CorpusItems, seen_at: DateTime<Utc>) {
+        if items.is_empty() {
+            return;
+        }
+        let corpus = self.clone();
+        tokio::spawn(
+            async move {
+                if let Err(e) = corpus.store(&items, seen_at).await {
+                    warn!("Failed to store fetched items in the corpus: {e}");
+                }
+            }
+            .in_current_span(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reddit_fetcher::reddit::mock::fixtures;
+
+    #[test]
+    fn test_collect_post_and_comment_tree() {
+        let listings: Vec<RawContainer> =
+            serde_json::from_value(fixtures::post_comments()).unwrap();
+        let mut items = CorpusItems::default();
+        listings
+            .iter()
+            .for_each(|l| items.extend(CorpusItems::from_container(l)));
+
+        assert_eq!(items.posts.len(), 1);
+        let mut names = items
+            .comments
+            .iter()
+            .map(|c| c.name().as_str())
+            .collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, vec!["t1_lsp8x2f", "t1_lsp90aa"]);
+        assert!(items.comments.iter().all(|c| c.replies.is_none()));
+    }
+
+    #[test]
+    fn test_stored_items_can_be_read_back() {
+        let listing: RawContainer = serde_json::from_value(fixtures::subreddit_posts()).unwrap();
+        let items = CorpusItems::from_container(&listing);
+        let post = &items.posts[0];
+
+        let stored = serde_json::to_value(post).unwrap();
+        assert_eq!(&serde_json::from_value::<RawPost>(stored).unwrap(), post);
+
+        let listings: Vec<RawContainer> =
+            serde_json::from_value(fixtures::post_comments()).unwrap();
+        let items = CorpusItems::from_container(&listings[1]);
+        let stored = serde_json::to_value(&items.comments[0]).unwrap();
+        let comment = serde_json::from_value::<RawComment>(stored).unwrap();
+        assert_eq!(comment.name(), items.comments[0].name());
+        assert_eq!(comment.created_utc(), items.comments[0].created_utc());
+    }
+}
@@ -0,0 +1,273 @@
+use crate::corpus::error::CorpusError;
+use crate::corpus::CorpusItems;
+use crate::reddit_fetcher::reddit::model::{RawComment, RawPost};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::{types::Json, FromRow, PgConnection, PgPool};
+
+/// Score of an item at some point in time.
+#[derive(Debug, Clone, PartialEq, Serialize, FromRow)]
+pub struct ScoreSnapshot {
+    pub seen_at: DateTime<Utc>,
+    pub score: i64,
+    /// Only for posts
+    pub num_comments: Option<i64>,
+}
+
+/// Progress of importing an archive dump.
+#[derive(Debug, Clone, PartialEq, FromRow)]
+pub struct ImportState {
+    /// File name of the dump, eg. RS_2023-01.zst
+    pub source: String,
+    /// Lines of the dump already processed
+    pub lines_done: i64,
+    /// Posts and comments stored so far
+    pub imported: i64,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+/// Record the scores that differ from the ones stored before the upsert.
+///
+/// `previous` and `upserted` are CTEs of the calling query, all of them see the tables as they were before.
+const RECORD_SCORES: &str = "
+    INSERT INTO corpus_scores (fullname, seen_at, score, num_comments)
+    SELECT u.fullname, u.last_seen_at, u.score, u.num_comments FROM upserted u
+    WHERE NOT EXISTS (
+        SELECT 1 FROM previous p
+        WHERE p.fullname = u.fullname
+            AND p.score = u.score
+            AND p.num_comments IS NOT DISTINCT FROM u.num_comments
+    )
+    ON CONFLICT DO NOTHING";
+
+/// Insert the posts or update the stored ones, each seen at the matching time of `seen_at`.
+///
+/// A stored post is only updated by a newer observation, and only then its score is recorded.
+/// Of the posts listed more than once, the last seen is kept.
+pub async fn upsert_posts(
+    conn: &mut PgConnection,
+    posts: &[RawPost],
+    seen_at: &[DateTime<Utc>],
+) -> Result<(), CorpusError> {
+    if posts.is_empty() {
+        return Ok(());
+    }
+    let data = posts
+        .iter()
+        .map(|p| serde_json::to_value(p).map(Json))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    sqlx::query(&format!(
+        "WITH input AS (
+            SELECT DISTINCT ON (fullname) * FROM UNNEST(
+                $1::TEXT[], $2::TEXT[], $3::TEXT[], $4::TIMESTAMPTZ[],
+                $5::BIGINT[], $6::BIGINT[], $7::JSONB[], $8::TIMESTAMPTZ[]
+            ) AS i (fullname, subreddit, author, created_utc, score, num_comments, data, seen_at)
+            ORDER BY fullname, seen_at DESC
+        ), previous AS (
+            SELECT fullname, score, num_comments FROM corpus_posts
+            WHERE fullname IN (SELECT fullname FROM input)
+        ), upserted AS (
+            INSERT INTO corpus_posts
+                (fullname, subreddit, author, created_utc, score, num_comments, data, first_seen_at, last_seen_at)
+            SELECT fullname, subreddit, author, created_utc, score, num_comments, data, seen_at, seen_at
+            FROM input
+            ON CONFLICT (fullname) DO UPDATE SET
+                author = EXCLUDED.author,
+                score = EXCLUDED.score,
+                num_comments = EXCLUDED.num_comments,
+                data = EXCLUDED.data,
+                last_seen_at = EXCLUDED.last_seen_at
+            WHERE corpus_posts.last_seen_at < EXCLUDED.last_seen_at
+            RETURNING fullname, last_seen_at, score, num_comments
+        ) {RECORD_SCORES}"
+    ))
+    .bind(posts.iter().map(|p| p.name().clone()).collect::<Vec<_>>())
+    .bind(posts.iter().map(|p| p.subreddit().clone()).collect::<Vec<_>>())
+    .bind(posts.iter().map(|p| p.author().clone()).collect::<Vec<_>>())
+    .bind(posts.iter().map(|p| *p.created_utc()).collect::<Vec<_>>())
+    .bind(posts.iter().map(|p| *p.score()).collect::<Vec<_>>())
+    .bind(posts.iter().map(|p| *p.num_comments() as i64).collect::<Vec<_>>())
+    .bind(data)
+    .bind(seen_at)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Insert the comments or update the stored ones, each seen at the matching time of `seen_at`.
+/// Their replies are not stored.
+///
+/// Like [upsert_posts], a stored comment is only updated by a newer observation.
+pub async fn upsert_comments(
+    conn: &mut PgConnection,
+    comments: &[RawComment],
+    seen_at: &[DateTime<Utc>],
+) -> Result<(), CorpusError> {
+    if comments.is_empty() {
+        return Ok(());
+    }
+    let data = comments
+        .iter()
+        .map(|c| {
+            let mut data = serde_json::to_value(c)?;
+            data["replies"] = Value::Null;
+            Ok(Json(data))
+        })
+        .collect::<Result<Vec<_>, serde_json::Error>>()?;
+
+    sqlx::query(&format!(
+        "WITH input AS (
+            SELECT DISTINCT ON (fullname) * FROM UNNEST(
+                $1::TEXT[], $2::TEXT[], $3::TEXT[], $4::TEXT[],
+                $5::TIMESTAMPTZ[], $6::BIGINT[], $7::JSONB[], $8::TIMESTAMPTZ[]
+            ) AS i (fullname, subreddit, author, link_id, created_utc, score, data, seen_at)
+            ORDER BY fullname, seen_at DESC
+        ), previous AS (
+            SELECT fullname, score, NULL::BIGINT AS num_comments FROM corpus_comments
+            WHERE fullname IN (SELECT fullname FROM input)
+        ), upserted AS (
+            INSERT INTO corpus_comments
+                (fullname, subreddit, author, link_id, created_utc, score, data, first_seen_at, last_seen_at)
+            SELECT fullname, subreddit, author, link_id, created_utc, score, data, seen_at, seen_at
+            FROM input
+            ON CONFLICT (fullname) DO UPDATE SET
+                author = EXCLUDED.author,
+                score = EXCLUDED.score,
+                data = EXCLUDED.data,
+                last_seen_at = EXCLUDED.last_seen_at
+            WHERE corpus_comments.last_seen_at < EXCLUDED.last_seen_at
+            RETURNING fullname, last_seen_at, score, NULL::BIGINT AS num_comments
+        ) {RECORD_SCORES}"
+    ))
+    .bind(comments.iter().map(|c| c.name().clone()).collect::<Vec<_>>())
+    .bind(comments.iter().map(|c| c.subreddit().clone()).collect::<Vec<_>>())
+    .bind(comments.iter().map(|c| c.author().clone()).collect::<Vec<_>>())
+    .bind(comments.iter().map(|c| c.link_id().clone()).collect::<Vec<_>>())
+    .bind(comments.iter().map(|c| *c.created_utc()).collect::<Vec<_>>())
+    .bind(comments.iter().map(|c| *c.score()).collect::<Vec<_>>())
+    .bind(data)
+    .bind(seen_at)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Upsert all the items, seen at `seen_at`, in one statement for the posts and one for the comments.
+pub async fn upsert_items(
+    conn: &mut PgConnection,
+    items: &CorpusItems,
+    seen_at: DateTime<Utc>,
+) -> Result<(), CorpusError> {
+    upsert_posts(conn, &items.posts, &vec![seen_at; items.posts.len()]).await?;
+    upsert_comments(conn, &items.comments, &vec![seen_at; items.comments.len()]).await?;
+    Ok(())
+}
+
+/// Stored posts of the subreddit created after `since`, newest first.
+pub async fn get_posts(
+    pool: &PgPool,
+    subreddit: &str,
+    since: Option<DateTime<Utc>>,
+    limit: i64,
+) -> Result<Vec<RawPost>, CorpusError> {
+    let rows: Vec<(Json<Value>,)> = sqlx::query_as(
+        "SELECT data FROM corpus_posts
+         WHERE lower(subreddit) = lower($1) AND ($2::TIMESTAMPTZ IS NULL OR created_utc > $2)
+         ORDER BY created_utc DESC LIMIT $3",
+    )
+    .bind(subreddit)
+    .bind(since)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    rows.into_iter()
+        .map(|(data,)| serde_json::from_value(data.0).map_err(CorpusError::from))
+        .collect()
+}
+
+/// Stored comments of the subreddit created after `since`, newest first.
+pub async fn get_comments(
+    pool: &PgPool,
+    subreddit: &str,
+    since: Option<DateTime<Utc>>,
+    limit: i64,
+) -> Result<Vec<RawComment>, CorpusError> {
+    let rows: Vec<(Json<Value>,)> = sqlx::query_as(
+        "SELECT data FROM corpus_comments
+         WHERE lower(subreddit) = lower($1) AND ($2::TIMESTAMPTZ IS NULL OR created_utc > $2)
+         ORDER BY created_utc DESC LIMIT $3",
+    )
+    .bind(subreddit)
+    .bind(since)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    rows.into_iter()
+        .map(|(data,)| serde_json::from_value(data.0).map_err(CorpusError::from))
+        .collect()
+}
+
+/// How the score of a post or comment changed, oldest first.
+pub async fn get_score_history(
+    pool: &PgPool,
+    fullname: &str,
+) -> Result<Vec<ScoreSnapshot>, CorpusError> {
+    let history = sqlx::query_as(
+        "SELECT seen_at, score, num_comments FROM corpus_scores
+         WHERE fullname = $1 ORDER BY seen_at",
+    )
+    .bind(fullname)
+    .fetch_all(pool)
+    .await?;
+    Ok(history)
+}
+
+/// Get the progress of importing the dump, `None` if it was never imported.
+pub async fn get_import(pool: &PgPool, source: &str) -> Result<Option<ImportState>, CorpusError> {
+    let state = sqlx::query_as(
+        "SELECT source, lines_done, imported, finished_at FROM corpus_imports WHERE source = $1",
+    )
+    .bind(source)
+    .fetch_optional(pool)
+    .await?;
+    Ok(state)
+}
+
+/// Save the progress of importing the dump, together with the items of the last batch.
+pub async fn save_import(
+    conn: &mut PgConnection,
+    source: &str,
+    lines_done: i64,
+    imported: i64,
+    finished: bool,
+) -> Result<(), CorpusError> {
+    sqlx::query(
+        "INSERT INTO corpus_imports (source, lines_done, imported, finished_at)
+         VALUES ($1, $2, $3, CASE WHEN $4 THEN now() END)
+         ON CONFLICT (source) DO UPDATE SET
+            lines_done = EXCLUDED.lines_done,
+            imported = EXCLUDED.imported,
+            updated_at = now(),
+            finished_at = EXCLUDED.finished_at",
+    )
+    .bind(source)
+    .bind(lines_done)
+    .bind(imported)
+    .bind(finished)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Forget the progress of importing the dump, so it's imported from the start.
+pub async fn delete_import(pool: &PgPool, source: &str) -> Result<(), CorpusError> {
+    sqlx::query("DELETE FROM corpus_imports WHERE source = $1")
+        .bind(source)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,38 @@
+//! RMoods backend: fetches Reddit data, analyzes it with the NLP service and serves the reports.
+//!
+//! The server lives in `main.rs`, command line tools in `src/bin`. They all share this library.
+
+use crate::config::Config;
+use crate::reddit_fetcher::fetcher::RMoodsFetcher;
+use crate::report::nlp::NlpClient;
+use crate::websocket::SystemMessage;
+use reqwest::Client;
+use sqlx::{Pool, Postgres};
+use std::sync::Arc;
+
+pub mod api;
+pub mod app_error;
+pub mod config;
+pub mod corpus;
+pub mod metrics;
+pub mod monitor;
+pub mod open_api;
+pub mod reddit_fetcher;
+pub mod report;
+pub mod source_group;
+pub mod startup;
+pub mod telemetry;
+pub mod websocket;
+
+/// State to be shared between all routes.
+///
+/// Contains common resources that shouldn't be created over and over again.
+#[derive(Clone)]
+pub struct AppState {
+    pub fetcher: RMoodsFetcher,
+    pub pool: Pool<Postgres>,
+    pub http: Client,
+    pub nlp: NlpClient,
+    pub system_tx: tokio::sync::mpsc::Sender<SystemMessage>,
+    pub config: Arc<Config>,
+}
@@ -1,52 +1,51 @@
-use crate::open_api::ApiDoc;
-use crate::reddit_fetcher::fetcher::RMoodsFetcher;
-use crate::startup::{shutdown_signal, verify_environment};
-use crate::websocket::SystemMessage;
-use api::auth;
 use axum::Router;
-use http::header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE};
+use http::header::{HeaderName, ACCEPT, AUTHORIZATION, CONTENT_TYPE};
 use log::{error, info, warn};
-use reqwest::Client;
-use sqlx::{postgres::PgPoolOptions, Pool, Postgres};
+use rmoods_backend::api::{self, auth};
+use rmoods_backend::config::{Config, ServerConfig};
+use rmoods_backend::corpus::Corpus;
+use rmoods_backend::open_api::ApiDoc;
+use rmoods_backend::reddit_fetcher::fetcher::RMoodsFetcher;
+use rmoods_backend::reddit_fetcher::reddit::cache::ResponseCache;
+use rmoods_backend::report::nlp::NlpClient;
+use rmoods_backend::startup::shutdown_signal;
+use rmoods_backend::telemetry::{self, Telemetry, REQUEST_ID_HEADER};
+use rmoods_backend::websocket::{self, SystemMessage};
+use rmoods_backend::{metrics, monitor, AppState};
+use sqlx::postgres::PgPoolOptions;
 use std::net::SocketAddr;
+use std::sync::Arc;
 use tower_http::{
     cors::{Any, CorsLayer},
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
     trace::TraceLayer,
 };
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
 
-mod api;
-mod app_error;
-mod open_api;
-mod reddit_fetcher;
-mod startup;
-mod websocket;
-
-/// State to be shared between all routes.
-///
-/// Contains common resources that shouldn't be created over and over again.
-#[derive(Clone)]
-pub struct AppState {
-    pub fetcher: RMoodsFetcher,
-    pub pool: Pool<Postgres>,
-    pub http: Client,
-    pub system_tx: tokio::sync::mpsc::Sender<SystemMessage>,
-}
-
-/// Run the server, assuming the environment has been already validated.
-async fn run() -> anyhow::Result<()> {
-    let url = std::env::var("DATABASE_URL").expect("DB_URL is set");
+/// Run the server with the validated config.
+async fn run(config: Config) -> anyhow::Result<()> {
     let pool = PgPoolOptions::new()
-        .max_connections(10)
-        .connect(&url)
+        .max_connections(config.database.pool_size)
+        .connect(&config.database.url)
         .await?;
     info!("Connected to the database");
 
+    sqlx::migrate!().run(&pool).await?;
+    info!("Database migrations applied");
+
     let http = reqwest::ClientBuilder::new().user_agent("RMoods").build()?;
-    let fetcher = RMoodsFetcher::new(http.clone()).await?;
+    let fetcher = RMoodsFetcher::new(http.clone(), &config.reddit).await?;
     info!("Connected to Reddit");
 
+    let fetcher = match ResponseCache::from_config(&config.reddit, &pool) {
+        Some(cache) => fetcher.with_cache(cache),
+        None => fetcher,
+    };
+    let fetcher = fetcher.with_corpus(Corpus::new(pool.clone()));
+
+    let nlp = NlpClient::new(http.clone(), &config.nlp.url);
+
     info!("Starting the WebSocket service");
     let cancellation_token = tokio_util::sync::CancellationToken::new();
 
@@ -60,19 +59,33 @@ async fn run() -> anyhow::Result<()> {
         fetcher,
         pool,
         http,
+        nlp,
         system_tx,
+        config: Arc::new(config),
     };
 
-    // Allow browsers to use GET and PUT from any origin
-    let cors =
-        CorsLayer::new()
-            .allow_origin(Any)
-            .allow_headers([AUTHORIZATION, ACCEPT, CONTENT_TYPE]);
+    info!("Starting the snapshot scheduler");
+    tokio::spawn(monitor::start_scheduler(
+        state.clone(),
+        cancellation_token.clone(),
+    ));
+
+    // Allow browsers to use GET and PUT from the configured origins, or any origin
+    let port = state.config.server.port;
+    let origins = state.config.server.cors_origins();
+    let cors = CorsLayer::new().allow_headers([AUTHORIZATION, ACCEPT, CONTENT_TYPE]);
+    let cors = if origins.is_empty() {
+        cors.allow_origin(Any)
+    } else {
+        cors.allow_origin(origins)
+    };
 
-    // Add logging
-    let tracing = TraceLayer::new_for_http();
+    // Add logging, every request gets a span with its ID
+    let tracing = TraceLayer::new_for_http().make_span_with(telemetry::request_span);
+    let request_id = HeaderName::from_static(REQUEST_ID_HEADER);
 
-    let authorization = axum::middleware::from_fn(auth::middleware::authorization);
+    let authorization =
+        axum::middleware::from_fn_with_state(state.clone(), auth::middleware::authorization);
 
     // Routes after the layers won't have the layers applied
     // Example: /auth routes won't have the authorization layer, but /api will
@@ -81,13 +94,16 @@ async fn run() -> anyhow::Result<()> {
         .nest("/ws", websocket::router())
         .layer(authorization)
         .nest("/auth", auth::router())
+        .merge(metrics::router())
         .with_state(state)
+        .layer(axum::middleware::from_fn(metrics::track_http))
         .layer(tracing)
+        .layer(PropagateRequestIdLayer::new(request_id.clone()))
+        .layer(SetRequestIdLayer::new(request_id, MakeRequestUuid))
         .layer(cors)
         .merge(SwaggerUi::new("/doc/ui").url("/doc/api.json", ApiDoc::openapi()))
         .into_make_service_with_connect_info::<SocketAddr>();
 
-    let port = std::env::var("PORT").unwrap_or_else(|_| "8001".to_string());
     // Listen on all addresses
     let addr = format!("0.0.0.0:{port}");
 
@@ -102,26 +118,41 @@ async fn run() -> anyhow::Result<()> {
 }
 
 /// Entry point of the RMoods server.
-/// Validates the environment, initializes the server and runs it.
+/// Loads and validates the config, initializes the server and runs it.
 #[tokio::main]
 async fn main() {
-    std::env::set_var("RUST_LOG", "debug");
     std::env::set_var("RUST_BACKTRACE", "0");
-    env_logger::init();
+    let dotenv = dotenvy::dotenv();
+    let config = Config::load();
+
+    // Problems with the config are logged as text, at the info level
+    let telemetry = match &config {
+        Ok(config) => Telemetry::init(&config.server),
+        Err(_) => Telemetry::init(&ServerConfig {
+            log_level: "info".to_string(),
+            ..Default::default()
+        }),
+    };
 
-    if dotenvy::dotenv().is_err() {
+    if dotenv.is_err() {
         warn!(".env not found. Environment variables will have to be defined outside of .env");
     }
+    let config = match config {
+        Ok(config) => config,
+        Err(e) => {
+            error!("{e}");
+            error!("Invalid configuration, aborting.");
+            std::process::exit(1);
+        }
+    };
+    info!("Configuration OK");
 
-    if !verify_environment() {
-        error!("Invalid environment, aborting.");
-        std::process::exit(1);
-    }
-    info!("Environment OK");
-
-    let res = run().await;
-    if let Err(e) = res {
+    let res = run(config).await;
+    if let Err(e) = &res {
         log::error!("{e}");
+    }
+    telemetry.shutdown().await;
+    if res.is_err() {
         std::process::exit(1);
     }
 }
@@ -0,0 +1,215 @@
+//! Prometheus metrics of the backend, served at `GET /metrics`.
+//!
+//! Metrics are registered in the default registry the first time they're used.
+//! Labels are kept to a small set of values, eg. routes and Reddit endpoints are recorded
+//! as templates like `/r/{subreddit}/about`, never with the names filled in.
+
+use crate::AppState;
+use axum::extract::{MatchedPath, Request};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use http::header::CONTENT_TYPE;
+use http::StatusCode;
+use lazy_static::lazy_static;
+use log::error;
+use prometheus::{
+    register_gauge, register_histogram, register_histogram_vec, register_int_counter,
+    register_int_counter_vec, register_int_gauge, Encoder, Gauge, Histogram, HistogramVec,
+    IntCounter, IntCounterVec, IntGauge, TextEncoder,
+};
+use std::time::Instant;
+
+lazy_static! {
+    pub static ref HTTP_REQUESTS: IntCounterVec = register_int_counter_vec!(
+        "rmoods_http_requests_total",
+        "HTTP requests handled, by method, route and status",
+        &["method", "route", "status"]
+    )
+    .unwrap();
+    pub static ref HTTP_REQUEST_DURATION: HistogramVec = register_histogram_vec!(
+        "rmoods_http_request_duration_seconds",
+        "Time to respond to HTTP requests, by method and route",
+        &["method", "route"]
+    )
+    .unwrap();
+    pub static ref REDDIT_REQUESTS: IntCounterVec = register_int_counter_vec!(
+        "rmoods_reddit_requests_total",
+        "Requests sent to the Reddit API, by endpoint and status, `error` if there was no response",
+        &["endpoint", "status"]
+    )
+    .unwrap();
+    pub static ref REDDIT_RATELIMIT_REMAINING: Gauge = register_gauge!(
+        "rmoods_reddit_ratelimit_remaining",
+        "Reddit requests left in the current period, from the headers of the last response"
+    )
+    .unwrap();
+    pub static ref REDDIT_CACHE_LOOKUPS: IntCounterVec = register_int_counter_vec!(
+        "rmoods_reddit_cache_lookups_total",
+        "Reddit requests looked up in the response cache, by result: hit, miss or bypass",
+        &["result"]
+    )
+    .unwrap();
+    pub static ref REDDIT_SKIPPED_ITEMS: IntCounter = register_int_counter!(
+        "rmoods_reddit_skipped_items_total",
+        "Listing items of unknown kinds skipped, a growing number means Reddit added an object type worth modeling"
+    )
+    .unwrap();
+    pub static ref WEBSOCKET_PEERS: IntGauge =
+        register_int_gauge!("rmoods_websocket_peers", "Open WebSocket connections").unwrap();
+    pub static ref REPORT_JOBS_IN_FLIGHT: IntGauge = register_int_gauge!(
+        "rmoods_report_jobs_in_progress",
+        "In-flight report jobs, started and not finished yet"
+    )
+    .unwrap();
+    pub static ref REPORT_DURATION: Histogram = register_histogram!(
+        "rmoods_report_duration_seconds",
+        "Time to analyze the items of a report",
+        vec![0.1, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0]
+    )
+    .unwrap();
+    pub static ref SNAPSHOTS: IntCounterVec = register_int_counter_vec!(
+        "rmoods_snapshots_total",
+        "Monitor snapshots taken by the scheduler, by outcome, `error` if it will be retried",
+        &["outcome"]
+    )
+    .unwrap();
+    pub static ref NLP_REQUEST_DURATION: HistogramVec = register_histogram_vec!(
+        "rmoods_nlp_request_duration_seconds",
+        "Time for the NLP service to analyze a batch of texts, by outcome",
+        &["outcome"],
+        vec![0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0]
+    )
+    .unwrap();
+}
+
+/// Defines the metrics route, it should stay outside of the authorization layer.
+pub fn router() -> axum::Router<AppState> {
+    axum::Router::new().route("/metrics", get(metrics))
+}
+
+/// All metrics in the Prometheus text format.
+async fn metrics() -> Response {
+    match render() {
+        Ok(text) => ([(CONTENT_TYPE, TextEncoder::new().format_type())], text).into_response(),
+        Err(e) => {
+            error!("Failed to encode the metrics: {e}");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Encode the registered metrics in the Prometheus text format.
+pub fn render() -> prometheus::Result<String> {
+    let mut buffer = vec![];
+    TextEncoder::new().encode(&prometheus::gather(), &mut buffer)?;
+    Ok(String::from_utf8_lossy(&buffer).into_owned())
+}
+
+/// Middleware counting the requests and timing the responses of every route.
+///
+/// Routes are recorded as they were defined, eg. `/api/corpus/r/:subreddit/posts`,
+/// requests without a matched route as `unmatched`, so random paths don't create new series.
+pub async fn track_http(request: Request, next: Next) -> Response {
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or("unmatched", MatchedPath::as_str)
+        .to_string();
+
+    let start = Instant::now();
+    let response = next.run(request).await;
+
+    HTTP_REQUEST_DURATION
+        .with_label_values(&[&method, &route])
+        .observe(start.elapsed().as_secs_f64());
+    HTTP_REQUESTS
+        .with_label_values(&[&method, &route, response.status().as_str()])
+        .inc();
+    response
+}
+
+/// Counts a report as in progress until dropped, then records how long it took.
+///
+/// Reports that fail are timed as well, the NLP service timing out is worth seeing.
+pub struct ReportJob {
+    start: Instant,
+}
+
+impl ReportJob {
+    pub fn start() -> Self {
+        REPORT_JOBS_IN_FLIGHT.inc();
+        Self {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Drop for ReportJob {
+    fn drop(&mut self) {
+        REPORT_JOBS_IN_FLIGHT.dec();
+        REPORT_DURATION.observe(self.start.elapsed().as_secs_f64());
+    }
+}
+
+/// Reddit API path with the subreddit, user and post names replaced by placeholders,
+/// eg. `/r/Polska/comments/1eubxgg.json` becomes `/r/{subreddit}/comments/{post_id}`.
+pub fn reddit_endpoint(path: &str) -> String {
+    let path = path.split('?').next().unwrap_or_default();
+    let path = path.trim_end_matches(".json");
+    let mut previous = "";
+    path.split('/')
+        .map(|segment| {
+            let label = match previous {
+                "r" => "{subreddit}",
+                "user" => "{username}",
+                "comments" => "{post_id}",
+                _ => segment,
+            };
+            previous = segment;
+            label
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reddit_endpoint() {
+        assert_eq!(reddit_endpoint("/r/Polska/new.json"), "/r/{subreddit}/new");
+        assert_eq!(
+            reddit_endpoint("/r/Polska+poland/comments.json"),
+            "/r/{subreddit}/comments"
+        );
+        assert_eq!(
+            reddit_endpoint("/r/Polska/comments/1eubxgg.json"),
+            "/r/{subreddit}/comments/{post_id}"
+        );
+        assert_eq!(reddit_endpoint("/user/spez.json"), "/user/{username}");
+        assert_eq!(
+            reddit_endpoint("/user/spez/about.json"),
+            "/user/{username}/about"
+        );
+        assert_eq!(reddit_endpoint("/search.json"), "/search");
+        assert_eq!(reddit_endpoint("/api/morechildren"), "/api/morechildren");
+    }
+
+    #[test]
+    fn test_render() {
+        REDDIT_REQUESTS
+            .with_label_values(&["/r/{subreddit}/about", "200"])
+            .inc();
+        drop(ReportJob::start());
+
+        let text = render().unwrap();
+        assert!(text.contains(
+            r#"rmoods_reddit_requests_total{endpoint="/r/{subreddit}/about",status="200"}"#
+        ));
+        assert!(text.contains("rmoods_report_duration_seconds_count"));
+        assert!(text.contains("# TYPE rmoods_report_jobs_in_progress gauge"));
+    }
+}
@@ -0,0 +1,328 @@
+use crate::monitor::error::MonitorError;
+use crate::monitor::store::{self, Monitor, Schedule, Snapshot};
+use crate::monitor::webhook::WebhookSender;
+use crate::websocket::SystemMessage;
+use crate::AppState;
+use chrono::{DateTime, Duration, Utc};
+use log::{error, info};
+use log_derive::logfn;
+use serde::{Deserialize, Serialize};
+use sqlx::{types::Json, FromRow};
+use tracing::Instrument;
+
+/// Snapshot metric watched by an alert rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "alert_metric", rename_all = "snake_case")]
+pub enum AlertMetric {
+    /// Number of new posts
+    ItemCount,
+    MeanScore,
+    SentimentMean,
+    NegativeShare,
+    ToxicityRate,
+}
+
+impl AlertMetric {
+    /// Read the metric from a snapshot. `None` if the snapshot's reports didn't compute it.
+    pub fn value(&self, snapshot: &Snapshot) -> Option<f64> {
+        match self {
+            AlertMetric::ItemCount => Some(snapshot.item_count as f64),
+            AlertMetric::MeanScore => Some(snapshot.mean_score),
+            AlertMetric::SentimentMean => snapshot.sentiment_mean,
+            AlertMetric::NegativeShare => snapshot.negative_share,
+            AlertMetric::ToxicityRate => snapshot.toxicity_rate,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Comparison {
+    Above,
+    Below,
+}
+
+/// When does an alert rule fire?
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AlertCondition {
+    /// The metric was above (or below) `value` in each of the last `snapshots` snapshots.
+    ///
+    /// eg. negative sentiment share above 0.4 over 3 snapshots.
+    Threshold {
+        comparison: Comparison,
+        value: f64,
+        snapshots: u32,
+    },
+    /// The metric in the latest snapshot is at least `factor` times its mean over the
+    /// preceding `baseline_days` days.
+    ///
+    /// eg. hate speech rate doubled (`factor` 2) vs. the 7-day baseline.
+    BaselineRatio { factor: f64, baseline_days: u32 },
+}
+
+impl AlertCondition {
+    /// Check that the condition can ever be evaluated.
+    pub fn validate(&self) -> Result<(), MonitorError> {
+        let problem = match self {
+            AlertCondition::Threshold { snapshots: 0, .. } => "`snapshots` must be at least 1",
+            AlertCondition::BaselineRatio { factor, .. } if *factor <= 0.0 => {
+                "`factor` must be positive"
+            }
+            AlertCondition::BaselineRatio {
+                baseline_days: 0, ..
+            } => "`baseline_days` must be at least 1",
+            _ => return Ok(()),
+        };
+        Err(MonitorError::InvalidAlertRule(problem.to_string()))
+    }
+
+    /// How far back do the snapshots needed to evaluate the condition go.
+    pub fn lookback(&self, schedule: Schedule) -> Duration {
+        match self {
+            AlertCondition::Threshold { snapshots, .. } => {
+                schedule.interval() * (*snapshots as i32 + 1)
+            }
+            AlertCondition::BaselineRatio { baseline_days, .. } => {
+                Duration::days(*baseline_days as i64) + schedule.interval()
+            }
+        }
+    }
+
+    /// Evaluate the condition on the metric's series, oldest snapshot first.
+    /// Returns the latest value of the metric if the condition holds.
+    pub fn evaluate(&self, metric: AlertMetric, series: &[Snapshot]) -> Option<f64> {
+        let latest = series.last()?;
+        let latest_value = metric.value(latest)?;
+
+        match self {
+            AlertCondition::Threshold {
+                comparison,
+                value,
+                snapshots,
+            } => {
+                let window = series.len().checked_sub(*snapshots as usize)?;
+                let holds = series[window..].iter().all(|s| match metric.value(s) {
+                    Some(v) => match comparison {
+                        Comparison::Above => v > *value,
+                        Comparison::Below => v < *value,
+                    },
+                    None => false,
+                });
+                holds.then_some(latest_value)
+            }
+            AlertCondition::BaselineRatio {
+                factor,
+                baseline_days,
+            } => {
+                let baseline_start = latest.taken_at - Duration::days(*baseline_days as i64);
+                let baseline = series[..series.len() - 1]
+                    .iter()
+                    .filter(|s| s.taken_at >= baseline_start)
+                    .filter_map(|s| metric.value(s))
+                    .collect::<Vec<_>>();
+                if baseline.is_empty() {
+                    return None;
+                }
+                let mean = baseline.iter().sum::<f64>() / baseline.len() as f64;
+                // A zero baseline can't be multiplied, any value would fire the rule
+                (mean > 0.0 && latest_value >= factor * mean).then_some(latest_value)
+            }
+        }
+    }
+}
+
+/// A rule watching one metric of a monitored subreddit.
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct AlertRule {
+    pub id: i64,
+    pub monitor_id: i64,
+    pub metric: AlertMetric,
+    pub condition: Json<AlertCondition>,
+    /// Fired alerts are POSTed to this URL
+    pub webhook_url: String,
+    /// Key used to sign the webhook payloads. Only shown to the user when the rule is created.
+    #[serde(skip_serializing)]
+    pub secret: String,
+    /// Did the condition hold after the last snapshot?
+    pub firing: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Sent to the webhook and to the owner's WebSocket connections when a rule fires.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertPayload {
+    pub rule_id: i64,
+    pub monitor_id: i64,
+    /// eg. Polska
+    pub subreddit: String,
+    pub metric: AlertMetric,
+    /// Latest value of the metric
+    pub value: f64,
+    pub condition: AlertCondition,
+    pub fired_at: DateTime<Utc>,
+}
+
+/// Evaluate the monitor's alert rules after a snapshot.
+///
+/// A rule fires only when its condition starts holding, not on every snapshot while it holds.
+#[logfn(err = "ERROR", fmt = "Failed to evaluate alert rules: {0}")]
+pub async fn evaluate_rules(state: &AppState, monitor: &Monitor) -> Result<(), MonitorError> {
+    let rules = store::alert_rules(&state.pool, monitor.id).await?;
+    let Some(lookback) = rules
+        .iter()
+        .map(|r| r.condition.lookback(monitor.schedule))
+        .max()
+    else {
+        return Ok(());
+    };
+
+    let series =
+        store::snapshots(&state.pool, monitor.id, Some(Utc::now() - lookback), None).await?;
+
+    for rule in rules {
+        let value = rule.condition.evaluate(rule.metric, &series);
+        if value.is_some() != rule.firing {
+            store::set_alert_rule_firing(&state.pool, rule.id, value.is_some()).await?;
+        }
+        if let (Some(value), false) = (value, rule.firing) {
+            fire(state, monitor, rule, value).await;
+        }
+    }
+    Ok(())
+}
+
+/// Push the alert to the owner's WebSocket connections and deliver it to the rule's webhook.
+async fn fire(state: &AppState, monitor: &Monitor, rule: AlertRule, value: f64) {
+    info!(
+        "Alert rule {} of r/{} fired: {:?} = {value}",
+        rule.id, monitor.subreddit, rule.metric
+    );
+    let payload = AlertPayload {
+        rule_id: rule.id,
+        monitor_id: monitor.id,
+        subreddit: monitor.subreddit.clone(),
+        metric: rule.metric,
+        value,
+        condition: rule.condition.0.clone(),
+        fired_at: Utc::now(),
+    };
+
+    let res = state
+        .system_tx
+        .send(SystemMessage::Alert((
+            monitor.owner.clone(),
+            payload.clone(),
+        )))
+        .await;
+    if let Err(e) = res {
+        error!("Failed to push the alert to the WebSocket service: {:?}", e);
+    }
+
+    // Retries can take a while, don't hold up the other snapshots
+    let sender = WebhookSender::new();
+    let pool = state.pool.clone();
+    tokio::spawn(
+        async move {
+            let attempts = sender
+                .deliver(&rule.webhook_url, &rule.secret, &payload)
+                .await;
+            if let Err(e) =
+                store::insert_alert_deliveries(&pool, rule.id, &payload, &attempts).await
+            {
+                error!(
+                    "Failed to store delivery attempts of alert rule {}: {e}",
+                    rule.id
+                );
+            }
+        }
+        .in_current_span(),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(hours_ago: i64, negative_share: Option<f64>) -> Snapshot {
+        Snapshot {
+            taken_at: Utc::now() - Duration::hours(hours_ago),
+            item_count: 10,
+            mean_score: 1.0,
+            sentiment_mean: None,
+            negative_share,
+            toxicity_rate: None,
+        }
+    }
+
+    fn above(value: f64, snapshots: u32) -> AlertCondition {
+        AlertCondition::Threshold {
+            comparison: Comparison::Above,
+            value,
+            snapshots,
+        }
+    }
+
+    #[test]
+    fn test_threshold_holds_over_all_snapshots() {
+        let series = [
+            snapshot(4, Some(0.1)),
+            snapshot(3, Some(0.5)),
+            snapshot(2, Some(0.45)),
+            snapshot(1, Some(0.6)),
+        ];
+        let metric = AlertMetric::NegativeShare;
+        assert_eq!(above(0.4, 3).evaluate(metric, &series), Some(0.6));
+        assert_eq!(above(0.4, 4).evaluate(metric, &series), None);
+        assert_eq!(above(0.4, 5).evaluate(metric, &series), None);
+    }
+
+    #[test]
+    fn test_threshold_needs_the_metric() {
+        let series = [snapshot(2, Some(0.5)), snapshot(1, None)];
+        assert_eq!(
+            above(0.4, 2).evaluate(AlertMetric::NegativeShare, &series),
+            None
+        );
+    }
+
+    #[test]
+    fn test_baseline_ratio() {
+        let doubled = AlertCondition::BaselineRatio {
+            factor: 2.0,
+            baseline_days: 7,
+        };
+        let metric = AlertMetric::NegativeShare;
+
+        let series = [
+            // Outside of the baseline window
+            snapshot(24 * 10, Some(0.5)),
+            snapshot(48, Some(0.1)),
+            snapshot(24, Some(0.2)),
+            snapshot(1, Some(0.35)),
+        ];
+        assert_eq!(doubled.evaluate(metric, &series), Some(0.35));
+
+        let series = [snapshot(48, Some(0.2)), snapshot(1, Some(0.3))];
+        assert_eq!(doubled.evaluate(metric, &series), None);
+
+        let series = [snapshot(48, Some(0.0)), snapshot(1, Some(0.3))];
+        assert_eq!(doubled.evaluate(metric, &series), None);
+
+        assert_eq!(doubled.evaluate(metric, &[snapshot(1, Some(0.3))]), None);
+    }
+
+    #[test]
+    fn test_condition_validation() {
+        assert!(above(0.4, 0).validate().is_err());
+        assert!(above(0.4, 1).validate().is_ok());
+        let ratio = |factor, baseline_days| AlertCondition::BaselineRatio {
+            factor,
+            baseline_days,
+        };
+        assert!(ratio(0.0, 7).validate().is_err());
+        assert!(ratio(2.0, 0).validate().is_err());
+        assert!(ratio(2.0, 7).validate().is_ok());
+    }
+}
@@ -0,0 +1,31 @@
+use crate::reddit_fetcher::fetcher_error::FetcherError;
+use crate::report::error::ReportError;
+use thiserror::Error;
+
+/// Represents any kind of error that can occur when managing monitors or taking snapshots.
+#[derive(Debug, Error)]
+pub enum MonitorError {
+    /// The user has no monitor with that ID.
+    #[error("Monitor not found: {0}")]
+    MonitorNotFound(i64),
+
+    /// The monitor has no alert rule with that ID.
+    #[error("Alert rule not found: {0}")]
+    AlertRuleNotFound(i64),
+
+    /// The alert rule can't be created as requested.
+    #[error("Invalid alert rule: {0}")]
+    InvalidAlertRule(String),
+
+    /// A query to the database failed.
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+
+    /// Fetching the data for the snapshot failed.
+    #[error("Failed to fetch snapshot data: {0}")]
+    FetcherError(#[from] FetcherError),
+
+    /// Analyzing the fetched data failed.
+    #[error("Failed to generate snapshot report: {0}")]
+    ReportError(#[from] ReportError),
+}
@@ -0,0 +1,153 @@
+//! Subreddit time-series monitoring.
+//!
+//! Users register subreddits to be snapshotted on a schedule. Every snapshot fetches the posts
+//! created since the previous one, generates the configured reports and stores the aggregated
+//! metrics, which are later served as a time series. After every snapshot the monitor's alert
+//! rules are evaluated, see [alert].
+
+use crate::reddit_fetcher::feed_request::{
+    DataSource, FetcherFeedRequest, RedditFeedKind, RequestSize,
+};
+use crate::reddit_fetcher::model::posts::Posts;
+use crate::reddit_fetcher::model::reddit_data::RedditFeedData;
+use crate::reddit_fetcher::reddit::request::params::FeedSorting;
+use crate::report::{Report, ReportItem};
+use crate::{metrics, telemetry, AppState};
+use chrono::Utc;
+use error::MonitorError;
+use log::{error, info};
+use log_derive::logfn;
+use store::Monitor;
+use tokio_util::sync::CancellationToken;
+use tracing::instrument;
+
+pub mod alert;
+pub mod error;
+pub mod store;
+pub mod webhook;
+
+/// How often the scheduler looks for monitors that are due for a snapshot.
+const SCHEDULER_TICK: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// How often the scheduler deletes the stale responses of the Reddit response cache.
+const CACHE_PURGE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+/// Maximum number of Reddit requests a single snapshot can make.
+const SNAPSHOT_BUDGET: u16 = 10;
+
+/// Starts and maintains the snapshot scheduler.
+///
+/// Every [SCHEDULER_TICK] the scheduler takes a snapshot of every monitor that is due.
+/// Snapshots are taken one after another, so they share the Reddit request quota fairly.
+/// Every [CACHE_PURGE_INTERVAL], starting right away, it also purges the response cache.
+pub async fn start_scheduler(mut state: AppState, cancellation_token: CancellationToken) {
+    let mut interval = tokio::time::interval(SCHEDULER_TICK);
+    let mut purge_interval = tokio::time::interval(CACHE_PURGE_INTERVAL);
+
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                if let Err(e) = run_due_snapshots(&mut state).await {
+                    error!("Scheduler failed to load monitors: {e}");
+                }
+            }
+            _ = purge_interval.tick() => {
+                state.fetcher.purge_expired_cache().await;
+            }
+            _ = cancellation_token.cancelled() => {
+                info!("Snapshot scheduler shut down");
+                return;
+            }
+        }
+    }
+}
+
+/// Take a snapshot of every monitor that is due.
+/// A failed snapshot doesn't stop the others, it's retried later with a growing delay.
+async fn run_due_snapshots(state: &mut AppState) -> Result<(), MonitorError> {
+    let now = Utc::now();
+    let due = store::all_monitors(&state.pool)
+        .await?
+        .into_iter()
+        .filter(|m| m.is_due(now))
+        .collect::<Vec<_>>();
+
+    if !due.is_empty() {
+        info!("{} monitors due for a snapshot", due.len());
+    }
+
+    for monitor in due {
+        match take_snapshot(state, &monitor).await {
+            Ok(()) => {
+                metrics::SNAPSHOTS.with_label_values(&["ok"]).inc();
+                let _ = alert::evaluate_rules(state, &monitor).await;
+            }
+            Err(e) => {
+                metrics::SNAPSHOTS.with_label_values(&["error"]).inc();
+                let retry_at = Utc::now() + monitor.retry_delay();
+                error!(
+                    "Snapshot of r/{} (monitor {}) failed, retrying at {retry_at}: {e}",
+                    monitor.subreddit, monitor.id
+                );
+                let error = e.to_string();
+                let res = store::record_failure(&state.pool, monitor.id, &error, retry_at).await;
+                if let Err(e) = res {
+                    error!("Failed to record the failed snapshot: {e}");
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Fetch the posts created since the monitor's last run, generate its reports and store
+/// the aggregated metrics.
+#[logfn(err = "ERROR", fmt = "Failed to take snapshot: {0}")]
+#[instrument(
+    name = "snapshot",
+    skip_all,
+    fields(job_id = telemetry::job_id(), monitor = monitor.id, subreddit = monitor.subreddit)
+)]
+pub async fn take_snapshot(state: &mut AppState, monitor: &Monitor) -> Result<(), MonitorError> {
+    info!(
+        "Taking snapshot of r/{} (monitor {})",
+        monitor.subreddit, monitor.id
+    );
+    let taken_at = Utc::now();
+
+    let request = FetcherFeedRequest {
+        resource_kind: RedditFeedKind::SubredditPosts,
+        report_types: monitor.report_types.to_vec(),
+        data_sources: vec![DataSource {
+            name: monitor.subreddit.clone(),
+            post_id: None,
+            share: 1.0,
+        }],
+        size: RequestSize::Custom(SNAPSHOT_BUDGET),
+        sorting: FeedSorting::New,
+        since: monitor.since(),
+        search: None,
+        user_filter: Default::default(),
+    };
+    // The fetcher stops at the posts seen by the previous snapshot.
+    // The first snapshot takes everything the budget allows.
+    let (posts, _) = state.fetcher.fetch_feed::<Posts>(request).await?;
+    let cursor = posts.watermark().or_else(|| monitor.since());
+    let items = posts.list.iter().map(ReportItem::from).collect::<Vec<_>>();
+
+    let report = Report::generate(&state.nlp, items, &monitor.report_types).await?;
+    store::insert_snapshot(
+        &state.pool,
+        monitor.id,
+        taken_at,
+        &report.summary,
+        cursor.as_ref(),
+    )
+    .await?;
+
+    info!(
+        "Snapshot of r/{} done: {} new posts",
+        monitor.subreddit, report.summary.item_count
+    );
+    Ok(())
+}
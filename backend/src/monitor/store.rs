@@ -0,0 +1,434 @@
+use crate::monitor::alert::{AlertCondition, AlertMetric, AlertPayload, AlertRule};
+use crate::monitor::webhook::DeliveryAttempt;
+use crate::reddit_fetcher::feed_request::{FeedCursor, RMoodsReportType};
+use crate::report::summary::ReportSummary;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{types::Json, FromRow, PgPool};
+
+/// How often a monitored subreddit is snapshotted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "monitor_schedule", rename_all = "lowercase")]
+pub enum Schedule {
+    Hourly,
+    Daily,
+}
+
+impl Schedule {
+    /// Time between two consecutive snapshots.
+    pub fn interval(&self) -> Duration {
+        match self {
+            Schedule::Hourly => Duration::hours(1),
+            Schedule::Daily => Duration::days(1),
+        }
+    }
+}
+
+/// A subreddit registered to be snapshotted on a schedule.
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct Monitor {
+    pub id: i64,
+    /// Google ID of the user who registered the monitor
+    pub owner: String,
+    /// eg. Polska
+    pub subreddit: String,
+    pub schedule: Schedule,
+    /// Reports computed for every snapshot
+    pub report_types: Json<Vec<RMoodsReportType>>,
+    /// When was the last snapshot taken, `None` if never
+    pub last_run_at: Option<DateTime<Utc>>,
+    /// Newest post seen by the last snapshot, the next one only fetches posts newer than that
+    pub cursor: Option<Json<FeedCursor>>,
+    /// Snapshots that failed in a row since the last successful one
+    pub failures: i32,
+    pub last_error: Option<String>,
+    /// When to retry the failed snapshot, see [Monitor::retry_delay]
+    pub retry_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Monitor {
+    /// Delay before retrying the first failed snapshot, doubled after every failure.
+    const FIRST_RETRY_DELAY: Duration = Duration::minutes(5);
+
+    /// Is it time to take the next snapshot?
+    ///
+    /// After a failure, the snapshot is retried at `retry_at` instead.
+    pub fn is_due(&self, now: DateTime<Utc>) -> bool {
+        if let Some(retry_at) = self.retry_at {
+            return retry_at <= now;
+        }
+        match self.last_run_at {
+            Some(last_run_at) => last_run_at + self.schedule.interval() <= now,
+            None => true,
+        }
+    }
+
+    /// How long to wait after the snapshot failed once more, at most the interval of the schedule.
+    pub fn retry_delay(&self) -> Duration {
+        let doublings = self.failures.clamp(0, 16) as u32;
+        (Self::FIRST_RETRY_DELAY * 2_i32.pow(doublings)).min(self.schedule.interval())
+    }
+
+    /// Cursor to fetch the posts created since the last snapshot.
+    ///
+    /// Monitors that never stored a cursor fall back to the time of their last run.
+    pub fn since(&self) -> Option<FeedCursor> {
+        match (&self.cursor, self.last_run_at) {
+            (Some(cursor), _) => Some(cursor.0.clone()),
+            (None, Some(last_run_at)) => Some(FeedCursor::at(None, last_run_at)),
+            (None, None) => None,
+        }
+    }
+}
+
+/// A single point of a monitored subreddit's time series.
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct Snapshot {
+    pub taken_at: DateTime<Utc>,
+    /// Activity - number of posts created since the previous snapshot
+    pub item_count: i32,
+    pub mean_score: f64,
+    pub sentiment_mean: Option<f64>,
+    /// Share of posts with negative sentiment
+    pub negative_share: Option<f64>,
+    /// Share of posts detected as hate speech
+    pub toxicity_rate: Option<f64>,
+}
+
+const MONITOR_COLUMNS: &str =
+    "id, owner, subreddit, schedule, report_types, last_run_at, cursor, failures, last_error, retry_at, created_at";
+
+/// Register a new monitor.
+pub async fn insert_monitor(
+    pool: &PgPool,
+    owner: &str,
+    subreddit: &str,
+    schedule: Schedule,
+    report_types: &[RMoodsReportType],
+) -> Result<Monitor, sqlx::Error> {
+    sqlx::query_as(&format!(
+        "INSERT INTO monitored_subreddits (owner, subreddit, schedule, report_types)
+         VALUES ($1, $2, $3, $4)
+         RETURNING {MONITOR_COLUMNS}"
+    ))
+    .bind(owner)
+    .bind(subreddit)
+    .bind(schedule)
+    .bind(Json(report_types))
+    .fetch_one(pool)
+    .await
+}
+
+/// Get a monitor of the given owner.
+pub async fn get_monitor(
+    pool: &PgPool,
+    owner: &str,
+    id: i64,
+) -> Result<Option<Monitor>, sqlx::Error> {
+    sqlx::query_as(&format!(
+        "SELECT {MONITOR_COLUMNS} FROM monitored_subreddits WHERE id = $1 AND owner = $2"
+    ))
+    .bind(id)
+    .bind(owner)
+    .fetch_optional(pool)
+    .await
+}
+
+/// List all monitors of the given owner.
+pub async fn list_monitors(pool: &PgPool, owner: &str) -> Result<Vec<Monitor>, sqlx::Error> {
+    sqlx::query_as(&format!(
+        "SELECT {MONITOR_COLUMNS} FROM monitored_subreddits WHERE owner = $1 ORDER BY id"
+    ))
+    .bind(owner)
+    .fetch_all(pool)
+    .await
+}
+
+/// List the monitors of all users. Used by the scheduler.
+pub async fn all_monitors(pool: &PgPool) -> Result<Vec<Monitor>, sqlx::Error> {
+    sqlx::query_as(&format!(
+        "SELECT {MONITOR_COLUMNS} FROM monitored_subreddits ORDER BY id"
+    ))
+    .fetch_all(pool)
+    .await
+}
+
+/// Delete a monitor with all its snapshots. Returns `false` if the owner has no such monitor.
+pub async fn delete_monitor(pool: &PgPool, owner: &str, id: i64) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM monitored_subreddits WHERE id = $1 AND owner = $2")
+        .bind(id)
+        .bind(owner)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Store the snapshot, mark the monitor as run at `taken_at` and move its cursor.
+pub async fn insert_snapshot(
+    pool: &PgPool,
+    monitor_id: i64,
+    taken_at: DateTime<Utc>,
+    summary: &ReportSummary,
+    cursor: Option<&FeedCursor>,
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(
+        "INSERT INTO subreddit_snapshots
+         (monitor_id, taken_at, item_count, mean_score, sentiment_mean, negative_share, toxicity_rate, summary)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+    )
+    .bind(monitor_id)
+    .bind(taken_at)
+    .bind(summary.item_count as i32)
+    .bind(summary.mean_score)
+    .bind(summary.sentiment.as_ref().map(|s| s.mean))
+    .bind(summary.sentiment.as_ref().map(|s| s.negative_share))
+    .bind(summary.toxicity_rate())
+    .bind(Json(summary))
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        "UPDATE monitored_subreddits
+         SET last_run_at = $1, cursor = $2, failures = 0, last_error = NULL, retry_at = NULL
+         WHERE id = $3",
+    )
+    .bind(taken_at)
+    .bind(cursor.map(Json))
+    .bind(monitor_id)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await
+}
+
+/// Record that a snapshot of the monitor failed, it's retried at `retry_at`.
+pub async fn record_failure(
+    pool: &PgPool,
+    monitor_id: i64,
+    error: &str,
+    retry_at: DateTime<Utc>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "UPDATE monitored_subreddits
+         SET failures = failures + 1, last_error = $1, retry_at = $2
+         WHERE id = $3",
+    )
+    .bind(error)
+    .bind(retry_at)
+    .bind(monitor_id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Get the time series of a monitor, oldest snapshot first.
+pub async fn snapshots(
+    pool: &PgPool,
+    monitor_id: i64,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+) -> Result<Vec<Snapshot>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT taken_at, item_count, mean_score, sentiment_mean, negative_share, toxicity_rate
+         FROM subreddit_snapshots
+         WHERE monitor_id = $1
+           AND ($2::timestamptz IS NULL OR taken_at >= $2)
+           AND ($3::timestamptz IS NULL OR taken_at <= $3)
+         ORDER BY taken_at",
+    )
+    .bind(monitor_id)
+    .bind(from)
+    .bind(to)
+    .fetch_all(pool)
+    .await
+}
+
+const ALERT_RULE_COLUMNS: &str =
+    "id, monitor_id, metric, condition, webhook_url, secret, firing, created_at";
+
+/// Add an alert rule to a monitor.
+pub async fn insert_alert_rule(
+    pool: &PgPool,
+    monitor_id: i64,
+    metric: AlertMetric,
+    condition: &AlertCondition,
+    webhook_url: &str,
+    secret: &str,
+) -> Result<AlertRule, sqlx::Error> {
+    sqlx::query_as(&format!(
+        "INSERT INTO alert_rules (monitor_id, metric, condition, webhook_url, secret)
+         VALUES ($1, $2, $3, $4, $5)
+         RETURNING {ALERT_RULE_COLUMNS}"
+    ))
+    .bind(monitor_id)
+    .bind(metric)
+    .bind(Json(condition))
+    .bind(webhook_url)
+    .bind(secret)
+    .fetch_one(pool)
+    .await
+}
+
+/// List the alert rules of a monitor.
+pub async fn alert_rules(pool: &PgPool, monitor_id: i64) -> Result<Vec<AlertRule>, sqlx::Error> {
+    sqlx::query_as(&format!(
+        "SELECT {ALERT_RULE_COLUMNS} FROM alert_rules WHERE monitor_id = $1 ORDER BY id"
+    ))
+    .bind(monitor_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// Delete an alert rule with its delivery log. Returns `false` if the monitor has no such rule.
+pub async fn delete_alert_rule(
+    pool: &PgPool,
+    monitor_id: i64,
+    id: i64,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM alert_rules WHERE id = $1 AND monitor_id = $2")
+        .bind(id)
+        .bind(monitor_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Remember whether the rule's condition held after the last snapshot.
+pub async fn set_alert_rule_firing(
+    pool: &PgPool,
+    id: i64,
+    firing: bool,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE alert_rules SET firing = $1 WHERE id = $2")
+        .bind(firing)
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// A logged attempt to deliver a fired alert to the rule's webhook.
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct AlertDelivery {
+    pub attempt: i32,
+    pub attempted_at: DateTime<Utc>,
+    pub payload: Json<AlertPayload>,
+    /// HTTP status of the webhook's response, `None` if there was no response
+    pub status_code: Option<i32>,
+    pub error: Option<String>,
+    pub delivered: bool,
+}
+
+/// Log the attempts to deliver an alert.
+pub async fn insert_alert_deliveries(
+    pool: &PgPool,
+    rule_id: i64,
+    payload: &AlertPayload,
+    attempts: &[DeliveryAttempt],
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    for attempt in attempts {
+        sqlx::query(
+            "INSERT INTO alert_deliveries
+             (rule_id, attempt, attempted_at, payload, status_code, error, delivered)
+             VALUES ($1, $2, $3, $4, $5, $6, $7)",
+        )
+        .bind(rule_id)
+        .bind(attempt.attempt)
+        .bind(attempt.attempted_at)
+        .bind(Json(payload))
+        .bind(attempt.status_code)
+        .bind(&attempt.error)
+        .bind(attempt.delivered)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await
+}
+
+/// Get the delivery log of an alert rule, newest attempt first.
+pub async fn alert_deliveries(
+    pool: &PgPool,
+    rule_id: i64,
+) -> Result<Vec<AlertDelivery>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT attempt, attempted_at, payload, status_code, error, delivered
+         FROM alert_deliveries
+         WHERE rule_id = $1
+         ORDER BY attempted_at DESC, id DESC",
+    )
+    .bind(rule_id)
+    .fetch_all(pool)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitor(schedule: Schedule, last_run_at: Option<DateTime<Utc>>) -> Monitor {
+        Monitor {
+            id: 1,
+            owner: "google_id".to_string(),
+            subreddit: "Polska".to_string(),
+            schedule,
+            report_types: Json(vec![RMoodsReportType::Sentiment]),
+            last_run_at,
+            cursor: None,
+            failures: 0,
+            last_error: None,
+            retry_at: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_never_run_monitor_is_due() {
+        assert!(monitor(Schedule::Daily, None).is_due(Utc::now()));
+    }
+
+    #[test]
+    fn test_monitor_is_due_after_interval() {
+        let now = Utc::now();
+        let hourly = monitor(Schedule::Hourly, Some(now - Duration::minutes(61)));
+        let daily = monitor(Schedule::Daily, Some(now - Duration::minutes(61)));
+        assert!(hourly.is_due(now));
+        assert!(!daily.is_due(now));
+    }
+
+    #[test]
+    fn test_failed_monitor_waits_for_retry() {
+        let now = Utc::now();
+        let mut m = monitor(Schedule::Hourly, Some(now - Duration::hours(2)));
+        m.failures = 1;
+        m.retry_at = Some(now + Duration::minutes(1));
+        assert!(!m.is_due(now));
+        assert!(m.is_due(now + Duration::minutes(1)));
+    }
+
+    #[test]
+    fn test_retry_delay_backs_off_up_to_interval() {
+        let mut m = monitor(Schedule::Hourly, None);
+        assert_eq!(m.retry_delay(), Duration::minutes(5));
+        m.failures = 2;
+        assert_eq!(m.retry_delay(), Duration::minutes(20));
+        m.failures = 10;
+        assert_eq!(m.retry_delay(), Duration::hours(1));
+    }
+
+    #[test]
+    fn test_since_prefers_stored_cursor() {
+        let last_run_at = Utc::now();
+        let mut m = monitor(Schedule::Hourly, Some(last_run_at));
+        assert_eq!(m.since(), Some(FeedCursor::at(None, last_run_at)));
+
+        let cursor = FeedCursor::at(Some("t3_abc"), last_run_at - Duration::minutes(5));
+        m.cursor = Some(Json(cursor.clone()));
+        assert_eq!(m.since(), Some(cursor));
+        assert_eq!(monitor(Schedule::Hourly, None).since(), None);
+    }
+}
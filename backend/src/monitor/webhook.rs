@@ -0,0 +1,447 @@
+use crate::monitor::alert::AlertPayload;
+use crate::monitor::error::MonitorError;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use log::{info, warn};
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::Url;
+use serde::Serialize;
+use sha2::Sha256;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Header carrying the payload's signature: `sha256=<hex encoded HMAC-SHA256 of the body>`.
+/// Receivers verify it with the secret returned when the alert rule was created.
+pub const SIGNATURE_HEADER: &str = "X-RMoods-Signature";
+
+/// Time for a webhook to respond to a single attempt.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Is the address reachable from the internet? Webhooks must not reach into the server's own network.
+pub fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            // 0.0.0.0/8 and the carrier-grade NAT range 100.64.0.0/10
+            let reserved = a == 0 || (a == 100 && b & 0xc0 == 64);
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || reserved)
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public(IpAddr::V4(ip)),
+            None => {
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_unique_local()
+                    || ip.is_unicast_link_local())
+            }
+        },
+    }
+}
+
+/// Parse the URL of a webhook: HTTP(S), without a loopback, link-local or private host.
+///
+/// Host names are only checked when resolved, see [check_host].
+pub fn check_url(url: &str) -> Result<Url, MonitorError> {
+    let invalid = |reason: &str| MonitorError::InvalidAlertRule(format!("`webhook_url` {reason}"));
+
+    let url = Url::parse(url)
+        .ok()
+        .filter(|url| matches!(url.scheme(), "http" | "https"))
+        .ok_or_else(|| invalid("must be an HTTP(S) URL"))?;
+    let public = match host(&url) {
+        Some(Ok(ip)) => is_public(ip),
+        Some(Err(name)) => {
+            let name = name.trim_end_matches('.').to_lowercase();
+            name != "localhost" && !name.ends_with(".localhost")
+        }
+        None => false,
+    };
+    if !public {
+        return Err(invalid("must point at a public host"));
+    }
+    Ok(url)
+}
+
+/// Host of the URL, either an IP address or a name.
+fn host(url: &Url) -> Option<Result<IpAddr, &str>> {
+    let host = url.host_str()?;
+    let ip = host.trim_start_matches('[').trim_end_matches(']');
+    Some(ip.parse().map_err(|_| host))
+}
+
+/// Resolve the host of the URL, it must only have public addresses.
+pub async fn check_host(url: &Url) -> Result<(), MonitorError> {
+    let Some(Err(name)) = host(url) else {
+        return Ok(());
+    };
+    let port = url.port_or_known_default().unwrap_or(80);
+    public_addrs(name, port)
+        .await
+        .map(|_| ())
+        .map_err(|e| MonitorError::InvalidAlertRule(format!("`webhook_url` {e}")))
+}
+
+/// Resolve the name, failing if any of its addresses is not public.
+async fn public_addrs(name: &str, port: u16) -> Result<Vec<SocketAddr>, String> {
+    let addrs = tokio::net::lookup_host((name, port))
+        .await
+        .map_err(|e| format!("can't be resolved: {e}"))?
+        .collect::<Vec<_>>();
+    if addrs.iter().any(|addr| !is_public(addr.ip())) {
+        return Err(format!("{name} resolves to a non-public address"));
+    }
+    Ok(addrs)
+}
+
+/// Resolves the names of webhooks on every request, so a name can't be pointed
+/// at a private address after its rule was created.
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addrs = public_addrs(name.as_str(), 0).await?;
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Sign the body with the rule's secret.
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// A single attempt to deliver an alert to a webhook.
+#[derive(Debug, Clone, Serialize)]
+pub struct DeliveryAttempt {
+    /// Starts at 1
+    pub attempt: i32,
+    pub attempted_at: DateTime<Utc>,
+    /// HTTP status of the response, `None` if there was no response
+    pub status_code: Option<i32>,
+    pub error: Option<String>,
+    pub delivered: bool,
+}
+
+/// Delivers signed alert payloads to webhooks, retrying with exponential backoff.
+///
+/// Only public hosts are reached, redirects are not followed.
+#[derive(Debug, Clone)]
+pub struct WebhookSender {
+    http: reqwest::Client,
+    max_attempts: i32,
+    /// Delay before the first retry, doubled after every failed attempt
+    retry_delay: Duration,
+    /// Skip the checks of [check_url], for local test receivers
+    allow_private: bool,
+}
+
+impl WebhookSender {
+    pub fn new() -> Self {
+        let http = reqwest::Client::builder()
+            .dns_resolver(Arc::new(PublicResolver))
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .expect("Webhook client has a valid configuration");
+        Self {
+            http,
+            max_attempts: 5,
+            retry_delay: Duration::from_secs(2),
+            allow_private: false,
+        }
+    }
+
+    #[cfg(test)]
+    pub fn with_retries(mut self, max_attempts: i32, retry_delay: Duration) -> Self {
+        self.max_attempts = max_attempts;
+        self.retry_delay = retry_delay;
+        self
+    }
+
+    #[cfg(test)]
+    pub fn allow_private_hosts(mut self) -> Self {
+        self.allow_private = true;
+        self
+    }
+
+    /// POST the payload to the webhook until it responds with a success status or the attempts
+    /// run out. Returns every attempt made.
+    pub async fn deliver(
+        &self,
+        url: &str,
+        secret: &str,
+        payload: &AlertPayload,
+    ) -> Vec<DeliveryAttempt> {
+        let body = serde_json::to_vec(payload).expect("AlertPayload is always serializable");
+        let signature = sign(secret, &body);
+
+        // Rules created before the URLs were checked, retrying won't help them
+        let checked = match self.allow_private {
+            true => Ok(()),
+            false => check_url(url).map(|_| ()),
+        };
+        if let Err(e) = checked {
+            warn!(
+                "Not delivering alert of rule {} to {url}: {e}",
+                payload.rule_id
+            );
+            return vec![DeliveryAttempt {
+                attempt: 1,
+                attempted_at: Utc::now(),
+                status_code: None,
+                error: Some(e.to_string()),
+                delivered: false,
+            }];
+        }
+
+        let mut attempts = vec![];
+        let mut delay = self.retry_delay;
+
+        for attempt in 1..=self.max_attempts {
+            let attempted_at = Utc::now();
+            let res = self
+                .http
+                .post(url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .header(SIGNATURE_HEADER, &signature)
+                .timeout(REQUEST_TIMEOUT)
+                .body(body.clone())
+                .send()
+                .await;
+
+            let (status_code, error) = match res {
+                Ok(res) if res.status().is_success() => (Some(res.status().as_u16() as i32), None),
+                Ok(res) => (
+                    Some(res.status().as_u16() as i32),
+                    Some(format!("Webhook responded with {}", res.status())),
+                ),
+                Err(e) => (None, Some(describe(&e))),
+            };
+            let delivered = error.is_none();
+            attempts.push(DeliveryAttempt {
+                attempt,
+                attempted_at,
+                status_code,
+                error,
+                delivered,
+            });
+
+            if delivered {
+                info!("Alert of rule {} delivered to {url}", payload.rule_id);
+                return attempts;
+            }
+            warn!(
+                "Failed to deliver alert of rule {} to {url} (attempt {attempt}/{})",
+                payload.rule_id, self.max_attempts
+            );
+            if attempt < self.max_attempts {
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+            }
+        }
+
+        attempts
+    }
+}
+
+impl Default for WebhookSender {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The error with its causes, eg. why a name couldn't be resolved.
+fn describe(error: &reqwest::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = std::error::Error::source(error);
+    while let Some(cause) = source {
+        message.push_str(&format!(": {cause}"));
+        source = cause.source();
+    }
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monitor::alert::{AlertCondition, AlertMetric};
+    use axum::{body::Bytes, extract::State, http::HeaderMap, routing::post, Router};
+    use reqwest::StatusCode;
+    use std::sync::{Arc, Mutex};
+
+    /// Requests received by the test webhook: signature header and body.
+    type Received = Arc<Mutex<Vec<(String, Bytes)>>>;
+
+    /// Start a local webhook receiver which fails the first `failures` requests.
+    async fn start_receiver(failures: usize) -> (String, Received) {
+        let received: Received = Default::default();
+
+        let handler = move |State(received): State<Received>, headers: HeaderMap, body: Bytes| async move {
+            let signature = headers[SIGNATURE_HEADER].to_str().unwrap().to_string();
+            let mut received = received.lock().unwrap();
+            received.push((signature, body));
+            if received.len() <= failures {
+                StatusCode::SERVICE_UNAVAILABLE
+            } else {
+                StatusCode::OK
+            }
+        };
+        let app = Router::new()
+            .route("/hook", post(handler))
+            .with_state(received.clone());
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        (format!("http://{addr}/hook"), received)
+    }
+
+    fn payload() -> AlertPayload {
+        AlertPayload {
+            rule_id: 1,
+            monitor_id: 2,
+            subreddit: "Polska".to_string(),
+            metric: AlertMetric::NegativeShare,
+            value: 0.5,
+            condition: AlertCondition::BaselineRatio {
+                factor: 2.0,
+                baseline_days: 7,
+            },
+            fired_at: Utc::now(),
+        }
+    }
+
+    fn sender() -> WebhookSender {
+        WebhookSender::new()
+            .with_retries(3, Duration::from_millis(10))
+            .allow_private_hosts()
+    }
+
+    #[test]
+    fn test_sign() {
+        // Reference value computed with `openssl dgst -sha256 -hmac secret`
+        assert_eq!(
+            sign("secret", b"{}"),
+            "sha256=77325902caca812dc259733aacd046b73817372c777b8d95b402647474516e13"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_deliver_signed_payload() {
+        let (url, received) = start_receiver(0).await;
+        let payload = payload();
+
+        let attempts = sender().deliver(&url, "secret", &payload).await;
+
+        assert_eq!(attempts.len(), 1);
+        assert!(attempts[0].delivered);
+        assert_eq!(attempts[0].status_code, Some(200));
+
+        let received = received.lock().unwrap();
+        let (signature, body) = &received[0];
+        assert_eq!(signature, &sign("secret", body));
+        let delivered: AlertPayload = serde_json::from_slice(body).unwrap();
+        assert_eq!(delivered.rule_id, payload.rule_id);
+    }
+
+    #[tokio::test]
+    async fn test_deliver_retries_until_success() {
+        let (url, received) = start_receiver(2).await;
+
+        let attempts = sender().deliver(&url, "secret", &payload()).await;
+
+        assert_eq!(attempts.len(), 3);
+        assert_eq!(attempts[0].status_code, Some(503));
+        assert!(!attempts[1].delivered);
+        assert!(attempts[2].delivered);
+        assert_eq!(received.lock().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_deliver_gives_up() {
+        let (url, _) = start_receiver(usize::MAX).await;
+
+        let attempts = sender().deliver(&url, "secret", &payload()).await;
+
+        assert_eq!(attempts.len(), 3);
+        assert!(attempts.iter().all(|a| !a.delivered));
+    }
+
+    #[tokio::test]
+    async fn test_deliver_unreachable() {
+        let attempts = sender()
+            .with_retries(1, Duration::ZERO)
+            .deliver("http://127.0.0.1:1/hook", "secret", &payload())
+            .await;
+
+        assert_eq!(attempts.len(), 1);
+        assert_eq!(attempts[0].status_code, None);
+        assert!(attempts[0].error.is_some());
+    }
+
+    #[test]
+    fn test_is_public() {
+        let public = ["8.8.8.8", "2a00:1450:401b:80e::200e"];
+        let private = [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ];
+        for ip in public {
+            assert!(is_public(ip.parse().unwrap()), "{ip}");
+        }
+        for ip in private {
+            assert!(!is_public(ip.parse().unwrap()), "{ip}");
+        }
+    }
+
+    #[test]
+    fn test_check_url() {
+        assert!(check_url("https://example.com/hook").is_ok());
+        assert!(check_url("ftp://example.com/hook").is_err());
+        assert!(check_url("http://127.0.0.1:8080/hook").is_err());
+        assert!(check_url("http://[::1]/hook").is_err());
+        assert!(check_url("http://169.254.169.254/latest/meta-data").is_err());
+        assert!(check_url("http://LOCALHOST./hook").is_err());
+        assert!(check_url("http://api.localhost/hook").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_deliver_rejects_private_hosts() {
+        let (url, received) = start_receiver(0).await;
+        let sender = WebhookSender::new().with_retries(3, Duration::ZERO);
+
+        let attempts = sender.deliver(&url, "secret", &payload()).await;
+        assert_eq!(attempts.len(), 1);
+        assert!(!attempts[0].delivered);
+
+        // Names are checked once resolved
+        let url = url.replace("127.0.0.1", "localhost");
+        let error = check_host(&reqwest::Url::parse(&url).unwrap()).await;
+        assert!(error
+            .unwrap_err()
+            .to_string()
+            .contains("non-public address"));
+        let resolved = PublicResolver.resolve("localhost".parse().unwrap()).await;
+        assert!(resolved.is_err());
+        assert!(received.lock().unwrap().is_empty());
+    }
+}
@@ -15,6 +15,23 @@ use crate::*;
     // debug::user_info,
     // debug::subreddit_posts,
     // debug::user_posts,
-    auth::login::login
+    api::auth::login::login,
+    api::compare::compare,
+    api::corpus::corpus_posts,
+    api::corpus::corpus_comments,
+    api::corpus::score_history,
+    api::report::user::user,
+    api::report::export::export,
+    api::monitor::create_monitor,
+    api::monitor::list_monitors,
+    api::monitor::delete_monitor,
+    api::monitor::monitor_series,
+    api::monitor::create_alert_rule,
+    api::monitor::list_alert_rules,
+    api::monitor::delete_alert_rule,
+    api::monitor::alert_deliveries,
+    api::source_group::create_source_group,
+    api::source_group::list_source_groups,
+    api::source_group::delete_source_group
 ))]
 pub struct ApiDoc;
@@ -1,20 +1,36 @@
-use crate::reddit_fetcher::reddit::request::params::FeedSorting;
+use crate::reddit_fetcher::reddit::model::timestamp;
+use crate::reddit_fetcher::reddit::request::params::{
+    FeedSorting, FeedSortingTime, SearchSorting, SearchType,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 
 /// What kind of feed do we fetch and make a report on?
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum RedditFeedKind {
     UserPosts,
     PostComments,
     SubredditPosts,
+    /// Latest comments of a whole subreddit, without fetching them post by post
+    SubredditComments,
+    Search,
 }
 
-// TODO: Add more types
 /// What NLP reports do we want to generate?
-#[derive(Debug)]
+///
+/// Serialized in `snake_case`, the same names are used by the NLP service.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum RMoodsReportType {
     Sentiment,
     Sarcasm,
-    Etc,
+    Keywords,
+    Language,
+    Spam,
+    Politics,
+    HateSpeech,
+    Clickbait,
+    Troll,
 }
 
 /// Represents a data source for the Reddit API.
@@ -52,8 +68,87 @@ impl From<RequestSize> for u16 {
     }
 }
 
+/// Position in a feed sorted by [FeedSorting::New]: the newest item fetched so far.
+///
+/// Passed as `since` in a [FetcherFeedRequest], it makes the fetcher stop paging once it reaches
+/// items it already has. Either field is enough. When both are set, the fullname is compared first,
+/// so another item created in the same second as the cursor is still new.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FeedCursor {
+    /// Fullname of the newest item, eg. t3_8z1v1z
+    pub fullname: Option<String>,
+    /// Creation time of the newest item
+    #[serde(default, with = "timestamp::option")]
+    pub created_utc: Option<DateTime<Utc>>,
+}
+
+impl FeedCursor {
+    /// Create a cursor pointing at the given item.
+    pub fn at(fullname: Option<&str>, created_utc: DateTime<Utc>) -> Self {
+        Self {
+            fullname: fullname.map(|f| f.to_string()),
+            created_utc: Some(created_utc),
+        }
+    }
+
+    /// Was the item already fetched, according to this cursor?
+    pub fn is_seen(&self, fullname: Option<&str>, created_utc: DateTime<Utc>) -> bool {
+        match (&self.fullname, fullname) {
+            (Some(a), Some(b)) if a == b => true,
+            // A different item, only seen if it's older than the cursor
+            (Some(_), Some(_)) => matches!(self.created_utc, Some(t) if created_utc < t),
+            _ => matches!(self.created_utc, Some(t) if created_utc <= t),
+        }
+    }
+
+    /// Drop the items from the first seen one on, the feed is sorted from the newest item.
+    /// Returns `true` if any item was dropped.
+    pub fn truncate_seen<T>(
+        &self,
+        items: &mut Vec<T>,
+        key: impl Fn(&T) -> (Option<&str>, DateTime<Utc>),
+    ) -> bool {
+        let seen = items.iter().position(|item| {
+            let (fullname, created_utc) = key(item);
+            self.is_seen(fullname, created_utc)
+        });
+        match seen {
+            Some(index) => {
+                items.truncate(index);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Which items of a user's profile are fetched, used with [RedditFeedKind::UserPosts].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UserContentFilter {
+    /// Posts and comments
+    #[default]
+    All,
+    Comments,
+    Submitted,
+}
+
+/// What to search for, used with [RedditFeedKind::Search].
+///
+/// Data sources name the subreddits to search in, `all` searches all of Reddit.
+#[derive(Debug, Clone, Default)]
+pub struct SearchQuery {
+    /// eg. `title:wybory OR sejm`
+    pub query: String,
+    pub sorting: SearchSorting,
+    /// Only search in this time period
+    pub time: Option<FeedSortingTime>,
+    /// Kinds of results to return, posts if empty
+    pub types: Vec<SearchType>,
+}
+
 /// Represents a request to fetch a feed from Reddit.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct FetcherFeedRequest {
     /// Determines what kind of feed do we fetch and make a report on.
     pub resource_kind: RedditFeedKind,
@@ -65,4 +160,128 @@ pub struct FetcherFeedRequest {
     pub size: RequestSize,
     /// Determines the sorting of the feed.
     pub sorting: FeedSorting,
+    /// Only fetch items newer than this cursor. Ignored on feeds that aren't listed newest first,
+    /// eg. posts not sorted by [FeedSorting::New].
+    pub since: Option<FeedCursor>,
+    /// What to search for. Only used with [RedditFeedKind::Search].
+    pub search: Option<SearchQuery>,
+    /// Which items of a user's profile to fetch. Only used with [RedditFeedKind::UserPosts].
+    pub user_filter: UserContentFilter,
+}
+
+impl FetcherFeedRequest {
+    /// Split the request into one request per data source.
+    /// Each source gets its `share` of the request budget, but always at least one request.
+    pub fn per_source(&self) -> Vec<FetcherFeedRequest> {
+        let budget = u16::from(self.size.clone()) as f32;
+        self.data_sources
+            .iter()
+            .map(|source| FetcherFeedRequest {
+                data_sources: vec![source.clone()],
+                size: RequestSize::Custom(((budget * source.share).round() as u16).max(1)),
+                ..self.clone()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(seconds: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(seconds, 0).unwrap()
+    }
+
+    #[test]
+    fn test_cursor_by_fullname() {
+        let cursor = FeedCursor {
+            fullname: Some("t3_b".to_string()),
+            created_utc: None,
+        };
+        assert!(cursor.is_seen(Some("t3_b"), time(100)));
+        assert!(!cursor.is_seen(Some("t3_c"), time(100)));
+        assert!(!cursor.is_seen(None, time(100)));
+    }
+
+    #[test]
+    fn test_cursor_by_created_utc() {
+        let cursor = FeedCursor::at(None, time(100));
+        assert!(cursor.is_seen(Some("t3_a"), time(50)));
+        assert!(cursor.is_seen(None, time(100)));
+        assert!(!cursor.is_seen(Some("t3_c"), time(150)));
+    }
+
+    #[test]
+    fn test_cursor_compares_fullname_first() {
+        let cursor = FeedCursor::at(Some("t3_b"), time(100));
+        assert!(cursor.is_seen(Some("t3_b"), time(100)));
+        // Created in the same second as the cursor
+        assert!(!cursor.is_seen(Some("t3_c"), time(100)));
+        assert!(cursor.is_seen(Some("t3_a"), time(99)));
+    }
+
+    #[test]
+    fn test_truncate_seen() {
+        let cursor = FeedCursor::at(Some("t3_b"), time(200));
+        // `t3_d` is older than the cursor, yet listed before it
+        let mut items = vec![("t3_c", 300), ("t3_d", 100), ("t3_b", 200), ("t3_a", 100)];
+        assert!(cursor.truncate_seen(&mut items, |(name, t)| (Some(name), time(*t))));
+        assert_eq!(items, [("t3_c", 300)]);
+
+        let mut items = vec![("t3_c", 300)];
+        assert!(!cursor.truncate_seen(&mut items, |(name, t)| (Some(name), time(*t))));
+        assert_eq!(items.len(), 1);
+    }
+
+    #[test]
+    fn test_cursor_tells_apart_close_timestamps() {
+        // Cursors stored before are plain float seconds
+        let cursor: FeedCursor =
+            serde_json::from_value(serde_json::json!({ "created_utc": 1729327268.0 })).unwrap();
+        assert!(cursor.is_seen(None, time(1729327268)));
+        assert!(!cursor.is_seen(None, time(1729327269)));
+    }
+
+    #[test]
+    fn test_per_source_splits_budget() {
+        let source = |name: &str, share| DataSource {
+            name: name.to_string(),
+            post_id: None,
+            share,
+        };
+        let request = FetcherFeedRequest {
+            resource_kind: RedditFeedKind::SubredditPosts,
+            report_types: vec![RMoodsReportType::Sentiment],
+            data_sources: vec![
+                source("Polska", 0.5),
+                source("poland", 0.5),
+                source("tiny", 0.0),
+            ],
+            size: RequestSize::Custom(10),
+            sorting: FeedSorting::New,
+            since: None,
+            search: None,
+            user_filter: UserContentFilter::All,
+        };
+
+        let budgets = request
+            .per_source()
+            .into_iter()
+            .map(|r| (r.data_sources[0].name.clone(), u16::from(r.size)))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            budgets,
+            [
+                ("Polska".to_string(), 5),
+                ("poland".to_string(), 5),
+                ("tiny".to_string(), 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_empty_cursor_sees_nothing() {
+        assert!(!FeedCursor::default().is_seen(Some("t3_a"), time(0)));
+    }
 }
@@ -1,13 +1,20 @@
-use crate::reddit_fetcher::feed_request::FetcherFeedRequest;
+use crate::config::RedditConfig;
+use crate::corpus::{Corpus, CorpusItems};
+use crate::reddit_fetcher::feed_request::{DataSource, FetcherFeedRequest};
 use crate::reddit_fetcher::fetcher_error::FetcherError;
+use crate::reddit_fetcher::model::items_info::ItemsInfo;
+use crate::reddit_fetcher::model::post_detail::PostDetail;
 use crate::reddit_fetcher::model::reddit_data::{RedditAboutData, RedditFeedData};
 use crate::reddit_fetcher::reddit::{
+    cache::{CacheStats, ResponseCache},
     connection::RedditConnection,
     error::RedditError,
     model::{MoreComments, RawComment},
+    request::{InfoRequest, PostDetailRequest},
 };
-use log::{debug, info};
+use log::{debug, info, warn};
 use log_derive::logfn;
+use tracing::instrument;
 
 /// Layer responsible for fetching data from Reddit.
 /// * It uses a `RedditConnection` to make requests to the Reddit API.
@@ -17,23 +24,84 @@ use log_derive::logfn;
 #[derive(Clone)]
 pub struct RMoodsFetcher {
     reddit_connection: RedditConnection,
+    /// Where fetched posts and comments are kept
+    corpus: Option<Corpus>,
 }
 
 impl RMoodsFetcher {
-    /// Create a new instance of RMoodsFetcher with the provided `http` client.
+    /// Create a new instance of RMoodsFetcher with the provided `http` client and Reddit config.
     /// There should exist only one instance of this struct in the application.
     #[logfn(err = "ERROR", fmt = "Failed to create Reddit fetcher: {0}")]
-    pub async fn new(http: reqwest::Client) -> Result<Self, RedditError> {
-        let reddit_connection = RedditConnection::new(http).await?;
-        Ok(Self { reddit_connection })
+    pub async fn new(http: reqwest::Client, config: &RedditConfig) -> Result<Self, RedditError> {
+        let reddit_connection = RedditConnection::new(http, config).await?;
+        Ok(Self {
+            reddit_connection,
+            corpus: None,
+        })
+    }
+
+    /// Serve fresh responses from the cache instead of fetching them again.
+    pub fn with_cache(self, cache: ResponseCache) -> Self {
+        Self {
+            reddit_connection: self.reddit_connection.with_cache(cache),
+            ..self
+        }
+    }
+
+    /// Store every fetched post and comment in the corpus.
+    pub fn with_corpus(self, corpus: Corpus) -> Self {
+        Self {
+            corpus: Some(corpus),
+            ..self
+        }
+    }
+
+    /// Store the items of the last response, unless it was served from the cache or a cassette.
+    /// Those were stored when they were fetched, storing them again would fake a newer observation.
+    fn store_in_corpus(&self, items: CorpusItems) {
+        let (Some(corpus), Some(fetched_at)) = (&self.corpus, self.reddit_connection.fetched_at())
+        else {
+            return;
+        };
+        corpus.store_in_background(items, fetched_at);
+    }
+
+    /// Skip cached responses in the requests made by this fetcher, eg. when a user asks for fresh data.
+    ///
+    /// The fetcher is cloned for every API request, so it only affects the current one.
+    pub fn bypass_cache(&mut self, bypass: bool) {
+        self.reddit_connection.bypass_cache(bypass);
+    }
+
+    /// Hits and misses of the response cache, `None` if there's no cache.
+    pub fn cache_stats(&self) -> Option<CacheStats> {
+        self.reddit_connection.cache_stats()
+    }
+
+    /// Delete the stale responses of the response cache, so the Postgres table doesn't keep growing.
+    pub async fn purge_expired_cache(&self) {
+        self.reddit_connection.purge_expired_cache().await;
+    }
+
+    /// Create a fetcher using an already established connection, eg. to a mock server.
+    #[cfg(test)]
+    pub fn with_connection(reddit_connection: RedditConnection) -> Self {
+        Self {
+            reddit_connection,
+            corpus: None,
+        }
     }
 
     /// Fetches a feed of Reddit data.
     /// * It fetches the data from the Reddit API using the provided `FetcherFeedRequest`.
     /// * It fetches the data in multiple requests if needed.
+    /// * With a `since` cursor on a chronological feed, see [RedditFeedData::is_chronological],
+    ///   it stops paging once it reaches items it already has.
+    ///   The cursor for the next fetch is available through [RedditFeedData::watermark].
     /// * It returns the parsed data and the number of requests made.
     /// * The parsed data is of type `T` which should implement the `RedditFeedData` trait.
     #[logfn(err = "ERROR", fmt = "Failed to fetch feed: {0}")]
+    #[instrument(skip_all, fields(kind = ?request.resource_kind, size = ?request.size))]
     pub async fn fetch_feed<T: RedditFeedData>(
         &mut self,
         request: FetcherFeedRequest,
@@ -44,26 +112,70 @@ impl RMoodsFetcher {
         // TODO: allow for multiple data sources
         let source = request.data_sources.first().unwrap().clone();
 
-        let initial_request = T::create_reddit_request(&request, source.clone(), None);
-        let (raw_data, mut after) = self.reddit_connection.fetch_raw(initial_request).await?;
-        let mut parsed = T::from_reddit_container(raw_data)?;
+        // Only a chronological feed can be fetched up to a cursor
+        let cursor = match &request.since {
+            Some(cursor) if T::is_chronological(&request) => Some(cursor),
+            Some(_) => {
+                warn!(
+                    "Ignoring the `since` cursor, {:?} sorted by {} is not chronological",
+                    request.resource_kind, request.sorting
+                );
+                None
+            }
+            None => None,
+        };
+
+        let (mut parsed, mut after) = self.fetch_page::<T>(&request, source.clone(), None).await?;
+        let mut reached_cursor = cursor.is_some_and(|c| parsed.retain_unseen(c));
 
         let mut requests_made = 1;
-        while requests_made < requests_to_make && after.is_some() {
-            let next_request = T::create_reddit_request(&request, source.clone(), after.clone());
-            let (raw_data, next_after) = self.reddit_connection.fetch_raw(next_request).await?;
-            parsed = T::from_reddit_container(raw_data)?.concat(parsed);
+        while requests_made < requests_to_make && after.is_some() && !reached_cursor {
+            let (mut page, next_after) = self
+                .fetch_page::<T>(&request, source.clone(), after.clone())
+                .await?;
+            reached_cursor = cursor.is_some_and(|c| page.retain_unseen(c));
+            parsed = parsed.concat(page);
 
             after = next_after;
             requests_made += 1;
         }
 
+        if reached_cursor {
+            debug!("Reached the `since` cursor, no more pages to fetch");
+        }
         debug!("Requests made: {}/{}", requests_made, requests_to_make);
+        debug!("Rate limit: {:?}", self.reddit_connection.rate_limit());
         info!("Done fetching feed: {:?}", request);
 
         Ok((parsed, requests_made))
     }
 
+    /// Fetches a single page of the feed of the request's first data source, starting after the `after` item.
+    ///
+    /// Returns the parsed page and the cursor of the next one, `None` on the last page.
+    /// Lets callers process a long feed page by page, [RMoodsFetcher::fetch_feed] fetches all of it at once.
+    #[logfn(err = "ERROR", fmt = "Failed to fetch feed page: {0}")]
+    pub async fn fetch_feed_page<T: RedditFeedData>(
+        &mut self,
+        request: &FetcherFeedRequest,
+        after: Option<String>,
+    ) -> Result<(T, Option<String>), FetcherError> {
+        let source = request.data_sources.first().unwrap().clone();
+        self.fetch_page(request, source, after).await
+    }
+
+    async fn fetch_page<T: RedditFeedData>(
+        &mut self,
+        request: &FetcherFeedRequest,
+        source: DataSource,
+        after: Option<String>,
+    ) -> Result<(T, Option<String>), FetcherError> {
+        let reddit_request = T::create_reddit_request(request, source, after);
+        let (raw_data, after) = self.reddit_connection.fetch_raw(reddit_request).await?;
+        self.store_in_corpus(CorpusItems::from_container(&raw_data));
+        Ok((T::from_reddit_container(raw_data)?, after))
+    }
+
     /// Uses the MoreComments stubs to fetch more comments.
     /// * It fetches the comments from the Reddit API using the provided `MoreComments` stubs.
     /// * It fetches the comments in multiple requests if needed.
@@ -84,7 +196,7 @@ impl RMoodsFetcher {
             requests_left
         );
 
-        for more_comments in stubs.into_iter() {
+        for more_comments in stubs.iter() {
             if requests_left == 0 {
                 break;
             }
@@ -97,6 +209,7 @@ impl RMoodsFetcher {
             info!("Requests left: {}", requests_left);
             comments.extend(new_comments);
         }
+        self.store_in_corpus(CorpusItems::from(comments.as_slice()));
 
         Ok(comments)
     }
@@ -114,4 +227,35 @@ impl RMoodsFetcher {
         let data = T::from_reddit_container(raw.0)?;
         Ok(data)
     }
+
+    /// Fetches a post together with its comment tree, in a single request.
+    #[logfn(err = "ERROR", fmt = "Failed to fetch post detail: {0}")]
+    pub async fn fetch_post_detail(
+        &mut self,
+        request: PostDetailRequest,
+    ) -> Result<PostDetail, FetcherError> {
+        let (post, comments) = self.reddit_connection.fetch_post_detail(request).await?;
+        let mut items = CorpusItems::from_container(&post);
+        items.extend(CorpusItems::from_container(&comments));
+        self.store_in_corpus(items);
+        PostDetail::from_reddit_containers(post, comments)
+    }
+
+    /// Looks up posts and comments by their fullnames, eg. to refresh their scores.
+    /// Makes one request per [INFO_CHUNK_SIZE](crate::reddit_fetcher::reddit::request::INFO_CHUNK_SIZE) fullnames.
+    #[logfn(err = "ERROR", fmt = "Failed to fetch info: {0}")]
+    pub async fn fetch_info(&mut self, fullnames: &[String]) -> Result<ItemsInfo, FetcherError> {
+        let mut info = ItemsInfo::default();
+        for request in InfoRequest::chunked(fullnames) {
+            let (raw, _) = self.reddit_connection.fetch_raw(request).await?;
+            self.store_in_corpus(CorpusItems::from_container(&raw));
+            info.extend(ItemsInfo::from_reddit_container(raw)?);
+        }
+        debug!(
+            "Found {}/{} items",
+            info.posts.len() + info.comments.len(),
+            fullnames.len()
+        );
+        Ok(info)
+    }
 }
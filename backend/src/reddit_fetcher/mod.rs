@@ -1,5 +1,5 @@
 pub mod feed_request;
 pub mod fetcher;
-mod fetcher_error;
-pub(crate) mod model;
+pub mod fetcher_error;
+pub mod model;
 pub mod reddit;
@@ -0,0 +1,116 @@
+use crate::cast;
+use crate::reddit_fetcher::fetcher_error::FetcherError;
+use crate::reddit_fetcher::model::reddit_data::RedditAboutData;
+use crate::reddit_fetcher::reddit::model::{RawComment, RawContainer, RawPost};
+use crate::reddit_fetcher::reddit::request::InfoRequest;
+use serde::{Deserialize, Serialize};
+
+/// Contains posts and comments looked up by their fullnames.
+/// Items are to be fetched by using the `Fetcher::fetch_info` method.
+///
+/// Items that don't exist are simply missing, Reddit doesn't report them.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ItemsInfo {
+    pub posts: Vec<RawPost>,
+    pub comments: Vec<RawComment>,
+}
+
+impl ItemsInfo {
+    /// Add the items of another lookup.
+    pub fn extend(&mut self, other: ItemsInfo) {
+        self.posts.extend(other.posts);
+        self.comments.extend(other.comments);
+    }
+}
+
+impl RedditAboutData for ItemsInfo {
+    type RequestType = InfoRequest;
+
+    fn from_reddit_container(container: RawContainer) -> Result<Self, FetcherError> {
+        let mut info = ItemsInfo::default();
+
+        let listing = cast!(container, RawContainer::Listing)?;
+
+        for child in listing.children {
+            match child {
+                RawContainer::Post(post) => info.posts.push(*post),
+                RawContainer::Comment(mut comment) => {
+                    comment.replies = None;
+                    info.comments.push(*comment);
+                }
+                _ => {
+                    return Err(FetcherError::RedditParseError(
+                        "Failed to parse post or comment from Reddit container".to_string(),
+                    ));
+                }
+            }
+        }
+
+        Ok(info)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_info_listing() {
+        let container = serde_json::from_value(json!({
+            "kind": "Listing",
+            "data": {
+                "after": null,
+                "dist": 2,
+                "before": null,
+                "children": [
+                    {
+                        "kind": "t3",
+                        "data": {
+                            "subreddit": "Polska",
+                            "selftext": "",
+                            "gilded": 0,
+                            "title": "Lorem ipsum",
+                            "name": "t3_abc",
+                            "score": 42,
+                            "created_utc": 100.0,
+                            "over_18": false,
+                            "id": "abc",
+                            "subreddit_id": "t5_2qh3s",
+                            "author": "spez",
+                            "num_comments": 1,
+                            "url": "https://www.reddit.com/r/Polska",
+                            "stickied": false
+                        }
+                    },
+                    {
+                        "kind": "t1",
+                        "data": {
+                            "subreddit_id": "t5_2qh3s",
+                            "subreddit": "Polska",
+                            "replies": "",
+                            "author": "spez",
+                            "body": "Dolor sit amet",
+                            "permalink": "/r/Polska/comments/abc/lorem/def/",
+                            "created_utc": 200.0,
+                            "depth": null,
+                            "score": 7,
+                            "id": "def",
+                            "name": "t1_def",
+                            "parent_id": "t3_abc",
+                            "link_id": "t3_abc"
+                        }
+                    }
+                ]
+            }
+        }))
+        .unwrap();
+
+        let mut info = ItemsInfo::from_reddit_container(container).unwrap();
+        assert_eq!(*info.posts[0].score(), 42);
+        assert_eq!(info.comments[0].body(), "Dolor sit amet");
+
+        info.extend(ItemsInfo::default());
+        assert_eq!(info.posts.len() + info.comments.len(), 2);
+    }
+}
@@ -1,6 +1,10 @@
+pub mod items_info;
 pub mod post_comments;
+pub mod post_detail;
 pub mod posts;
 pub mod reddit_data;
+pub mod search_results;
+pub mod subreddit_comments;
 pub mod subreddit_info;
 pub mod user_info;
 pub mod user_posts;
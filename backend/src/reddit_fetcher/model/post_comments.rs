@@ -40,8 +40,7 @@ impl RedditFeedData for PostComments {
                 _ => {
                     return Err(FetcherError::RedditParseError(
                         "Failed to parse comment from Reddit container".to_string(),
-                    )
-                    .into());
+                    ));
                 }
             }
         }
@@ -94,8 +93,7 @@ fn flatten_replies_internal(
                 _ => {
                     return Err(FetcherError::RedditParseError(
                         "Failed to parse comment from Reddit container".to_string(),
-                    )
-                    .into());
+                    ));
                 }
             }
         }
@@ -104,7 +102,7 @@ fn flatten_replies_internal(
 
         for mut reply in replies {
             debug!("{tabs}u/{}", reply.author());
-            let _ = flatten_replies_internal(&reply, all_replies, mores, depth + 1)?;
+            flatten_replies_internal(&reply, all_replies, mores, depth + 1)?;
             reply.replies = None;
             all_replies.push(reply.clone());
         }
@@ -113,13 +111,13 @@ fn flatten_replies_internal(
 }
 
 #[logfn(err = "ERROR", fmt = "Failed to flatten replies: {0}")]
-fn flattened_replies(
+pub(crate) fn flattened_replies(
     comment: &RawComment,
 ) -> Result<(Vec<RawComment>, Vec<MoreComments>), FetcherError> {
     let mut all_replies = vec![];
     let mut mores = vec![];
 
-    let _ = flatten_replies_internal(comment, &mut all_replies, &mut mores, 0)?;
+    flatten_replies_internal(comment, &mut all_replies, &mut mores, 0)?;
 
     Ok((all_replies, mores))
 }
@@ -0,0 +1,169 @@
+use crate::cast;
+use crate::reddit_fetcher::fetcher_error::FetcherError;
+use crate::reddit_fetcher::model::post_comments::{flattened_replies, PostComments};
+use crate::reddit_fetcher::reddit::model::{MoreComments, RawComment, RawContainer, RawPost};
+use log_derive::logfn;
+use serde::{Deserialize, Serialize};
+
+/// Contains a Reddit post together with its comment tree.
+/// It is to be fetched by using the `Fetcher::fetch_post_detail` method.
+///
+/// Unlike [PostComments], the comments keep their replies, so the tree can be walked as is.
+/// Use [PostDetail::flattened] to get a flat list of comments instead.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PostDetail {
+    pub post: RawPost,
+    /// Top-level comments, with their replies
+    pub comments: Vec<RawComment>,
+    /// Top-level comments that didn't fit in the response
+    pub more: Vec<MoreComments>,
+}
+
+impl PostDetail {
+    /// Takes both listings of the post detail response and converts them into a [PostDetail].
+    #[logfn(err = "ERROR", fmt = "Failed to parse post detail: {0}")]
+    pub fn from_reddit_containers(
+        post: RawContainer,
+        comments: RawContainer,
+    ) -> Result<Self, FetcherError> {
+        let post = cast!(post, RawContainer::Listing)?
+            .children
+            .into_iter()
+            .next()
+            .ok_or_else(|| {
+                FetcherError::RedditParseError("Post detail listing is empty".to_string())
+            })?;
+        let post = cast!(post, RawContainer::Post)?;
+
+        let mut list = vec![];
+        let mut more = vec![];
+        for child in cast!(comments, RawContainer::Listing)?.children {
+            match child {
+                RawContainer::Comment(comment) => list.push(*comment),
+                RawContainer::More(stub) => more.push(*stub),
+                _ => {
+                    return Err(FetcherError::RedditParseError(
+                        "Failed to parse comment from Reddit container".to_string(),
+                    ));
+                }
+            }
+        }
+
+        Ok(Self {
+            post: *post,
+            comments: list,
+            more,
+        })
+    }
+
+    /// All comments of the tree as a flat list, with the `more` stubs of every level.
+    pub fn flattened(&self) -> Result<PostComments, FetcherError> {
+        let mut list = vec![];
+        let mut more = self.more.clone();
+
+        for comment in &self.comments {
+            let (mut replies, mut mores) = flattened_replies(comment)?;
+            list.append(&mut replies);
+            more.append(&mut mores);
+
+            let mut comment = comment.clone();
+            comment.replies = None;
+            list.push(comment);
+        }
+
+        Ok(PostComments { list, more })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    fn comment(body: &str, replies: Value) -> Value {
+        json!({
+            "kind": "t1",
+            "data": {
+                "subreddit_id": "t5_2qh3s",
+                "subreddit": "Polska",
+                "replies": replies,
+                "author": "spez",
+                "body": body,
+                "permalink": format!("/r/Polska/comments/abc/lorem/{body}/"),
+                "created_utc": 200.0,
+                "depth": 0,
+                "score": 1,
+                "id": body,
+                "name": format!("t1_{body}"),
+                "parent_id": "t3_abc",
+                "link_id": "t3_abc"
+            }
+        })
+    }
+
+    fn listing(children: Vec<Value>) -> Value {
+        json!({
+            "kind": "Listing",
+            "data": { "after": null, "dist": null, "before": null, "children": children }
+        })
+    }
+
+    fn response() -> Vec<RawContainer> {
+        let post = json!({
+            "kind": "t3",
+            "data": {
+                "subreddit": "Polska",
+                "selftext": "Dolor sit amet",
+                "gilded": 0,
+                "title": "Lorem ipsum",
+                "name": "t3_abc",
+                "score": 42,
+                "created_utc": 100.0,
+                "over_18": false,
+                "id": "abc",
+                "subreddit_id": "t5_2qh3s",
+                "author": "spez",
+                "num_comments": 3,
+                "url": "https://www.reddit.com/r/Polska",
+                "stickied": false
+            }
+        });
+        let more = json!({
+            "kind": "more",
+            "data": {
+                "count": 1,
+                "name": "t1_more",
+                "id": "more",
+                "parent_id": "t3_abc",
+                "depth": 0,
+                "children": ["xyz"]
+            }
+        });
+        let reply = comment("reply", "".into());
+        let tree = vec![comment("top", listing(vec![reply])), more];
+
+        serde_json::from_value(json!([listing(vec![post]), listing(tree)])).unwrap()
+    }
+
+    #[test]
+    fn test_post_detail_keeps_post_and_tree() {
+        let [post, comments] = <[RawContainer; 2]>::try_from(response()).unwrap();
+        let detail = PostDetail::from_reddit_containers(post, comments).unwrap();
+
+        assert_eq!(detail.post.title(), "Lorem ipsum");
+        assert_eq!(detail.post.selftext(), "Dolor sit amet");
+        assert_eq!(detail.comments.len(), 1);
+        assert!(detail.comments[0].replies().is_some());
+        assert_eq!(detail.more.len(), 1);
+
+        let flat = detail.flattened().unwrap();
+        let bodies = flat
+            .list
+            .iter()
+            .map(|c| c.body().as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(bodies, ["reply", "top"]);
+        assert!(flat.list.iter().all(|c| c.replies().is_none()));
+        assert_eq!(flat.more.len(), 1);
+    }
+}
@@ -1,5 +1,5 @@
 use crate::cast;
-use crate::reddit_fetcher::feed_request::{DataSource, FetcherFeedRequest};
+use crate::reddit_fetcher::feed_request::{DataSource, FeedCursor, FetcherFeedRequest};
 use crate::reddit_fetcher::fetcher_error::FetcherError;
 use crate::reddit_fetcher::model::reddit_data::RedditFeedData;
 use crate::reddit_fetcher::reddit::model::{RawContainer, RawPost};
@@ -46,4 +46,80 @@ impl RedditFeedData for Posts {
             list: [self.list.clone(), other.list].concat(),
         }
     }
+    fn retain_unseen(&mut self, cursor: &FeedCursor) -> bool {
+        cursor.truncate_seen(&mut self.list, |p| (Some(p.name()), *p.created_utc()))
+    }
+    fn watermark(&self) -> Option<FeedCursor> {
+        self.list
+            .iter()
+            .max_by_key(|p| *p.created_utc())
+            .map(|p| FeedCursor::at(Some(p.name()), *p.created_utc()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, Utc};
+    use serde_json::json;
+
+    fn time(seconds: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(seconds, 0).unwrap()
+    }
+
+    fn post(name: &str, created_utc: i64) -> RawPost {
+        serde_json::from_value(json!({
+            "subreddit": "Polska",
+            "selftext": "",
+            "gilded": 0,
+            "title": "Lorem ipsum",
+            "name": name,
+            "score": 1,
+            "created_utc": created_utc,
+            "over_18": false,
+            "id": name.trim_start_matches("t3_"),
+            "subreddit_id": "t5_2qh3s",
+            "author": "spez",
+            "num_comments": 0,
+            "url": "https://www.reddit.com/r/Polska",
+            "stickied": false
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_retain_unseen_stops_at_cursor() {
+        let mut posts = Posts {
+            list: vec![post("t3_c", 300), post("t3_b", 200), post("t3_a", 100)],
+        };
+        let cursor = FeedCursor {
+            fullname: Some("t3_b".to_string()),
+            created_utc: None,
+        };
+
+        assert!(posts.retain_unseen(&cursor));
+        assert_eq!(posts.list.len(), 1);
+        assert_eq!(posts.list[0].name(), "t3_c");
+    }
+
+    #[test]
+    fn test_retain_unseen_keeps_new_posts() {
+        let mut posts = Posts {
+            list: vec![post("t3_c", 300), post("t3_b", 200)],
+        };
+        assert!(!posts.retain_unseen(&FeedCursor::at(None, time(100))));
+        assert_eq!(posts.list.len(), 2);
+    }
+
+    #[test]
+    fn test_watermark_is_newest_post() {
+        let posts = Posts {
+            list: vec![post("t3_b", 200), post("t3_c", 300)],
+        };
+        assert_eq!(
+            posts.watermark(),
+            Some(FeedCursor::at(Some("t3_c"), time(300)))
+        );
+        assert_eq!(Posts { list: vec![] }.watermark(), None);
+    }
 }
@@ -1,12 +1,14 @@
-use crate::reddit_fetcher::feed_request::{DataSource, FetcherFeedRequest};
+use crate::reddit_fetcher::feed_request::{DataSource, FeedCursor, FetcherFeedRequest};
 use crate::reddit_fetcher::fetcher_error::FetcherError;
 use crate::reddit_fetcher::reddit::model::RawContainer;
-use crate::reddit_fetcher::reddit::request::RedditRequest;
+use crate::reddit_fetcher::reddit::request::{params::FeedSorting, RedditRequest};
 
 /// Describes a common interface for any data that is a feed in Reddit.
 /// 1. Subreddit Posts
 /// 2. Post Comments
 /// 3. User Posts
+/// 4. Search Results
+/// 5. Subreddit Comments
 pub trait RedditFeedData {
     /// The type of request that is used to fetch this data.
     type RequestType: RedditRequest;
@@ -27,6 +29,28 @@ pub trait RedditFeedData {
     fn concat(&mut self, other: Self) -> Self
     where
         Self: Sized;
+
+    /// Is the feed listed newest first, so it can be fetched up to a cursor?
+    ///
+    /// Feeds with a sorting of their own are chronological with [FeedSorting::New].
+    fn is_chronological(request: &FetcherFeedRequest) -> bool {
+        matches!(request.sorting, FeedSorting::New)
+    }
+
+    /// Drops the items that were already fetched according to the cursor.
+    /// Returns `true` if any item was dropped, which means the feed was fetched up to the cursor
+    /// and there's no need to fetch more pages.
+    ///
+    /// Feeds that can't be fetched incrementally keep all their items.
+    fn retain_unseen(&mut self, _cursor: &FeedCursor) -> bool {
+        false
+    }
+
+    /// Cursor pointing at the newest item of the feed, `None` if the feed is empty
+    /// or can't be fetched incrementally.
+    fn watermark(&self) -> Option<FeedCursor> {
+        None
+    }
 }
 
 /// Simpler trait for data that is fetched from the Reddit API as a single object, not as a feed.
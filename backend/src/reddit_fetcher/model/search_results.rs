@@ -0,0 +1,155 @@
+use crate::cast;
+use crate::reddit_fetcher::feed_request::{DataSource, FetcherFeedRequest};
+use crate::reddit_fetcher::fetcher_error::FetcherError;
+use crate::reddit_fetcher::model::reddit_data::RedditFeedData;
+use crate::reddit_fetcher::reddit::model::{
+    RawContainer, RawPost, RawSubredditAbout, RawUserAbout,
+};
+use crate::reddit_fetcher::reddit::request::SearchRequest;
+use log_derive::logfn;
+use serde::{Deserialize, Serialize};
+
+/// Name of the data source that searches all of Reddit instead of a single subreddit.
+pub const SITE_WIDE: &str = "all";
+
+/// Contains the results of a Reddit search.
+/// Results are to be fetched by using the `Fetcher::fetch_feed` method with a
+/// [SearchQuery](crate::reddit_fetcher::feed_request::SearchQuery).
+/// Depending on the requested search types, the results can be posts, subreddits and users.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SearchResults {
+    pub posts: Vec<RawPost>,
+    pub subreddits: Vec<RawSubredditAbout>,
+    pub users: Vec<RawUserAbout>,
+}
+
+impl RedditFeedData for SearchResults {
+    type RequestType = SearchRequest;
+
+    #[logfn(err = "ERROR", fmt = "Failed to parse from RedditContainer: {0}")]
+    fn from_reddit_container(container: RawContainer) -> Result<Self, FetcherError> {
+        let mut results = SearchResults::default();
+
+        let listing = cast!(container, RawContainer::Listing)?;
+
+        for child in listing.children {
+            match child {
+                RawContainer::Post(post) => results.posts.push(*post),
+                RawContainer::SubredditAbout(subreddit) => results.subreddits.push(*subreddit),
+                RawContainer::UserAbout(user) => results.users.push(*user),
+                _ => {
+                    return Err(FetcherError::RedditParseError(
+                        "Failed to parse search result from Reddit container".to_string(),
+                    ));
+                }
+            }
+        }
+
+        Ok(results)
+    }
+    fn create_reddit_request(
+        request: &FetcherFeedRequest,
+        source: DataSource,
+        after: Option<String>,
+    ) -> Self::RequestType {
+        let search = request.search.clone().unwrap_or_default();
+        let subreddit = (source.name != SITE_WIDE).then_some(source.name);
+        Self::RequestType {
+            query: search.query,
+            restrict_sr: subreddit.is_some(),
+            subreddit,
+            sorting: search.sorting,
+            time: search.time,
+            types: search.types,
+            after,
+        }
+    }
+    fn concat(&mut self, other: Self) -> Self {
+        Self {
+            posts: [self.posts.clone(), other.posts].concat(),
+            subreddits: [self.subreddits.clone(), other.subreddits].concat(),
+            users: [self.users.clone(), other.users].concat(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reddit_fetcher::feed_request::{RedditFeedKind, RequestSize, SearchQuery};
+    use crate::reddit_fetcher::reddit::request::params::{FeedSorting, SearchSorting};
+    use serde_json::json;
+
+    fn request(source: &str) -> SearchRequest {
+        let request = FetcherFeedRequest {
+            resource_kind: RedditFeedKind::Search,
+            report_types: vec![],
+            data_sources: vec![],
+            size: RequestSize::Small,
+            sorting: FeedSorting::default(),
+            since: None,
+            search: Some(SearchQuery {
+                query: "rust".to_string(),
+                sorting: SearchSorting::New,
+                ..Default::default()
+            }),
+            user_filter: Default::default(),
+        };
+        let source = DataSource {
+            name: source.to_string(),
+            post_id: None,
+            share: 1.0,
+        };
+        SearchResults::create_reddit_request(&request, source, None)
+    }
+
+    #[test]
+    fn test_search_request_from_source() {
+        let site_wide = request(SITE_WIDE);
+        assert_eq!(site_wide.subreddit, None);
+        assert!(!site_wide.restrict_sr);
+        assert_eq!(site_wide.sorting, SearchSorting::New);
+
+        let subreddit = request("Polska");
+        assert_eq!(subreddit.subreddit.as_deref(), Some("Polska"));
+        assert!(subreddit.restrict_sr);
+        assert_eq!(subreddit.query, "rust");
+    }
+
+    #[test]
+    fn test_parse_search_listing() {
+        let container = serde_json::from_value(json!({
+            "kind": "Listing",
+            "data": {
+                "after": "t3_abc",
+                "dist": 1,
+                "before": null,
+                "children": [{
+                    "kind": "t3",
+                    "data": {
+                        "subreddit": "rust",
+                        "selftext": "",
+                        "gilded": 0,
+                        "title": "Rust 2024 is out",
+                        "name": "t3_abc",
+                        "score": 100,
+                        "created_utc": 1704067200.0,
+                        "over_18": false,
+                        "id": "abc",
+                        "subreddit_id": "t5_2s7lj",
+                        "author": "spez",
+                        "num_comments": 10,
+                        "url": "https://www.reddit.com/r/rust",
+                        "stickied": false
+                    }
+                }]
+            }
+        }))
+        .unwrap();
+
+        let results = SearchResults::from_reddit_container(container).unwrap();
+        assert_eq!(results.posts.len(), 1);
+        assert_eq!(results.posts[0].title(), "Rust 2024 is out");
+        assert!(results.subreddits.is_empty());
+    }
+}
@@ -0,0 +1,137 @@
+use crate::cast;
+use crate::reddit_fetcher::feed_request::{DataSource, FeedCursor, FetcherFeedRequest};
+use crate::reddit_fetcher::fetcher_error::FetcherError;
+use crate::reddit_fetcher::model::reddit_data::RedditFeedData;
+use crate::reddit_fetcher::reddit::model::{RawComment, RawContainer};
+use crate::reddit_fetcher::reddit::request::SubredditCommentsRequest;
+use log_derive::logfn;
+use serde::{Deserialize, Serialize};
+
+/// Contains the latest comments of a subreddit, across all its posts.
+/// Comments are to be fetched by using the `Fetcher::fetch_feed` method with appropriate parameters.
+///
+/// The listing is always sorted by [New](crate::reddit_fetcher::reddit::request::params::FeedSorting::New),
+/// which lets it be fetched incrementally. Unlike post comments, it comes flat, without replies
+/// or `more` stubs, so no quota is spent on `morechildren` calls.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SubredditComments {
+    pub list: Vec<RawComment>,
+}
+
+impl RedditFeedData for SubredditComments {
+    type RequestType = SubredditCommentsRequest;
+
+    #[logfn(err = "ERROR", fmt = "Failed to parse from RedditContainer: {0}")]
+    fn from_reddit_container(container: RawContainer) -> Result<Self, FetcherError> {
+        let mut comments: Vec<RawComment> = Vec::new();
+
+        let listing = cast!(container, RawContainer::Listing)?;
+
+        for child in listing.children {
+            let mut comment = cast!(child, RawContainer::Comment)?;
+            // Replies show up in the listing on their own
+            comment.replies = None;
+            comments.push(*comment);
+        }
+
+        Ok(Self { list: comments })
+    }
+    fn create_reddit_request(
+        _request: &FetcherFeedRequest,
+        source: DataSource,
+        after: Option<String>,
+    ) -> Self::RequestType {
+        Self::RequestType {
+            subreddit: source.name,
+            after,
+        }
+    }
+    fn concat(&mut self, other: Self) -> Self {
+        Self {
+            list: [self.list.clone(), other.list].concat(),
+        }
+    }
+    fn is_chronological(_request: &FetcherFeedRequest) -> bool {
+        // The listing has no sorting of its own, it's always the newest comments first
+        true
+    }
+    fn retain_unseen(&mut self, cursor: &FeedCursor) -> bool {
+        cursor.truncate_seen(&mut self.list, |c| (Some(c.name()), *c.created_utc()))
+    }
+    fn watermark(&self) -> Option<FeedCursor> {
+        self.list
+            .iter()
+            .max_by_key(|c| *c.created_utc())
+            .map(|c| FeedCursor::at(Some(c.name()), *c.created_utc()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::DateTime;
+    use serde_json::json;
+
+    fn comment(id: &str, created_utc: i64) -> serde_json::Value {
+        json!({
+            "kind": "t1",
+            "data": {
+                "subreddit_id": "t5_2qh3s",
+                "subreddit": "Polska",
+                "replies": "",
+                "author": "spez",
+                "body": "Lorem ipsum",
+                "permalink": format!("/r/Polska/comments/abc/lorem/{id}/"),
+                "created_utc": created_utc,
+                "depth": null,
+                "score": 1,
+                "id": id,
+                "name": format!("t1_{id}"),
+                "parent_id": "t3_abc",
+                "link_id": "t3_abc"
+            }
+        })
+    }
+
+    fn listing(children: Vec<serde_json::Value>) -> RawContainer {
+        serde_json::from_value(json!({
+            "kind": "Listing",
+            "data": {
+                "after": null,
+                "dist": children.len(),
+                "before": null,
+                "children": children
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_parse_subreddit_comments() {
+        let comments = SubredditComments::from_reddit_container(listing(vec![
+            comment("c", 300),
+            comment("b", 200),
+        ]))
+        .unwrap();
+        assert_eq!(comments.list.len(), 2);
+        assert_eq!(comments.list[0].body(), "Lorem ipsum");
+    }
+
+    #[test]
+    fn test_retain_unseen_and_watermark() {
+        let mut comments = SubredditComments::from_reddit_container(listing(vec![
+            comment("c", 300),
+            comment("b", 200),
+            comment("a", 100),
+        ]))
+        .unwrap();
+        let time = |seconds| DateTime::from_timestamp(seconds, 0).unwrap();
+
+        assert!(comments.retain_unseen(&FeedCursor::at(None, time(200))));
+        assert_eq!(comments.list.len(), 1);
+        assert_eq!(
+            comments.watermark(),
+            Some(FeedCursor::at(Some("t1_c"), time(300)))
+        );
+    }
+}
@@ -3,11 +3,12 @@ use crate::reddit_fetcher::fetcher_error::FetcherError;
 use crate::reddit_fetcher::model::reddit_data::RedditAboutData;
 use crate::reddit_fetcher::reddit::model::{RawContainer, RawUserAbout};
 use crate::reddit_fetcher::reddit::request::UserAboutRequest;
+use derive_getters::Getters;
 use serde::{Deserialize, Serialize};
 
 /// Contains information about a Reddit user.
 /// It's a wrapper around the raw data returned by the Reddit API, just for consistency
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Getters, Debug, Serialize, Deserialize)]
 pub struct UserAbout {
     info: RawUserAbout,
 }
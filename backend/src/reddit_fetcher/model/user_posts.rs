@@ -1,15 +1,20 @@
 use crate::cast;
-use crate::reddit_fetcher::feed_request::{DataSource, FetcherFeedRequest};
+use crate::reddit_fetcher::feed_request::{
+    DataSource, FeedCursor, FetcherFeedRequest, UserContentFilter,
+};
 use crate::reddit_fetcher::fetcher_error::FetcherError;
 use crate::reddit_fetcher::model::reddit_data::RedditFeedData;
 use crate::reddit_fetcher::reddit::model::{RawComment, RawContainer, RawPost};
-use crate::reddit_fetcher::reddit::request::UserPostsRequest;
+use crate::reddit_fetcher::reddit::request::{
+    UserCommentsRequest, UserFeedRequest, UserPostsRequest, UserSubmittedRequest,
+};
 use log_derive::logfn;
 use serde::{Deserialize, Serialize};
 
 /// Contains the posts and comments of a Reddit user.
 /// Posts and comments are to be fetches by using the `Fetcher::fetch_feed` method with appropriate parameters.
 /// The user's feed contains both posts and comments, so this struct contains both.
+/// The `user_filter` of the feed request can limit it to only one of them.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UserPosts {
     pub posts: Vec<RawPost>,
@@ -17,7 +22,7 @@ pub struct UserPosts {
 }
 
 impl RedditFeedData for UserPosts {
-    type RequestType = UserPostsRequest;
+    type RequestType = UserFeedRequest;
 
     #[logfn(err = "ERROR", fmt = "Failed to parse from RedditContainer: {0}")]
     fn from_reddit_container(container: RawContainer) -> Result<Self, FetcherError> {
@@ -33,8 +38,7 @@ impl RedditFeedData for UserPosts {
                 _ => {
                     return Err(FetcherError::RedditParseError(
                         "Failed to parse post from Reddit container".to_string(),
-                    )
-                    .into());
+                    ));
                 }
             }
         }
@@ -46,10 +50,24 @@ impl RedditFeedData for UserPosts {
         source: DataSource,
         after: Option<String>,
     ) -> Self::RequestType {
-        Self::RequestType {
-            username: source.name,
-            sorting: request.sorting,
-            after,
+        let username = source.name;
+        let sorting = request.sorting;
+        match request.user_filter {
+            UserContentFilter::All => UserFeedRequest::Overview(UserPostsRequest {
+                username,
+                sorting,
+                after,
+            }),
+            UserContentFilter::Comments => UserFeedRequest::Comments(UserCommentsRequest {
+                username,
+                sorting,
+                after,
+            }),
+            UserContentFilter::Submitted => UserFeedRequest::Submitted(UserSubmittedRequest {
+                username,
+                sorting,
+                after,
+            }),
         }
     }
     fn concat(&mut self, other: Self) -> Self {
@@ -58,4 +76,20 @@ impl RedditFeedData for UserPosts {
             comments: [self.comments.clone(), other.comments].concat(),
         }
     }
+    fn retain_unseen(&mut self, cursor: &FeedCursor) -> bool {
+        // Both lists keep the order of the feed
+        let posts = cursor.truncate_seen(&mut self.posts, |p| (Some(p.name()), *p.created_utc()));
+        let comments =
+            cursor.truncate_seen(&mut self.comments, |c| (Some(c.name()), *c.created_utc()));
+        posts || comments
+    }
+    fn watermark(&self) -> Option<FeedCursor> {
+        let posts = self.posts.iter().map(|p| (p.name(), *p.created_utc()));
+        let comments = self.comments.iter().map(|c| (c.name(), *c.created_utc()));
+
+        posts
+            .chain(comments)
+            .max_by_key(|(_, created_utc)| *created_utc)
+            .map(|(fullname, created_utc)| FeedCursor::at(Some(fullname), created_utc))
+    }
 }
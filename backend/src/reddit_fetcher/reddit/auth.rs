@@ -37,6 +37,14 @@ impl RedditAccessToken {
         );
         self.created_at + self.expires_in < now // expired if expiration date was before now
     }
+    /// A placeholder token that never expires, for connections that don't talk to Reddit.
+    pub fn offline() -> Self {
+        Self {
+            token: "offline".to_string(),
+            expires_in: u32::MAX.into(),
+            created_at: get_sys_time_in_secs(),
+        }
+    }
     /// Get a reference to the token
     pub fn token(&self) -> &str {
         &self.token
@@ -66,9 +74,10 @@ impl RedditApp {
     pub async fn fetch_access_token(
         &self,
         http_client: &Client,
+        url: &str,
     ) -> Result<RedditAccessToken, reqwest::Error> {
         let req = http_client
-            .post("https://www.reddit.com/api/v1/access_token")
+            .post(url)
             .basic_auth(self.client_id.as_str(), Some(self.client_secret.as_str())) // basic http auth
             .header("Content-Type", "application/x-www-form-urlencoded")
             .header("Accept", "application/json")
@@ -79,6 +88,7 @@ impl RedditApp {
         let res = http_client
             .execute(req)
             .await?
+            .error_for_status()?
             .json::<RedditAccessToken>()
             .await?;
         Ok(res)
@@ -0,0 +1,346 @@
+//! Cache of Reddit API responses, so analyzing the same resource twice doesn't spend the request quota twice.
+//!
+//! Responses are kept in an in-memory LRU and, optionally, in Postgres, which survives restarts
+//! and is shared by all backend instances. How long a response stays fresh depends on its [CacheKind].
+//!
+//! Configured by the `cache_*` settings of [RedditConfig].
+
+pub mod store;
+
+use crate::config::RedditConfig;
+use crate::metrics;
+use crate::reddit_fetcher::reddit::request::RequestParts;
+use chrono::{DateTime, Duration, Utc};
+use log::{debug, info, warn};
+use lru::LruCache;
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::PgPool;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// How quickly a resource changes, which decides how long its responses are cached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheKind {
+    /// Subreddit and user `about` data, it barely changes
+    About,
+    /// Feeds sorted by [New](crate::reddit_fetcher::reddit::request::params::FeedSorting::New),
+    /// new items show up all the time
+    NewFeed,
+    /// Any other listing
+    Listing,
+}
+
+/// How long responses of each [CacheKind] stay fresh.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CacheTtls {
+    pub about: Duration,
+    pub new_feed: Duration,
+    pub listing: Duration,
+}
+
+impl Default for CacheTtls {
+    fn default() -> Self {
+        Self {
+            about: Duration::hours(24),
+            new_feed: Duration::minutes(2),
+            listing: Duration::minutes(15),
+        }
+    }
+}
+
+impl CacheTtls {
+    pub fn from_config(config: &RedditConfig) -> Self {
+        Self {
+            about: Duration::seconds(config.cache_ttl_about),
+            new_feed: Duration::seconds(config.cache_ttl_new),
+            listing: Duration::seconds(config.cache_ttl_listing),
+        }
+    }
+
+    pub fn ttl(&self, kind: CacheKind) -> Duration {
+        match kind {
+            CacheKind::About => self.about,
+            CacheKind::NewFeed => self.new_feed,
+            CacheKind::Listing => self.listing,
+        }
+    }
+}
+
+/// Cache key of a request: its path and query, eg. `/r/Polska/new.json?limit=100`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey(String);
+
+impl CacheKey {
+    pub fn new((path, query): &RequestParts) -> Self {
+        let query = query
+            .iter()
+            .map(|(k, v)| format!("{k}={v}"))
+            .collect::<Vec<_>>()
+            .join("&");
+        Self(format!("{path}?{query}"))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// A cached response and when it goes stale.
+#[derive(Debug, Clone)]
+struct Entry {
+    body: Value,
+    expires_at: DateTime<Utc>,
+}
+
+/// Cache usage since the start of the server.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    /// Requests that skipped the cache on purpose
+    pub bypassed: u64,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    bypassed: AtomicU64,
+}
+
+/// Cache of Reddit API responses, see the [module docs](self).
+///
+/// Clones share the cached responses and statistics.
+#[derive(Debug, Clone)]
+pub struct ResponseCache {
+    memory: Arc<Mutex<LruCache<CacheKey, Entry>>>,
+    /// Optional persistent storage
+    pool: Option<PgPool>,
+    ttls: CacheTtls,
+    counters: Arc<Counters>,
+}
+
+impl ResponseCache {
+    /// Create an in-memory cache of the given capacity.
+    pub fn new(capacity: NonZeroUsize, ttls: CacheTtls) -> Self {
+        Self {
+            memory: Arc::new(Mutex::new(LruCache::new(capacity))),
+            pool: None,
+            ttls,
+            counters: Arc::default(),
+        }
+    }
+
+    /// The configured cache, `None` if it's disabled.
+    ///
+    /// The pool is used to store responses only if [RedditConfig::cache_db] is set.
+    pub fn from_config(config: &RedditConfig, pool: &PgPool) -> Option<Self> {
+        let Some(capacity) = NonZeroUsize::new(config.cache_size) else {
+            info!("Reddit response cache disabled");
+            return None;
+        };
+        let ttls = CacheTtls::from_config(config);
+        let cache = Self::new(capacity, ttls);
+        info!(
+            "Reddit response cache of {} responses, TTLs: {ttls:?}",
+            config.cache_size
+        );
+
+        Some(if config.cache_db {
+            info!("Storing Reddit responses in the database");
+            cache.with_pool(pool.clone())
+        } else {
+            cache
+        })
+    }
+
+    /// Also store the responses in Postgres.
+    pub fn with_pool(self, pool: PgPool) -> Self {
+        Self {
+            pool: Some(pool),
+            ..self
+        }
+    }
+
+    /// The fresh cached response to the request.
+    ///
+    /// Responses found only in Postgres are brought back into memory.
+    /// Database errors are logged and treated as a miss, the response can always be fetched from Reddit.
+    pub async fn get(&self, key: &CacheKey) -> Option<Value> {
+        let now = Utc::now();
+        let in_memory = self.memory.lock().unwrap().get(key).cloned();
+
+        let found = match in_memory {
+            Some(entry) if entry.expires_at > now => Some(entry),
+            _ => self.get_stored(key, now).await,
+        };
+
+        match found {
+            Some(entry) => {
+                debug!("Cache hit: {}", key.as_str());
+                self.counters.hits.fetch_add(1, Ordering::Relaxed);
+                metrics::REDDIT_CACHE_LOOKUPS
+                    .with_label_values(&["hit"])
+                    .inc();
+                let body = entry.body.clone();
+                self.memory.lock().unwrap().put(key.clone(), entry);
+                Some(body)
+            }
+            None => {
+                debug!("Cache miss: {}", key.as_str());
+                self.counters.misses.fetch_add(1, Ordering::Relaxed);
+                metrics::REDDIT_CACHE_LOOKUPS
+                    .with_label_values(&["miss"])
+                    .inc();
+                None
+            }
+        }
+    }
+
+    async fn get_stored(&self, key: &CacheKey, now: DateTime<Utc>) -> Option<Entry> {
+        let pool = self.pool.as_ref()?;
+        match store::get_response(pool, key.as_str(), now).await {
+            Ok(stored) => stored.map(|(body, expires_at)| Entry { body, expires_at }),
+            Err(e) => {
+                warn!("Failed to read cached Reddit response: {e}");
+                None
+            }
+        }
+    }
+
+    /// Cache the response for the TTL of its kind.
+    pub async fn put(&self, key: CacheKey, kind: CacheKind, body: &Value) {
+        let expires_at = Utc::now() + self.ttls.ttl(kind);
+
+        if let Some(pool) = &self.pool {
+            if let Err(e) = store::put_response(pool, key.as_str(), body, expires_at).await {
+                warn!("Failed to store Reddit response: {e}");
+            }
+        }
+        let entry = Entry {
+            body: body.clone(),
+            expires_at,
+        };
+        self.memory.lock().unwrap().put(key, entry);
+    }
+
+    /// Count a request that skipped the cache.
+    pub fn record_bypass(&self) {
+        self.counters.bypassed.fetch_add(1, Ordering::Relaxed);
+        metrics::REDDIT_CACHE_LOOKUPS
+            .with_label_values(&["bypass"])
+            .inc();
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.counters.hits.load(Ordering::Relaxed),
+            misses: self.counters.misses.load(Ordering::Relaxed),
+            bypassed: self.counters.bypassed.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Delete stale responses from Postgres, in memory they are simply evicted.
+    pub async fn purge_expired(&self) {
+        let Some(pool) = &self.pool else {
+            return;
+        };
+        match store::delete_expired(pool, Utc::now()).await {
+            Ok(deleted) => info!("Deleted {deleted} stale Reddit responses"),
+            Err(e) => warn!("Failed to delete stale Reddit responses: {e}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn cache(capacity: usize, ttls: CacheTtls) -> ResponseCache {
+        ResponseCache::new(NonZeroUsize::new(capacity).unwrap(), ttls)
+    }
+
+    fn key(path: &str) -> CacheKey {
+        CacheKey::new(&(path.to_string(), vec![("limit", "100".to_string())]))
+    }
+
+    #[test]
+    fn test_key_includes_query() {
+        let parts = (
+            "/r/Polska/new.json".to_string(),
+            vec![("limit", "100".to_string()), ("after", "t3_a".to_string())],
+        );
+        assert_eq!(
+            CacheKey::new(&parts).as_str(),
+            "/r/Polska/new.json?limit=100&after=t3_a"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_hit_and_miss_are_counted() {
+        let exported = |result| {
+            metrics::REDDIT_CACHE_LOOKUPS
+                .with_label_values(&[result])
+                .get()
+        };
+        let hits_before = exported("hit");
+        let cache = cache(10, CacheTtls::default());
+        assert_eq!(cache.get(&key("/r/Polska/new.json")).await, None);
+
+        let body = json!({ "kind": "Listing" });
+        cache
+            .put(key("/r/Polska/new.json"), CacheKind::NewFeed, &body)
+            .await;
+        assert_eq!(cache.get(&key("/r/Polska/new.json")).await, Some(body));
+        cache.record_bypass();
+
+        assert_eq!(
+            cache.stats(),
+            CacheStats {
+                hits: 1,
+                misses: 1,
+                bypassed: 1,
+            }
+        );
+        // Tests run in parallel, other tests could use a cache too
+        assert!(exported("hit") > hits_before);
+    }
+
+    #[tokio::test]
+    async fn test_stale_responses_are_not_served() {
+        let ttls = CacheTtls {
+            new_feed: Duration::zero(),
+            ..CacheTtls::default()
+        };
+        let cache = cache(10, ttls);
+        let body = json!({});
+        cache
+            .put(key("/r/Polska/new.json"), CacheKind::NewFeed, &body)
+            .await;
+        cache
+            .put(key("/r/Polska/about.json"), CacheKind::About, &body)
+            .await;
+
+        assert_eq!(cache.get(&key("/r/Polska/new.json")).await, None);
+        assert_eq!(cache.get(&key("/r/Polska/about.json")).await, Some(body));
+    }
+
+    #[tokio::test]
+    async fn test_least_recently_used_is_evicted() {
+        let cache = cache(2, CacheTtls::default());
+        let body = json!({});
+        for path in ["/r/a/new.json", "/r/b/new.json"] {
+            cache.put(key(path), CacheKind::Listing, &body).await;
+        }
+        cache.get(&key("/r/a/new.json")).await;
+        cache
+            .put(key("/r/c/new.json"), CacheKind::Listing, &body)
+            .await;
+
+        assert!(cache.get(&key("/r/a/new.json")).await.is_some());
+        assert!(cache.get(&key("/r/b/new.json")).await.is_none());
+    }
+}
@@ -0,0 +1,47 @@
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use sqlx::{types::Json, PgPool};
+
+/// Get the stored response for the key, if it's still fresh at `now`.
+pub async fn get_response(
+    pool: &PgPool,
+    key: &str,
+    now: DateTime<Utc>,
+) -> Result<Option<(Value, DateTime<Utc>)>, sqlx::Error> {
+    let row: Option<(Json<Value>, DateTime<Utc>)> = sqlx::query_as(
+        "SELECT body, expires_at FROM reddit_cache WHERE key = $1 AND expires_at > $2",
+    )
+    .bind(key)
+    .bind(now)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(|(body, expires_at)| (body.0, expires_at)))
+}
+
+/// Store the response, replacing an earlier one for the same key.
+pub async fn put_response(
+    pool: &PgPool,
+    key: &str,
+    body: &Value,
+    expires_at: DateTime<Utc>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO reddit_cache (key, body, expires_at) VALUES ($1, $2, $3)
+         ON CONFLICT (key) DO UPDATE SET body = EXCLUDED.body, expires_at = EXCLUDED.expires_at",
+    )
+    .bind(key)
+    .bind(Json(body))
+    .bind(expires_at)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Delete the responses that are stale at `now`, returns how many were deleted.
+pub async fn delete_expired(pool: &PgPool, now: DateTime<Utc>) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM reddit_cache WHERE expires_at <= $1")
+        .bind(now)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected())
+}
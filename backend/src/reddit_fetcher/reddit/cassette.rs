@@ -0,0 +1,259 @@
+//! Recording of Reddit API sessions, to replay them offline.
+//!
+//! In record mode, [RedditConnection](super::connection::RedditConnection) appends every request
+//! and its response to the cassette file, one JSON object per line. In replay mode, it serves them
+//! back without touching the network, so report logic can be iterated on without spending the request quota.
+//!
+//! The mode is chosen by `REDDIT_CASSETTE_MODE` (`record` or `replay`), the file by
+//! `REDDIT_CASSETTE` (`cassettes/reddit.ndjson` by default).
+
+use super::error::RedditError;
+use crate::config::RedditConfig;
+use http::StatusCode;
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use tokio::fs::File;
+use tokio::io::AsyncWriteExt;
+
+/// Cassette file used when `REDDIT_CASSETTE` is not set.
+pub const DEFAULT_CASSETTE: &str = "cassettes/reddit.ndjson";
+
+/// What the connection does with the cassette.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CassetteMode {
+    /// Talk to Reddit and write down every request and response
+    Record,
+    /// Serve recorded responses, never talk to Reddit
+    Replay,
+}
+
+/// A request to the Reddit API and the response to it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Interaction {
+    /// Path relative to the API base URL, eg. `/r/Polska/new.json`
+    pub path: String,
+    pub query: Vec<(String, String)>,
+    pub status: u16,
+    /// Response body, `null` if it wasn't JSON
+    pub body: Value,
+}
+
+impl Interaction {
+    /// The body of a successful response, [RedditError::ErrorStatus] otherwise.
+    pub fn into_result(self) -> Result<Value, RedditError> {
+        let status = StatusCode::from_u16(self.status)
+            .map_err(|_| RedditError::CassetteError(format!("Invalid status {}", self.status)))?;
+        if status.is_success() {
+            Ok(self.body)
+        } else {
+            Err(RedditError::ErrorStatus(status))
+        }
+    }
+}
+
+/// Recorded Reddit API session, see the [module docs](self).
+#[derive(Debug)]
+pub struct Cassette {
+    mode: CassetteMode,
+    file: PathBuf,
+    interactions: Vec<Interaction>,
+    /// Which interactions have been replayed already
+    played: Vec<bool>,
+    /// File the interactions are appended to, opened with the first one
+    output: Option<File>,
+}
+
+impl Cassette {
+    /// Start recording into the file, replacing an earlier recording.
+    pub fn record(file: impl Into<PathBuf>) -> Self {
+        Self {
+            mode: CassetteMode::Record,
+            file: file.into(),
+            interactions: vec![],
+            played: vec![],
+            output: None,
+        }
+    }
+
+    /// Load a recording from the file to replay it.
+    pub fn replay(file: impl Into<PathBuf>) -> Result<Self, RedditError> {
+        let file = file.into();
+        let json = std::fs::read_to_string(&file).map_err(|err| {
+            RedditError::CassetteError(format!("Failed to read {}: {err}", file.display()))
+        })?;
+        let interactions = json
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<Result<Vec<Interaction>, _>>()?;
+        info!(
+            "Replaying {} Reddit interactions from {}",
+            interactions.len(),
+            file.display()
+        );
+
+        Ok(Self {
+            mode: CassetteMode::Replay,
+            file,
+            played: vec![false; interactions.len()],
+            interactions,
+            output: None,
+        })
+    }
+
+    /// The configured cassette, `None` if no mode is set.
+    pub fn from_config(config: &RedditConfig) -> Result<Option<Self>, RedditError> {
+        match config.cassette_mode {
+            None => Ok(None),
+            Some(CassetteMode::Record) => Ok(Some(Self::record(&config.cassette))),
+            Some(CassetteMode::Replay) => Self::replay(&config.cassette).map(Some),
+        }
+    }
+
+    pub fn mode(&self) -> CassetteMode {
+        self.mode
+    }
+
+    pub fn file(&self) -> &Path {
+        &self.file
+    }
+
+    /// Append the interaction to the file, so an interrupted session is kept too.
+    ///
+    /// The first interaction replaces an earlier recording.
+    pub async fn add(&mut self, interaction: &Interaction) -> Result<(), RedditError> {
+        debug!("Recording {} {:?}", interaction.path, interaction.query);
+        let mut line = serde_json::to_vec(interaction)?;
+        line.push(b'\n');
+        self.append(&line).await.map_err(|err| {
+            RedditError::CassetteError(format!("Failed to write {}: {err}", self.file.display()))
+        })?;
+        self.interactions.push(interaction.clone());
+        self.played.push(false);
+        Ok(())
+    }
+
+    async fn append(&mut self, line: &[u8]) -> std::io::Result<()> {
+        let output = match &mut self.output {
+            Some(output) => output,
+            None => {
+                if let Some(dir) = self.file.parent() {
+                    tokio::fs::create_dir_all(dir).await?;
+                }
+                self.output.insert(File::create(&self.file).await?)
+            }
+        };
+        output.write_all(line).await?;
+        output.flush().await
+    }
+
+    /// The recorded response to the request.
+    ///
+    /// Responses to a repeated request are served in the recorded order,
+    /// after that the last one is served again.
+    pub fn find(&mut self, path: &str, query: &[(String, String)]) -> Option<Interaction> {
+        let matching = |i: &Interaction| i.path == path && i.query == query;
+
+        let unplayed = self
+            .interactions
+            .iter()
+            .zip(&self.played)
+            .position(|(i, played)| !played && matching(i));
+        if let Some(index) = unplayed {
+            self.played[index] = true;
+            return Some(self.interactions[index].clone());
+        }
+        self.interactions
+            .iter()
+            .rev()
+            .find(|i| matching(i))
+            .cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn interaction(after: Option<&str>, page: u32) -> Interaction {
+        let mut query = vec![("limit".to_string(), "100".to_string())];
+        if let Some(after) = after {
+            query.push(("after".to_string(), after.to_string()));
+        }
+        Interaction {
+            path: "/r/Polska/new.json".to_string(),
+            query,
+            status: 200,
+            body: json!({ "page": page }),
+        }
+    }
+
+    fn temp_file(name: &str) -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("rmoods-cassette-{}", std::process::id()))
+            .join(name)
+    }
+
+    #[tokio::test]
+    async fn test_find_matches_query() {
+        let mut cassette = Cassette::record(temp_file("query.ndjson"));
+        cassette.add(&interaction(None, 1)).await.unwrap();
+        cassette.add(&interaction(Some("t3_a"), 2)).await.unwrap();
+
+        let query = interaction(Some("t3_a"), 2).query;
+        let found = cassette.find("/r/Polska/new.json", &query).unwrap();
+        assert_eq!(found.body, json!({ "page": 2 }));
+        assert_eq!(cassette.find("/r/poland/new.json", &query), None);
+    }
+
+    #[tokio::test]
+    async fn test_find_replays_repeated_requests_in_order() {
+        let mut cassette = Cassette::record(temp_file("repeated.ndjson"));
+        cassette.add(&interaction(None, 1)).await.unwrap();
+        cassette.add(&interaction(None, 2)).await.unwrap();
+
+        let query = interaction(None, 0).query;
+        let mut next = || cassette.find("/r/Polska/new.json", &query).unwrap().body;
+        assert_eq!(next(), json!({ "page": 1 }));
+        assert_eq!(next(), json!({ "page": 2 }));
+        assert_eq!(next(), json!({ "page": 2 }));
+    }
+
+    #[tokio::test]
+    async fn test_recording_can_be_replayed() {
+        let file = temp_file("roundtrip.ndjson");
+        let mut recording = Cassette::record(&file);
+        recording.add(&interaction(None, 1)).await.unwrap();
+        recording
+            .add(&Interaction {
+                path: "/r/doesnotexist/about.json".to_string(),
+                query: vec![],
+                status: 404,
+                body: json!({ "message": "Not Found", "error": 404 }),
+            })
+            .await
+            .unwrap();
+
+        let mut replay = Cassette::replay(&file).unwrap();
+        assert_eq!(replay.mode(), CassetteMode::Replay);
+        let not_found = replay.find("/r/doesnotexist/about.json", &[]).unwrap();
+        assert!(matches!(
+            not_found.into_result(),
+            Err(RedditError::ErrorStatus(StatusCode::NOT_FOUND))
+        ));
+        let page = replay
+            .find("/r/Polska/new.json", &interaction(None, 1).query)
+            .unwrap();
+        assert_eq!(page.into_result().unwrap(), json!({ "page": 1 }));
+    }
+
+    #[test]
+    fn test_replay_of_missing_file_fails() {
+        let result = Cassette::replay(temp_file("missing.ndjson"));
+        assert!(matches!(result, Err(RedditError::CassetteError(_))));
+    }
+}
@@ -1,16 +1,88 @@
-use http::StatusCode;
+use crate::config::RedditConfig;
+use crate::metrics;
+use chrono::{DateTime, Utc};
+use http::{HeaderMap, StatusCode};
 use log::{debug, info, warn};
 use log_derive::logfn;
 use serde_json::Value;
+use std::sync::Arc;
 use std::time::SystemTime;
+use tokio::sync::Mutex;
+use tracing::field::Empty;
+use tracing::instrument;
 
 use super::{
     auth::{RedditAccessToken, RedditApp},
+    cache::{CacheKey, CacheStats, ResponseCache},
+    cassette::{Cassette, CassetteMode, Interaction},
     error::RedditError,
     model::{MoreComments, RawComment, RawContainer},
-    request::RedditRequest,
+    request::{PostDetailRequest, RedditRequest},
 };
 
+/// Base URLs of the Reddit API.
+///
+/// Reddit is used by default, tests point them at a mock server.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RedditUrls {
+    /// Base of every API request path, eg. `https://oauth.reddit.com`
+    pub api: String,
+    /// Full URL of the access token endpoint
+    pub access_token: String,
+}
+
+impl Default for RedditUrls {
+    fn default() -> Self {
+        Self {
+            api: "https://oauth.reddit.com".to_string(),
+            access_token: "https://www.reddit.com/api/v1/access_token".to_string(),
+        }
+    }
+}
+
+impl RedditUrls {
+    /// Reddit's URLs, or the configured ones, eg. to run against a mock server.
+    pub fn from_config(config: &RedditConfig) -> Self {
+        config
+            .url
+            .as_deref()
+            .map(Self::with_base)
+            .unwrap_or_default()
+    }
+
+    /// A server serving both the API and the access tokens under the paths Reddit uses.
+    pub fn with_base(base: &str) -> Self {
+        let base = base.trim_end_matches('/');
+        Self {
+            api: base.to_string(),
+            access_token: format!("{base}/api/v1/access_token"),
+        }
+    }
+}
+
+/// Rate limit state reported by Reddit in the headers of the last response.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    /// Requests left in the current period
+    pub remaining: f32,
+    /// Seconds until the period resets
+    pub reset: u32,
+    /// Requests made in the current period
+    pub used: u32,
+}
+
+impl RateLimit {
+    /// Read the `x-ratelimit-*` headers, `None` if any of them is missing or malformed.
+    fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let header = |name: &str| headers.get(name)?.to_str().ok();
+        Some(Self {
+            remaining: header("x-ratelimit-remaining")?.parse().ok()?,
+            reset: header("x-ratelimit-reset")?.parse().ok()?,
+            used: header("x-ratelimit-used")?.parse().ok()?,
+        })
+    }
+}
+
 /// Manages a collection of RedditApp clients and their access tokens.
 ///
 /// In the future, it's planned to multiplex Reddit requests by using multiple apps and their request quotas all at once.
@@ -22,27 +94,68 @@ pub struct RedditConnection {
     pub(crate) access_token: RedditAccessToken,
     /// The connection's own HTTP client, decoupled from our main app. Can remove, but it would hurt performance a bit when making many requests.
     pub(crate) http: reqwest::Client,
+    /// Where the requests are sent
+    pub(crate) urls: RedditUrls,
+    /// Rate limit reported with the last response, `None` before the first request
+    rate_limit: Option<RateLimit>,
+    /// When the last response was received from Reddit, `None` if it came from the cache or a cassette
+    fetched_at: Option<DateTime<Utc>>,
+    /// Cassette to record to or replay from, shared by all clones of the connection
+    cassette: Option<Arc<Mutex<Cassette>>>,
+    /// Cache of the responses, shared by all clones of the connection
+    cache: Option<ResponseCache>,
+    /// Skip cached responses, fresh ones are still cached
+    bypass_cache: bool,
 }
 
 impl RedditConnection {
-    /// Read credentials from the environment and create a new [RedditConnection]
+    /// Create a new [RedditConnection] with the configured credentials, URLs and cassette.
+    ///
+    /// When replaying a cassette, no credentials are needed and Reddit isn't contacted.
     #[logfn(
         err = "ERROR",
         fmt = "Fetcher - Failed to create RedditConnection: {:?}"
     )]
-    pub async fn new(http: reqwest::Client) -> Result<RedditConnection, RedditError> {
-        let id = std::env::var("CLIENT_ID").expect("CLIENT_ID should be set");
-        let secret = std::env::var("CLIENT_SECRET").expect("CLIENT_SECRET should be set");
-
-        assert!(!id.is_empty());
-        assert!(!secret.is_empty());
+    pub async fn new(
+        http: reqwest::Client,
+        config: &RedditConfig,
+    ) -> Result<RedditConnection, RedditError> {
+        let cassette = Cassette::from_config(config)?;
+        if let Some(cassette) = &cassette {
+            info!(
+                "Reddit cassette mode {:?}, file: {}",
+                cassette.mode(),
+                cassette.file().display()
+            );
+        }
+        let cassette = match cassette {
+            Some(cassette) if cassette.mode() == CassetteMode::Replay => {
+                return Ok(Self::replay(http, cassette))
+            }
+            cassette => cassette,
+        };
 
-        let client = RedditApp::new(id, secret);
+        let app = RedditApp::new(config.client_id.clone(), config.client_secret.clone());
+        let connection = Self::connect(http, app, RedditUrls::from_config(config)).await?;
+        Ok(match cassette {
+            Some(cassette) => connection.with_cassette(cassette),
+            None => connection,
+        })
+    }
 
+    /// Create a new [RedditConnection] for the app, fetching its first access token.
+    pub async fn connect(
+        http: reqwest::Client,
+        client: RedditApp,
+        urls: RedditUrls,
+    ) -> Result<RedditConnection, RedditError> {
         info!("Fetching initial access token");
-        let access_token = client.fetch_access_token(&http).await.or(Err(
-            RedditError::FailedToFetchAccessToken(client.client_id.to_string()),
-        ))?;
+        let access_token = client
+            .fetch_access_token(&http, &urls.access_token)
+            .await
+            .or(Err(RedditError::FailedToFetchAccessToken(
+                client.client_id.to_string(),
+            )))?;
         debug!("Access token: {:?}", access_token);
         info!("Done fetching access token");
 
@@ -50,44 +163,227 @@ impl RedditConnection {
             client,
             access_token,
             http,
+            urls,
+            rate_limit: None,
+            fetched_at: None,
+            cassette: None,
+            cache: None,
+            bypass_cache: false,
         })
     }
 
+    /// Create a [RedditConnection] serving the responses recorded in the cassette, without contacting Reddit.
+    pub fn replay(http: reqwest::Client, cassette: Cassette) -> RedditConnection {
+        RedditConnection {
+            client: RedditApp::new(String::new(), String::new()),
+            access_token: RedditAccessToken::offline(),
+            http,
+            urls: RedditUrls::default(),
+            rate_limit: None,
+            fetched_at: None,
+            cassette: Some(Arc::new(Mutex::new(cassette))),
+            cache: None,
+            bypass_cache: false,
+        }
+    }
+
+    /// Record to or replay from the cassette, depending on its mode.
+    pub fn with_cassette(self, cassette: Cassette) -> RedditConnection {
+        RedditConnection {
+            cassette: Some(Arc::new(Mutex::new(cassette))),
+            ..self
+        }
+    }
+
+    /// Serve responses from the cache when they are fresh.
+    pub fn with_cache(self, cache: ResponseCache) -> RedditConnection {
+        RedditConnection {
+            cache: Some(cache),
+            ..self
+        }
+    }
+
+    /// Skip cached responses in the requests made with this connection.
+    ///
+    /// Meant for a clone used to serve a single API request.
+    pub fn bypass_cache(&mut self, bypass: bool) {
+        self.bypass_cache = bypass;
+    }
+
+    /// Hits and misses of the cache, `None` if there's no cache.
+    pub fn cache_stats(&self) -> Option<CacheStats> {
+        self.cache.as_ref().map(|c| c.stats())
+    }
+
+    /// Delete the stale responses of the cache, if there's one.
+    pub async fn purge_expired_cache(&self) {
+        if let Some(cache) = &self.cache {
+            cache.purge_expired().await;
+        }
+    }
+
+    /// Rate limit reported by Reddit with the last response.
+    pub fn rate_limit(&self) -> Option<RateLimit> {
+        self.rate_limit
+    }
+
+    /// When the last response was received from Reddit.
+    ///
+    /// `None` if it was served from the cache or replayed from a cassette, so it's not a new observation.
+    pub fn fetched_at(&self) -> Option<DateTime<Utc>> {
+        self.fetched_at
+    }
+
     #[logfn(err = "ERROR", fmt = "Failed to refresh access token: {0}")]
     async fn refresh_access_token(&mut self) -> Result<(), RedditError> {
         if self.access_token.is_expired() {
             warn!("Access token expired, fetching new one");
-            self.access_token = self.client.fetch_access_token(&self.http).await?;
+            self.access_token = self
+                .client
+                .fetch_access_token(&self.http, &self.urls.access_token)
+                .await?;
             info!("New access token fetched");
         }
         Ok(())
     }
 
+    /// Send the request to Reddit, or replay the response from the cassette.
+    ///
+    /// A response with an error status is returned as [RedditError::ErrorStatus].
     #[logfn(err = "ERROR", fmt = "Failed inner_fetch: {0}")]
     async fn inner_fetch(
         &mut self,
-        url: String,
+        path: String,
         query: Vec<(&str, String)>,
-    ) -> Result<Value, reqwest::Error> {
+    ) -> Result<Value, RedditError> {
+        let query = query
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v))
+            .collect::<Vec<_>>();
+
+        let replaying = match &self.cassette {
+            Some(cassette) => {
+                let mut cassette = cassette.lock().await;
+                (cassette.mode() == CassetteMode::Replay).then(|| cassette.find(&path, &query))
+            }
+            None => None,
+        };
+        let interaction = match replaying {
+            Some(Some(interaction)) => {
+                info!("Replaying: {path:?}\nWith query params: {query:?}");
+                self.fetched_at = None;
+                interaction
+            }
+            Some(None) => {
+                return Err(RedditError::CassetteError(format!(
+                    "No recorded response to {path} with query params {query:?}"
+                )))
+            }
+            None => {
+                let interaction = self.send(path, query).await?;
+                self.fetched_at = Some(Utc::now());
+                if let Some(cassette) = &self.cassette {
+                    cassette.lock().await.add(&interaction).await?;
+                }
+                interaction
+            }
+        };
+
+        interaction.into_result()
+    }
+
+    /// Send the request to Reddit.
+    #[instrument(
+        name = "reddit_request",
+        skip_all,
+        fields(endpoint = metrics::reddit_endpoint(&path), status = Empty)
+    )]
+    async fn send(
+        &mut self,
+        path: String,
+        query: Vec<(String, String)>,
+    ) -> Result<Interaction, RedditError> {
+        let url = format!("{}{path}", self.urls.api);
         info!("Fetching data from: {url:?}\nWith query params: {query:?}");
 
         let req = self
             .http
             .get(url)
             .query(&query)
-            .bearer_auth(&self.access_token.token())
+            .bearer_auth(self.access_token.token())
             .build()?;
 
+        let endpoint = metrics::reddit_endpoint(&path);
         let start = SystemTime::now();
-        let res = self.http.execute(req).await?;
+        let res = self.http.execute(req).await.inspect_err(|_| {
+            metrics::REDDIT_REQUESTS
+                .with_label_values(&[&endpoint, "error"])
+                .inc();
+        })?;
         let elapsed = SystemTime::now().duration_since(start).unwrap();
+        metrics::REDDIT_REQUESTS
+            .with_label_values(&[&endpoint, res.status().as_str()])
+            .inc();
+        tracing::Span::current().record("status", res.status().as_u16());
 
         let header_log = create_ratelimit_log(&res);
         info!("Headers: {}", header_log.trim_end());
+        self.rate_limit = RateLimit::from_headers(res.headers()).or(self.rate_limit);
+        if let Some(rate_limit) = self.rate_limit {
+            metrics::REDDIT_RATELIMIT_REMAINING.set(rate_limit.remaining.into());
+        }
 
         info!("Data fetched successfully. Took {:?}", elapsed);
 
-        Ok(res.json().await?)
+        let status = res.status();
+        let bytes = res.bytes().await?;
+        // Error responses aren't always JSON, only data must parse
+        let body = match serde_json::from_slice(&bytes) {
+            Ok(body) => body,
+            Err(_) if !status.is_success() => Value::Null,
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(Interaction {
+            path,
+            query,
+            status: status.as_u16(),
+            body,
+        })
+    }
+
+    /// Execute a request to the Reddit API and return the unparsed response.
+    ///
+    /// A fresh cached response is returned instead, unless the cache is bypassed.
+    async fn fetch_json(&mut self, request: &impl RedditRequest) -> Result<Value, RedditError> {
+        let parts = request.to_request_parts();
+        let key = CacheKey::new(&parts);
+        if let Some(cache) = &self.cache {
+            if self.bypass_cache {
+                cache.record_bypass();
+            } else if let Some(json) = cache.get(&key).await {
+                self.fetched_at = None;
+                return Ok(json);
+            }
+        }
+
+        self.refresh_access_token().await?;
+        let (url, query) = parts;
+
+        let json = self
+            .inner_fetch(url, query)
+            .await
+            .map_err(|err| match err {
+                RedditError::ErrorStatus(StatusCode::NOT_FOUND) => {
+                    RedditError::ResourceNotFound(request.resource_name())
+                }
+                err => err,
+            })?;
+
+        if let Some(cache) = &self.cache {
+            cache.put(key, request.cache_kind(), &json).await;
+        }
+        Ok(json)
     }
 
     /// Execute a request to the Reddit API.
@@ -98,18 +394,7 @@ impl RedditConnection {
         &mut self,
         request: impl RedditRequest,
     ) -> Result<(RawContainer, Option<String>), RedditError> {
-        self.refresh_access_token().await?;
-        let (url, query) = request.to_request_parts();
-
-        let json = self.inner_fetch(url, query).await;
-
-        let json = json.map_err(|err| match err.status() {
-            Some(status) => match status {
-                StatusCode::NOT_FOUND => RedditError::ResourceNotFound(request.resource_name()),
-                _ => RedditError::HttpError(err),
-            },
-            None => RedditError::HttpError(err),
-        })?;
+        let json = self.fetch_json(&request).await?;
 
         // Special case for comments, as they are wrapped in an array
         // First element of said array is the post, second is the comments
@@ -132,6 +417,26 @@ impl RedditConnection {
         }
     }
 
+    /// Fetch a post and its comments.
+    ///
+    /// Reddit responds with `[Listing<Post>, Listing<Comment>]`, both listings are returned.
+    #[logfn(err = "ERROR", fmt = "Failed to fetch post detail: {0}")]
+    pub async fn fetch_post_detail(
+        &mut self,
+        request: PostDetailRequest,
+    ) -> Result<(RawContainer, RawContainer), RedditError> {
+        let json = self.fetch_json(&request).await?;
+
+        let listings = serde_json::from_value::<Vec<RawContainer>>(json)?;
+        let [post, comments] = <[RawContainer; 2]>::try_from(listings).map_err(|_| {
+            RedditError::OtherJsonError(
+                "Expected [Listing<Post>, Listing<Comment>] in response to post detail request"
+                    .to_string(),
+            )
+        })?;
+        Ok((post, comments))
+    }
+
     #[logfn(err = "ERROR", fmt = "Failed to fetch more comments: {0}")]
     pub async fn fetch_more_comments(
         &mut self,
@@ -147,14 +452,11 @@ impl RedditConnection {
         for (url, query) in request_parts_vec {
             let json = self.inner_fetch(url, query).await;
 
-            let json = json.map_err(|err| match err.status() {
-                Some(status) => match status {
-                    StatusCode::NOT_FOUND => {
-                        RedditError::ResourceNotFound(format!("{}/children", more.parent_id))
-                    }
-                    _ => RedditError::HttpError(err),
-                },
-                None => RedditError::HttpError(err),
+            let json = json.map_err(|err| match err {
+                RedditError::ErrorStatus(StatusCode::NOT_FOUND) => {
+                    RedditError::ResourceNotFound(format!("{}/children", more.parent_id))
+                }
+                err => err,
             })?;
 
             requests_made += 1;
@@ -181,8 +483,7 @@ impl RedditConnection {
                 return Err(RedditError::OtherJsonError(
                     "Expected json.data.things to be present in response to MoreComments request"
                         .to_string(),
-                )
-                .into());
+                ));
             }
             if requests_made >= requests_left {
                 debug!("No more comments - no requests left");
@@ -15,6 +15,14 @@ pub enum RedditError {
     #[error("HTTP Error: `{0}`")]
     HttpError(#[from] reqwest::Error),
 
+    /// Reddit responded with an error status other than 404.
+    #[error("Reddit responded with status {0}")]
+    ErrorStatus(http::StatusCode),
+
+    /// The cassette couldn't be read or written, or it has no response to a replayed request.
+    #[error("Cassette error: {0}")]
+    CassetteError(String),
+
     /// Deserialization to our structs failed.
     ///
     /// Possible causes:
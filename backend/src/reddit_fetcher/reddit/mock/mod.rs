@@ -0,0 +1,201 @@
+//! Mock Reddit API serving canned responses, so the fetcher can be tested offline.
+//!
+//! Every API response carries Reddit's rate limit headers. Unknown paths get Reddit's 404 response.
+//!
+//! ```ignore
+//! let server = MockReddit::new()
+//!     .route("/r/Polska/new.json", fixtures::subreddit_posts())
+//!     .route("/r/Polska/new.json", fixtures::subreddit_posts_page2())
+//!     .start()
+//!     .await;
+//! let connection = server.connection().await;
+//! ```
+
+use crate::reddit_fetcher::reddit::auth::RedditApp;
+use crate::reddit_fetcher::reddit::connection::{RedditConnection, RedditUrls};
+use axum::{
+    extract::{Query, State},
+    http::{header, HeaderMap, StatusCode, Uri},
+    response::{IntoResponse, Response},
+    routing::post,
+    Json, Router,
+};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Access token handed out by the mock, requests without it are rejected.
+pub const MOCK_TOKEN: &str = "mock-access-token";
+/// Requests allowed in a rate limit period, like Reddit's free tier.
+pub const RATE_LIMIT: u32 = 1000;
+/// Seconds until the rate limit period resets, reported with every response.
+pub const RATE_LIMIT_RESET: u32 = 300;
+
+/// Hand-written responses, shaped like the ones of the Reddit API.
+pub mod fixtures {
+    use serde_json::Value;
+
+    fn parse(json: &str) -> Value {
+        serde_json::from_str(json).unwrap()
+    }
+
+    /// First page of `r/Polska/new.json`, continued by [subreddit_posts_page2].
+    pub fn subreddit_posts() -> Value {
+        parse(include_str!("../model/fixtures/subreddit_posts.json"))
+    }
+
+    /// Last page of `r/Polska/new.json`.
+    pub fn subreddit_posts_page2() -> Value {
+        parse(include_str!("fixtures/subreddit_posts_page2.json"))
+    }
+
+    /// `r/Polska/comments/1g7dw8m.json`: the post, a comment with a reply and a `more` stub.
+    pub fn post_comments() -> Value {
+        parse(include_str!("fixtures/post_comments.json"))
+    }
+
+    /// `api/morechildren` response for the `more` stub of [post_comments].
+    pub fn morechildren() -> Value {
+        parse(include_str!("fixtures/morechildren.json"))
+    }
+}
+
+/// Builder of the mock server: which responses are served on which paths.
+#[derive(Debug, Default)]
+pub struct MockReddit {
+    /// Pages served for every path, in order
+    routes: HashMap<String, Vec<Value>>,
+}
+
+impl MockReddit {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serve the response on the path.
+    ///
+    /// Routing the same path again adds the next page of a listing. It's served when requested
+    /// with the `after` of the previous page, like Reddit does.
+    pub fn route(mut self, path: &str, response: Value) -> Self {
+        self.routes
+            .entry(path.to_string())
+            .or_default()
+            .push(response);
+        self
+    }
+
+    /// Start the server on a random local port.
+    pub async fn start(self) -> MockServer {
+        let state = Arc::new(MockState {
+            routes: self.routes,
+            requests: Mutex::new(vec![]),
+        });
+
+        let app = Router::new()
+            .route("/api/v1/access_token", post(access_token))
+            .fallback(api)
+            .with_state(state.clone());
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        MockServer {
+            urls: RedditUrls::with_base(&format!("http://{address}")),
+            state,
+        }
+    }
+}
+
+/// A running mock server.
+pub struct MockServer {
+    /// URLs to connect to the mock
+    pub urls: RedditUrls,
+    state: Arc<MockState>,
+}
+
+impl MockServer {
+    /// A connection to the mock, authenticated with [MOCK_TOKEN].
+    pub async fn connection(&self) -> RedditConnection {
+        let app = RedditApp::new("mock-client".to_string(), "mock-secret".to_string());
+        RedditConnection::connect(reqwest::Client::new(), app, self.urls.clone())
+            .await
+            .unwrap()
+    }
+
+    /// API requests received so far, as paths with query strings, eg. `/r/Polska/new.json?limit=100`.
+    pub fn requests(&self) -> Vec<String> {
+        self.state.requests.lock().unwrap().clone()
+    }
+}
+
+struct MockState {
+    routes: HashMap<String, Vec<Value>>,
+    requests: Mutex<Vec<String>>,
+}
+
+impl MockState {
+    /// The page requested with the `after` cursor, `None` if there's no such page.
+    fn page(&self, path: &str, after: Option<&String>) -> Option<&Value> {
+        let pages = self.routes.get(path)?;
+        let Some(after) = after else {
+            return pages.first();
+        };
+        pages
+            .windows(2)
+            .find(|pair| pair[0]["data"]["after"].as_str() == Some(after))
+            .map(|pair| &pair[1])
+    }
+}
+
+async fn access_token(headers: HeaderMap) -> Response {
+    if !headers.contains_key(header::AUTHORIZATION) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    Json(json!({
+        "access_token": MOCK_TOKEN,
+        "token_type": "bearer",
+        "expires_in": 86400,
+        "scope": "*"
+    }))
+    .into_response()
+}
+
+async fn api(
+    State(state): State<Arc<MockState>>,
+    uri: Uri,
+    headers: HeaderMap,
+    Query(query): Query<HashMap<String, String>>,
+) -> Response {
+    let authorized = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .is_some_and(|h| h == format!("Bearer {MOCK_TOKEN}"));
+    if !authorized {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+
+    let used = {
+        let mut requests = state.requests.lock().unwrap();
+        requests.push(uri.to_string());
+        requests.len() as u32
+    };
+    let rate_limit = [
+        (
+            "x-ratelimit-remaining",
+            format!("{:.1}", RATE_LIMIT.saturating_sub(used) as f32),
+        ),
+        ("x-ratelimit-reset", RATE_LIMIT_RESET.to_string()),
+        ("x-ratelimit-used", used.to_string()),
+    ];
+
+    match state.page(uri.path(), query.get("after")) {
+        Some(page) => (rate_limit, Json(page.clone())).into_response(),
+        None => (
+            StatusCode::NOT_FOUND,
+            rate_limit,
+            Json(json!({ "message": "Not Found", "error": 404 })),
+        )
+            .into_response(),
+    }
+}
@@ -1,6 +1,11 @@
 pub mod auth;
+pub mod cache;
+pub mod cassette;
 pub mod connection;
 pub mod error;
+#[cfg(test)]
+pub mod mock;
 pub mod model;
 pub mod request;
+#[cfg(test)]
 mod tests;
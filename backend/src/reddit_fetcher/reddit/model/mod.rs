@@ -1,10 +1,17 @@
 #![allow(dead_code)]
 
+use crate::metrics;
+use chrono::{DateTime, Utc};
 use derive_getters::Getters;
+use log::warn;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{json, Value};
 use serde_with::{serde_as, NoneAsEmptyString};
 
+#[cfg(test)]
+mod tests;
+pub mod timestamp;
+
 /// Represents a generic Reddit data container.
 ///
 /// Reddit API uses a special structure to represent varying types of object in a uniform way.
@@ -24,7 +31,7 @@ use serde_with::{serde_as, NoneAsEmptyString};
 /// So, we have a `kind` field and the corresponding `data` field, which has an object of the type indicated by `kind`.
 ///
 /// [Serde](https://serde.rs) provides a convenient macro, that lets us describe how our enum should be handled in serialization/deserialization:
-/// ```rust
+/// ```ignore
 /// #[serde(tag = "kind", content = "data")]
 /// enum Foo {}
 /// ```
@@ -45,7 +52,12 @@ use serde_with::{serde_as, NoneAsEmptyString};
 ///
 /// ### Recursive case
 /// For the [KindContainer::Listing], this works recursively, since [RedditListing] is basically a list of [KindContainer]s.
-#[derive(Debug, Serialize, Deserialize, Clone)]
+///
+/// ### Unknown kinds
+/// Serde's derived tagging fails on a `kind` it doesn't know, so [Deserialize] is implemented by hand,
+/// dispatching on `kind` the same way. Unknown kinds become [RawContainer::Unknown] instead of an error
+/// and are skipped from listings, counted by [REDDIT_SKIPPED_ITEMS](crate::metrics::REDDIT_SKIPPED_ITEMS).
+#[derive(Debug, Serialize, Clone)]
 #[serde(tag = "kind", content = "data")]
 pub enum RawContainer {
     #[serde(rename = "Listing")]
@@ -64,13 +76,68 @@ pub enum RawContainer {
     Post(Box<RawPost>), // Link/Post
 
     #[serde(rename = "t4")]
-    Message(Box<Value>), // Message TODO
+    Message(Box<RawMessage>),
 
     #[serde(rename = "t5")]
     SubredditAbout(Box<RawSubredditAbout>),
 
     #[serde(rename = "t6")]
-    Award(Box<Value>), // Award TODO
+    Award(Box<RawAward>),
+
+    /// Object of a kind we don't model, eg. t9, kept as received with its `kind` and `data`
+    /// so it's serialized back unchanged
+    #[serde(untagged)]
+    Unknown(Box<Value>),
+}
+
+impl<'de> Deserialize<'de> for RawContainer {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct Tagged {
+            kind: String,
+            #[serde(default)]
+            data: Value,
+        }
+
+        fn content<T: serde::de::DeserializeOwned, E: serde::de::Error>(
+            data: Value,
+        ) -> Result<Box<T>, E> {
+            serde_json::from_value(data).map_err(E::custom)
+        }
+
+        let Tagged { kind, data } = Tagged::deserialize(deserializer)?;
+        Ok(match kind.as_str() {
+            "Listing" => RawContainer::Listing(content(data)?),
+            "more" => RawContainer::More(content(data)?),
+            "t1" => RawContainer::Comment(content(data)?),
+            "t2" => RawContainer::UserAbout(content(data)?),
+            "t3" => RawContainer::Post(content(data)?),
+            "t4" => RawContainer::Message(content(data)?),
+            "t5" => RawContainer::SubredditAbout(content(data)?),
+            "t6" => RawContainer::Award(content(data)?),
+            _ => RawContainer::Unknown(Box::new(json!({ "kind": kind, "data": data }))),
+        })
+    }
+}
+
+/// Text Reddit puts in place of the author or the body of an item deleted by its author.
+pub const DELETED: &str = "[deleted]";
+/// Text Reddit puts in place of the body of an item removed by moderators or Reddit.
+pub const REMOVED: &str = "[removed]";
+
+/// Is the item still visible, or was it taken down?
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContentStatus {
+    #[default]
+    Visible,
+    /// Deleted by its author
+    Deleted,
+    /// Removed by moderators or Reddit
+    Removed,
 }
 
 /// List of IDs of items to fetch to get the ones that didn't fit in the first response.
@@ -91,6 +158,8 @@ pub struct MoreComments {
 pub struct RawListing {
     pub after: Option<String>,
     pub dist: Option<u32>,
+    /// Items of unknown kinds are left out, so a single new object type doesn't break the whole response
+    #[serde(deserialize_with = "deserialize_children")]
     pub children: Vec<RawContainer>,
     pub before: Option<String>,
 }
@@ -112,13 +181,58 @@ pub struct RawComment {
     /// Standard url to the comment, without `.json` at the end
     permalink: String,
     /// UNIX timestamp of the comment creation
-    created_utc: f32,
+    #[serde(with = "timestamp")]
+    created_utc: DateTime<Utc>,
     /// Depth of the comment in the thread. 0 is the top-level comment, 1 is a reply to the top-level comment, etc.
     ///
     /// When fetching posts/comments from a user, this field is always `None`.
     depth: Option<u32>,
     /// Upvotes - downvotes
     score: i64,
+    /// Fullname without the kind info, eg. lm3k2x9
+    id: String,
+    /// Fullname, eg. t1_lm3k2x9
+    name: String,
+    /// Fullname of the parent: the post for top-level comments (t3_), the comment for replies (t1_)
+    parent_id: String,
+    /// Fullname of the post the comment is in, eg. t3_8z1v1z
+    link_id: String,
+    /// UNIX timestamp of the last edit, `None` if the comment was never edited
+    #[serde(
+        default,
+        deserialize_with = "deserialize_edited",
+        serialize_with = "timestamp::option::serialize"
+    )]
+    edited: Option<DateTime<Utc>>,
+    /// Set when the author commented as a moderator or admin, eg. moderator
+    #[serde(default, deserialize_with = "deserialize_optional_text")]
+    distinguished: Option<String>,
+    /// Is the comment stickied to the top of the thread?
+    #[serde(default)]
+    stickied: bool,
+    /// 1 if the comment got a similar number of upvotes and downvotes, 0 otherwise
+    #[serde(default)]
+    controversiality: u32,
+    /// Flair of the author in the subreddit, eg. Kraków
+    #[serde(default, deserialize_with = "deserialize_optional_text")]
+    author_flair_text: Option<String>,
+}
+
+impl RawComment {
+    /// Deleted and removed comments keep their place in the thread, but lose their text.
+    pub fn status(&self) -> ContentStatus {
+        match self.body.as_str() {
+            REMOVED => ContentStatus::Removed,
+            DELETED => ContentStatus::Deleted,
+            // An author who deleted their account shows up as `[deleted]`, but the text stays
+            _ => ContentStatus::Visible,
+        }
+    }
+
+    /// Is it a top-level comment, a direct reply to the post?
+    pub fn is_top_level(&self) -> bool {
+        self.parent_id == self.link_id
+    }
 }
 
 /// Contains some properties of a Reddit post. For some real-world examples see
@@ -138,7 +252,8 @@ pub struct RawPost {
     /// Upvotes - downvotes
     score: i64,
     /// UNIX timestamp of the post creation
-    created_utc: f32,
+    #[serde(with = "timestamp")]
+    created_utc: DateTime<Utc>,
     /// Is the post NSFW?
     /// This is inconsistent with the `over18` field in Subreddit. THIS IS INTENTED AND CORRECT.
     over_18: bool,
@@ -154,6 +269,122 @@ pub struct RawPost {
     url: String,
     /// Is the post stickied?
     stickied: bool,
+    /// Flair of the post, eg. Polityka
+    #[serde(default, deserialize_with = "deserialize_optional_text")]
+    link_flair_text: Option<String>,
+    /// Share of upvotes among all votes, eg. 0.92. Missing in some responses
+    #[serde(default)]
+    upvote_ratio: Option<f32>,
+    /// UNIX timestamp of the last edit, `None` if the post was never edited
+    #[serde(
+        default,
+        deserialize_with = "deserialize_edited",
+        serialize_with = "timestamp::option::serialize"
+    )]
+    edited: Option<DateTime<Utc>>,
+    /// Who removed the post, eg. moderator, deleted, automod_filtered. `None` if it's not removed
+    #[serde(default, deserialize_with = "deserialize_optional_text")]
+    removed_by_category: Option<String>,
+    /// Is it a text post? Link posts have `false`
+    #[serde(default)]
+    is_self: bool,
+    /// Domain of the link, eg. youtube.com, or self.Polska for text posts
+    #[serde(default)]
+    domain: String,
+    /// Path of the post on Reddit, eg. /r/Polska/comments/8z1v1z/lorem_ipsum/
+    #[serde(default)]
+    permalink: String,
+    /// Number of awards of any kind
+    #[serde(default)]
+    total_awards_received: u32,
+    /// Set when the author posted as a moderator or admin, eg. moderator
+    #[serde(default, deserialize_with = "deserialize_optional_text")]
+    distinguished: Option<String>,
+    /// Fullname of the original post, if this is a crosspost, eg. t3_8z1v1z
+    #[serde(default, deserialize_with = "deserialize_optional_text")]
+    crosspost_parent: Option<String>,
+}
+
+impl RawPost {
+    /// Was the post removed by a moderator, Reddit or its author?
+    pub fn is_removed(&self) -> bool {
+        self.removed_by_category.is_some()
+    }
+
+    /// Distinguishes posts deleted by their authors from the ones removed by moderators or Reddit.
+    pub fn status(&self) -> ContentStatus {
+        match self.removed_by_category.as_deref() {
+            None => ContentStatus::Visible,
+            Some("deleted" | "author") => ContentStatus::Deleted,
+            Some(_) => ContentStatus::Removed,
+        }
+    }
+
+    /// Was the post edited after being published?
+    pub fn is_edited(&self) -> bool {
+        self.edited.is_some()
+    }
+}
+
+/// A private message, or a comment reply or mention in the inbox.
+#[derive(Getters, Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct RawMessage {
+    /// Fullname without the kind info, eg. 2bx3d1
+    id: String,
+    /// Fullname, eg. t4_2bx3d1
+    name: String,
+    /// Username without `u/`, `None` for messages sent as a subreddit
+    #[serde(default, deserialize_with = "deserialize_optional_text")]
+    author: Option<String>,
+    /// Recipient, a username or a subreddit, eg. #Polska, for modmail
+    dest: String,
+    subject: String,
+    /// Message text
+    body: String,
+    /// UNIX timestamp of the message creation
+    #[serde(with = "timestamp")]
+    created_utc: DateTime<Utc>,
+    /// Is the message unread?
+    #[serde(default)]
+    new: bool,
+    /// Is it a comment reply or a username mention, not a private message?
+    #[serde(default)]
+    was_comment: bool,
+    /// Subreddit of the comment for comment replies and mentions, or the sending subreddit
+    #[serde(default, deserialize_with = "deserialize_optional_text")]
+    subreddit: Option<String>,
+    /// Fullname of the message or comment this one replies to
+    #[serde(default, deserialize_with = "deserialize_optional_text")]
+    parent_id: Option<String>,
+    /// Permalink of the comment, for comment replies and mentions
+    #[serde(default)]
+    context: String,
+    /// Set when the message was sent as a moderator or admin, eg. moderator
+    #[serde(default, deserialize_with = "deserialize_optional_text")]
+    distinguished: Option<String>,
+}
+
+/// An award given to a post or a comment, or one available to give.
+#[derive(Getters, Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct RawAward {
+    /// eg. gid_1 or award_5f123e3d-4f48-42f4-9c11-e98b566d5897
+    id: String,
+    /// eg. Silver
+    name: String,
+    #[serde(default)]
+    description: String,
+    /// Price in Reddit coins
+    #[serde(default)]
+    coin_price: u32,
+    /// Link to the award icon
+    #[serde(default)]
+    icon_url: String,
+    /// eg. global, community or moderator
+    #[serde(default)]
+    award_type: String,
+    /// How many times it was given to the item, when part of an item
+    #[serde(default)]
+    count: u32,
 }
 
 /// Reddit subreddit data
@@ -181,7 +412,8 @@ pub struct RawSubredditAbout {
     /// Banner background image, link needs to be parsed to get the actual image
     banner_background_image: String,
     /// UNIX timestamp of the subreddit creation
-    created_utc: f32,
+    #[serde(with = "timestamp")]
+    created_utc: DateTime<Utc>,
     /// Fullname of the subreddit creator, eg. t2_1w72
     id: String,
     /// Language of the subreddit, eg. pl
@@ -194,7 +426,7 @@ pub struct RawSubredditAbout {
 /// Contains some properties of a Reddit user. For some real-world examples see
 /// [this u/spez profile request.](https://www.reddit.com/user/spez/about.json)
 #[serde_as]
-#[derive(Getters, Debug, Hash, Eq, PartialEq, Clone, Serialize, Deserialize)]
+#[derive(Getters, Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct RawUserAbout {
     /// Is the user a Reddit employee?
     is_employee: bool,
@@ -214,6 +446,9 @@ pub struct RawUserAbout {
     total_karma: i64,
     /// Username without `u/`, eg. spez
     name: String,
+    /// UNIX timestamp of the account creation
+    #[serde(with = "timestamp")]
+    created_utc: DateTime<Utc>,
     /// Link to the user icon
     #[serde_as(as = "NoneAsEmptyString")]
     icon_img: Option<String>,
@@ -237,3 +472,43 @@ where
         _ => Ok(serde_json::from_value(replies).map_err(serde::de::Error::custom)?),
     }
 }
+
+/// Reddit sends `false` for items that were never edited and the UNIX timestamp of the last edit otherwise.
+/// Some old items have `true` instead of the timestamp, it's kept as an edit at an unknown time, the UNIX epoch.
+fn deserialize_edited<'de, D>(deserializer: D) -> Result<Option<DateTime<Utc>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    match Value::deserialize(deserializer)? {
+        Value::Bool(true) => Ok(Some(DateTime::UNIX_EPOCH)),
+        Value::Number(seconds) => Ok(seconds.as_f64().and_then(timestamp::from_seconds)),
+        _ => Ok(None),
+    }
+}
+
+/// Optional texts are sometimes `null` and sometimes an empty string, both mean there's no value.
+fn deserialize_optional_text<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let text = Option::<String>::deserialize(deserializer)?;
+    Ok(text.filter(|t| !t.is_empty()))
+}
+
+/// Drops the items of unknown kinds from a listing, counting them.
+fn deserialize_children<'de, D>(deserializer: D) -> Result<Vec<RawContainer>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let mut children = Vec::<RawContainer>::deserialize(deserializer)?;
+    children.retain(|child| match child {
+        RawContainer::Unknown(raw) => {
+            let kind = raw["kind"].as_str().unwrap_or_default();
+            warn!("Skipping a listing item of unknown kind: {kind}");
+            metrics::REDDIT_SKIPPED_ITEMS.inc();
+            false
+        }
+        _ => true,
+    });
+    Ok(children)
+}
@@ -0,0 +1,216 @@
+use crate::metrics;
+use crate::reddit_fetcher::reddit::model::{ContentStatus, RawComment, RawContainer, RawPost};
+use chrono::DateTime;
+use serde_json::json;
+
+fn children(fixture: &str) -> Vec<RawContainer> {
+    let container: RawContainer = serde_json::from_str(fixture).unwrap();
+    let RawContainer::Listing(listing) = container else {
+        panic!("Fixture is not a listing");
+    };
+    listing.children
+}
+
+fn posts(fixture: &str) -> Vec<RawPost> {
+    children(fixture)
+        .into_iter()
+        .map(|child| match child {
+            RawContainer::Post(post) => *post,
+            _ => panic!("Fixture listing has a non-post child"),
+        })
+        .collect()
+}
+
+fn comments(fixture: &str) -> Vec<RawComment> {
+    children(fixture)
+        .into_iter()
+        .map(|child| match child {
+            RawContainer::Comment(comment) => *comment,
+            _ => panic!("Fixture listing has a non-comment child"),
+        })
+        .collect()
+}
+
+#[test]
+fn test_parse_post_fields() {
+    let posts = posts(include_str!("fixtures/subreddit_posts.json"));
+    assert_eq!(posts.len(), 3);
+
+    let post = &posts[0];
+    assert_eq!(post.link_flair_text().as_deref(), Some("Pytanie"));
+    assert_eq!(*post.upvote_ratio(), Some(0.94));
+    assert_eq!(post.edited().map(|e| e.timestamp()), Some(1729332514));
+    assert_eq!(post.created_utc().timestamp(), 1729327268);
+    assert!(post.is_edited());
+    assert!(!post.is_removed());
+    assert!(*post.is_self());
+    assert_eq!(post.domain(), "self.Polska");
+    assert_eq!(
+        post.permalink(),
+        "/r/Polska/comments/1g7e1g6/najlepsza_piekarnia_w_krakowie/"
+    );
+    assert_eq!(*post.total_awards_received(), 2);
+    assert_eq!(*post.distinguished(), None);
+    assert_eq!(*post.crosspost_parent(), None);
+}
+
+#[test]
+fn test_parse_removed_crosspost() {
+    let posts = posts(include_str!("fixtures/subreddit_posts.json"));
+
+    let post = &posts[1];
+    assert!(post.is_removed());
+    assert_eq!(post.status(), ContentStatus::Removed);
+    assert_eq!(post.removed_by_category().as_deref(), Some("moderator"));
+    // Empty flair is no flair
+    assert_eq!(*post.link_flair_text(), None);
+    assert_eq!(*post.edited(), None);
+    // Crossposts link to the original post
+    assert!(!*post.is_self());
+    assert_eq!(post.domain(), "self.poland");
+    assert_eq!(post.crosspost_parent().as_deref(), Some("t3_1g7avx9"));
+}
+
+#[test]
+fn test_parse_inconsistent_post_fields() {
+    let posts = posts(include_str!("fixtures/subreddit_posts.json"));
+
+    let post = &posts[2];
+    // Old posts have `edited: true` without the timestamp
+    assert_eq!(*post.edited(), Some(DateTime::UNIX_EPOCH));
+    // Not every response has the ratio
+    assert_eq!(*post.upvote_ratio(), None);
+    assert_eq!(post.distinguished().as_deref(), Some("moderator"));
+    assert_eq!(*post.total_awards_received(), 5);
+}
+
+#[test]
+fn test_parse_comment_fields() {
+    let comments = comments(include_str!("fixtures/post_comments.json"));
+    assert_eq!(comments.len(), 3);
+
+    let comment = &comments[0];
+    assert_eq!(comment.name(), "t1_lsq1x0a");
+    assert_eq!(comment.id(), "lsq1x0a");
+    assert_eq!(comment.link_id(), "t3_1g7e1g6");
+    assert!(comment.is_top_level());
+    assert_eq!(comment.edited().map(|e| e.timestamp()), Some(1729328402));
+    assert_eq!(*comment.controversiality(), 1);
+    assert_eq!(comment.author_flair_text().as_deref(), Some("Kraków"));
+    assert_eq!(comment.status(), ContentStatus::Visible);
+
+    let RawContainer::Listing(replies) = comment.replies().clone().unwrap() else {
+        panic!("Replies are not a listing");
+    };
+    let RawContainer::Comment(reply) = &replies.children[0] else {
+        panic!("Reply is not a comment");
+    };
+    // Replies point at their parent comment, so a flattened list can be re-threaded
+    assert_eq!(reply.parent_id(), comment.name());
+    assert!(!reply.is_top_level());
+}
+
+#[test]
+fn test_deleted_and_removed_comments() {
+    let comments = comments(include_str!("fixtures/post_comments.json"));
+
+    let RawContainer::Listing(replies) = comments[0].replies().clone().unwrap() else {
+        panic!("Replies are not a listing");
+    };
+    let RawContainer::Comment(deleted) = &replies.children[0] else {
+        panic!("Reply is not a comment");
+    };
+    assert_eq!(deleted.status(), ContentStatus::Deleted);
+
+    let removed = &comments[1];
+    assert_eq!(removed.status(), ContentStatus::Removed);
+
+    let moderator = &comments[2];
+    assert_eq!(moderator.status(), ContentStatus::Visible);
+    assert_eq!(moderator.distinguished().as_deref(), Some("moderator"));
+    assert!(*moderator.stickied());
+    // Empty flair is no flair
+    assert_eq!(*moderator.author_flair_text(), None);
+}
+
+#[test]
+fn test_parse_messages() {
+    let messages = children(include_str!("fixtures/inbox.json"));
+    assert_eq!(messages.len(), 2);
+
+    let RawContainer::Message(message) = &messages[0] else {
+        panic!("Expected a message");
+    };
+    assert_eq!(message.name(), "t4_2bx3d1");
+    assert_eq!(message.author().as_deref(), Some("pierogi_enjoyer"));
+    assert_eq!(message.subject(), "Piekarnia");
+    assert!(!*message.was_comment());
+    assert_eq!(*message.subreddit(), None);
+
+    let RawContainer::Message(notice) = &messages[1] else {
+        panic!("Expected a message");
+    };
+    // Messages sent as a subreddit have no author
+    assert_eq!(*notice.author(), None);
+    assert_eq!(notice.subreddit().as_deref(), Some("Polska"));
+    assert_eq!(notice.distinguished().as_deref(), Some("moderator"));
+    assert_eq!(*notice.parent_id(), None);
+}
+
+#[test]
+fn test_parse_award() {
+    let award: RawContainer = serde_json::from_value(json!({
+        "kind": "t6",
+        "data": {
+            "id": "gid_1",
+            "name": "Silver",
+            "description": "Shows the Silver Award... and that's it.",
+            "coin_price": 100,
+            "icon_url": "https://www.redditstatic.com/gold/awards/icon/silver_512.png",
+            "award_type": "global",
+            "count": 2,
+            "days_of_premium": null,
+            "is_enabled": true
+        }
+    }))
+    .unwrap();
+
+    let RawContainer::Award(award) = award else {
+        panic!("Expected an award");
+    };
+    assert_eq!(award.name(), "Silver");
+    assert_eq!(*award.coin_price(), 100);
+    assert_eq!(*award.count(), 2);
+}
+
+#[test]
+fn test_unknown_kinds_are_skipped() {
+    let skipped_before = metrics::REDDIT_SKIPPED_ITEMS.get();
+    let listing: RawContainer = serde_json::from_value(json!({
+        "kind": "Listing",
+        "data": {
+            "after": null,
+            "dist": 2,
+            "before": null,
+            "children": [
+                { "kind": "t9", "data": { "id": "abc", "whatever": [1, 2, 3] } },
+                { "kind": "t6", "data": { "id": "gid_2", "name": "Gold" } }
+            ]
+        }
+    }))
+    .unwrap();
+
+    let RawContainer::Listing(listing) = listing else {
+        panic!("Expected a listing");
+    };
+    assert_eq!(listing.children.len(), 1);
+    assert!(matches!(listing.children[0], RawContainer::Award(_)));
+    // Tests run in parallel, other tests could skip items too
+    assert!(metrics::REDDIT_SKIPPED_ITEMS.get() > skipped_before);
+
+    // Outside of a listing, the object is kept and serialized back unchanged
+    let raw = json!({ "kind": "t9", "data": { "id": "abc", "whatever": [1, 2, 3] } });
+    let unknown: RawContainer = serde_json::from_value(raw.clone()).unwrap();
+    assert!(matches!(&unknown, RawContainer::Unknown(kept) if **kept == raw));
+    assert_eq!(serde_json::to_value(&unknown).unwrap(), raw);
+}
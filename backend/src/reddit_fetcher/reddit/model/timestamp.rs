@@ -0,0 +1,92 @@
+//! Serde adapter for Reddit's timestamps: UNIX time in seconds, as a float, eg. `1729327268.0`.
+//!
+//! Use with `#[serde(with = "timestamp")]`, or `#[serde(with = "timestamp::option")]` for optional ones.
+//! Serializes back to float seconds, so the API returns the timestamps the way Reddit does.
+
+use chrono::{DateTime, Utc};
+use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+/// Convert float UNIX seconds to a [DateTime], keeping the fractional part.
+pub fn from_seconds(seconds: f64) -> Option<DateTime<Utc>> {
+    let whole = seconds.floor();
+    let nanos = ((seconds - whole) * 1e9).round() as u32;
+    DateTime::from_timestamp(whole as i64, nanos.min(999_999_999))
+}
+
+/// Convert a [DateTime] to float UNIX seconds.
+pub fn to_seconds(timestamp: &DateTime<Utc>) -> f64 {
+    timestamp.timestamp() as f64 + timestamp.timestamp_subsec_nanos() as f64 / 1e9
+}
+
+pub fn serialize<S: Serializer>(
+    timestamp: &DateTime<Utc>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(to_seconds(timestamp))
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<DateTime<Utc>, D::Error> {
+    let seconds = f64::deserialize(deserializer)?;
+    from_seconds(seconds).ok_or_else(|| D::Error::custom(format!("Invalid timestamp: {seconds}")))
+}
+
+/// The same adapter for optional timestamps, `null` is `None`.
+pub mod option {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        timestamp: &Option<DateTime<Utc>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match timestamp {
+            Some(timestamp) => super::serialize(timestamp, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<DateTime<Utc>>, D::Error> {
+        match Option::<f64>::deserialize(deserializer)? {
+            Some(seconds) => from_seconds(seconds)
+                .map(Some)
+                .ok_or_else(|| D::Error::custom(format!("Invalid timestamp: {seconds}"))),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Serialize;
+    use serde_json::json;
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct Item {
+        #[serde(with = "super")]
+        created_utc: DateTime<Utc>,
+        #[serde(default, with = "super::option")]
+        edited: Option<DateTime<Utc>>,
+    }
+
+    #[test]
+    fn test_keeps_second_precision() {
+        // An f32 rounds this to 1729327232, half a minute off
+        let item: Item = serde_json::from_value(json!({ "created_utc": 1729327268.0 })).unwrap();
+        assert_eq!(item.created_utc.timestamp(), 1729327268);
+        assert_eq!(item.edited, None);
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let item: Item =
+            serde_json::from_value(json!({ "created_utc": 1729327268, "edited": 1729328402.5 }))
+                .unwrap();
+        assert_eq!(item.edited.unwrap().timestamp_subsec_millis(), 500);
+        assert_eq!(
+            serde_json::to_value(&item).unwrap(),
+            json!({ "created_utc": 1729327268.0, "edited": 1729328402.5 })
+        );
+    }
+}
@@ -1,11 +1,16 @@
 #![allow(dead_code)]
 
-use params::FeedSorting;
+use params::{FeedSorting, FeedSortingTime, SearchSorting, SearchType};
 
+use super::cache::CacheKind;
 use super::model::MoreComments;
 pub mod params;
+#[cfg(test)]
 mod tests;
 
+/// Joins subreddit names into one combined listing, eg. `Polska+poland`.
+pub const SUBREDDIT_SEPARATOR: &str = "+";
+
 /// Represents a request to the Reddit API.
 ///
 /// Used to fetch posts, comments, and user/subreddit information.
@@ -13,27 +18,38 @@ mod tests;
 ///
 /// # Example
 ///
-/// ```
+/// ```ignore
 /// use params::{FeedSorting, FeedSortingTime};
 ///
 /// let req = SubredditPosts {
 ///     subreddit: "Polska".to_string(),
 ///     sorting: FeedSorting::New
 /// };
-
+///
 /// let (url, query) = req.into_http_request_parts();
-
-/// assert_eq!(url, "https://oauth.reddit.com/r/Polska/new.json");
+///
+/// assert_eq!(url, "/r/Polska/new.json");
 /// assert_eq!(query, vec![("limit", "100".to_string()), ("sort", "new".to_string())]);
 /// ```
 #[derive(Debug)]
 pub struct SubredditPostsRequest {
     /// The subreddit name.
+    ///
+    /// Several subreddits joined with `+`, eg. `Polska+poland`, are fetched as one combined listing,
+    /// see [SubredditPostsRequest::combined].
     pub subreddit: String,
     pub sorting: FeedSorting,
     pub after: Option<String>,
 }
 
+/// Fetch the latest comments posted anywhere in a subreddit, newest first.
+#[derive(Debug)]
+pub struct SubredditCommentsRequest {
+    /// The subreddit name.
+    pub subreddit: String,
+    pub after: Option<String>,
+}
+
 /// Fetch information about a subreddit.
 #[derive(Debug)]
 pub struct SubredditAboutRequest {
@@ -49,6 +65,34 @@ pub struct UserPostsRequest {
     pub after: Option<String>,
 }
 
+/// Fetch only the comments from a user's profile.
+#[derive(Debug)]
+pub struct UserCommentsRequest {
+    /// The user's username.
+    pub username: String,
+    pub sorting: FeedSorting,
+    pub after: Option<String>,
+}
+
+/// Fetch only the posts submitted by a user.
+#[derive(Debug)]
+pub struct UserSubmittedRequest {
+    /// The user's username.
+    pub username: String,
+    pub sorting: FeedSorting,
+    pub after: Option<String>,
+}
+
+/// Any of the user profile feeds, picked by the
+/// [UserContentFilter](crate::reddit_fetcher::feed_request::UserContentFilter) of a feed request.
+#[derive(Debug)]
+pub enum UserFeedRequest {
+    /// Posts and comments mixed together
+    Overview(UserPostsRequest),
+    Comments(UserCommentsRequest),
+    Submitted(UserSubmittedRequest),
+}
+
 /// Fetch information about a user.
 #[derive(Debug)]
 pub struct UserAboutRequest {
@@ -67,21 +111,102 @@ pub struct PostCommentsRequest {
     pub after: Option<String>,
 }
 
-/// The parts of an HTTP request: URL and query parameters.
+/// Search Reddit, site-wide or within a subreddit.
+#[derive(Debug)]
+pub struct SearchRequest {
+    /// The search query, eg. `title:wybory OR sejm`
+    pub query: String,
+    /// Search within this subreddit, `None` to search all of Reddit.
+    pub subreddit: Option<String>,
+    /// Only return results from `subreddit`. Without it, the subreddit only boosts its results.
+    pub restrict_sr: bool,
+    pub sorting: SearchSorting,
+    /// Only return results from this time period
+    pub time: Option<FeedSortingTime>,
+    /// Kinds of results to return, Reddit returns posts if empty.
+    pub types: Vec<SearchType>,
+    pub after: Option<String>,
+}
+
+/// Maximum number of items Reddit returns from a single `/api/info` call.
+pub const INFO_CHUNK_SIZE: usize = 100;
+
+/// Fetch a post together with its comment tree.
+#[derive(Debug)]
+pub struct PostDetailRequest {
+    /// The subreddit name.
+    pub subreddit: String,
+    /// The post's ID, eg. 1eubxgg
+    pub post_id: String,
+    pub sorting: FeedSorting,
+    /// Maximum depth of the returned comment tree
+    pub depth: Option<u32>,
+    /// Maximum number of comments to return
+    pub limit: Option<u32>,
+    /// ID of a comment to focus on, only that comment's thread is returned, eg. e3fw1k
+    pub comment: Option<String>,
+}
+
+/// Look up posts and comments by their fullnames, eg. t3_8z1v1z or t1_e3fw1k.
+///
+/// A single request takes up to [INFO_CHUNK_SIZE] fullnames, use [InfoRequest::chunked] for more.
+#[derive(Debug)]
+pub struct InfoRequest {
+    pub fullnames: Vec<String>,
+}
+
+impl InfoRequest {
+    /// Split the fullnames into as many requests as needed.
+    pub fn chunked(fullnames: &[String]) -> Vec<InfoRequest> {
+        fullnames
+            .chunks(INFO_CHUNK_SIZE)
+            .map(|chunk| InfoRequest {
+                fullnames: chunk.to_vec(),
+            })
+            .collect()
+    }
+}
+
+/// The parts of an HTTP request: path and query parameters.
+///
+/// The path is relative to the API base URL of the connection, see
+/// [RedditUrls](crate::reddit_fetcher::reddit::connection::RedditUrls).
 pub type RequestParts = (String, Vec<(&'static str, String)>);
 
 pub trait RedditRequest {
     fn to_request_parts(&self) -> RequestParts;
     fn resource_name(&self) -> String;
+    /// How long the response can be cached for.
+    fn cache_kind(&self) -> CacheKind {
+        CacheKind::Listing
+    }
+}
+
+/// Feeds sorted by new change the fastest.
+fn feed_cache_kind(sorting: &FeedSorting) -> CacheKind {
+    match sorting {
+        FeedSorting::New => CacheKind::NewFeed,
+        _ => CacheKind::Listing,
+    }
+}
+
+impl SubredditPostsRequest {
+    /// Fetch the posts of several subreddits as a single listing, eg. `r/Polska+poland`.
+    ///
+    /// Reddit merges and sorts the posts itself, so the whole set is paginated
+    /// with a single `after` cursor.
+    pub fn combined(subreddits: &[String], sorting: FeedSorting) -> Self {
+        Self {
+            subreddit: subreddits.join(SUBREDDIT_SEPARATOR),
+            sorting,
+            after: None,
+        }
+    }
 }
 
 impl RedditRequest for SubredditPostsRequest {
     fn to_request_parts(&self) -> RequestParts {
-        let url = format!(
-            "https://oauth.reddit.com/r/{}/{}.json",
-            self.subreddit,
-            self.sorting.to_string()
-        );
+        let url = format!("/r/{}/{}.json", self.subreddit, self.sorting);
 
         let mut query = vec![];
         if let Some(time) = self.sorting.time() {
@@ -98,21 +223,140 @@ impl RedditRequest for SubredditPostsRequest {
     fn resource_name(&self) -> String {
         format!("r/{}", self.subreddit)
     }
+    fn cache_kind(&self) -> CacheKind {
+        feed_cache_kind(&self.sorting)
+    }
+}
+
+impl RedditRequest for SubredditCommentsRequest {
+    fn to_request_parts(&self) -> RequestParts {
+        let url = format!("/r/{}/comments.json", self.subreddit);
+
+        let mut query = vec![("limit", "100".to_string())];
+        if let Some(after) = &self.after {
+            query.push(("after", after.to_string()));
+        }
+
+        (url, query)
+    }
+    fn resource_name(&self) -> String {
+        format!("r/{}/comments", self.subreddit)
+    }
+    fn cache_kind(&self) -> CacheKind {
+        CacheKind::NewFeed
+    }
 }
 
 impl RedditRequest for SubredditAboutRequest {
     fn to_request_parts(&self) -> RequestParts {
-        let url = format!("https://oauth.reddit.com/r/{}/about.json", self.subreddit);
+        let url = format!("/r/{}/about.json", self.subreddit);
         (url, vec![])
     }
     fn resource_name(&self) -> String {
         format!("r/{}", self.subreddit)
     }
+    fn cache_kind(&self) -> CacheKind {
+        CacheKind::About
+    }
+}
+
+/// Query of the user profile listings, they all take the same parameters.
+fn user_listing_query(
+    sorting: &FeedSorting,
+    after: &Option<String>,
+) -> Vec<(&'static str, String)> {
+    let mut query = vec![("sort", sorting.to_string())];
+    if let Some(time) = sorting.time() {
+        query.push(("t", time.to_string()));
+    }
+    query.push(("limit", "100".to_string()));
+
+    if let Some(after) = after {
+        query.push(("after", after.to_string()));
+    }
+
+    query
 }
 
 impl RedditRequest for UserPostsRequest {
     fn to_request_parts(&self) -> RequestParts {
-        let url = format!("https://oauth.reddit.com/user/{}.json", self.username);
+        let url = format!("/user/{}.json", self.username);
+        (url, user_listing_query(&self.sorting, &self.after))
+    }
+    fn resource_name(&self) -> String {
+        format!("u/{}", self.username)
+    }
+    fn cache_kind(&self) -> CacheKind {
+        feed_cache_kind(&self.sorting)
+    }
+}
+
+impl RedditRequest for UserCommentsRequest {
+    fn to_request_parts(&self) -> RequestParts {
+        let url = format!("/user/{}/comments.json", self.username);
+        (url, user_listing_query(&self.sorting, &self.after))
+    }
+    fn resource_name(&self) -> String {
+        format!("u/{}/comments", self.username)
+    }
+    fn cache_kind(&self) -> CacheKind {
+        feed_cache_kind(&self.sorting)
+    }
+}
+
+impl RedditRequest for UserSubmittedRequest {
+    fn to_request_parts(&self) -> RequestParts {
+        let url = format!("/user/{}/submitted.json", self.username);
+        (url, user_listing_query(&self.sorting, &self.after))
+    }
+    fn resource_name(&self) -> String {
+        format!("u/{}/submitted", self.username)
+    }
+    fn cache_kind(&self) -> CacheKind {
+        feed_cache_kind(&self.sorting)
+    }
+}
+
+impl RedditRequest for UserFeedRequest {
+    fn to_request_parts(&self) -> RequestParts {
+        match self {
+            UserFeedRequest::Overview(req) => req.to_request_parts(),
+            UserFeedRequest::Comments(req) => req.to_request_parts(),
+            UserFeedRequest::Submitted(req) => req.to_request_parts(),
+        }
+    }
+    fn resource_name(&self) -> String {
+        match self {
+            UserFeedRequest::Overview(req) => req.resource_name(),
+            UserFeedRequest::Comments(req) => req.resource_name(),
+            UserFeedRequest::Submitted(req) => req.resource_name(),
+        }
+    }
+    fn cache_kind(&self) -> CacheKind {
+        match self {
+            UserFeedRequest::Overview(req) => req.cache_kind(),
+            UserFeedRequest::Comments(req) => req.cache_kind(),
+            UserFeedRequest::Submitted(req) => req.cache_kind(),
+        }
+    }
+}
+
+impl RedditRequest for UserAboutRequest {
+    fn to_request_parts(&self) -> RequestParts {
+        let url = format!("/user/{}/about.json", self.username);
+        (url, vec![])
+    }
+    fn resource_name(&self) -> String {
+        format!("u/{}", self.username)
+    }
+    fn cache_kind(&self) -> CacheKind {
+        CacheKind::About
+    }
+}
+
+impl RedditRequest for PostCommentsRequest {
+    fn to_request_parts(&self) -> RequestParts {
+        let url = format!("/r/{}/comments/{}.json", self.subreddit, self.post_id);
 
         let mut query = vec![("sort", self.sorting.to_string())];
         if let Some(time) = self.sorting.time() {
@@ -127,35 +371,67 @@ impl RedditRequest for UserPostsRequest {
         (url, query)
     }
     fn resource_name(&self) -> String {
-        format!("u/{}", self.username)
+        format!("r/{}/comments/{}", self.subreddit, self.post_id)
     }
 }
 
-impl RedditRequest for UserAboutRequest {
+impl RedditRequest for SearchRequest {
     fn to_request_parts(&self) -> RequestParts {
-        let url = format!("https://oauth.reddit.com/user/{}/about.json", self.username);
-        (url, vec![])
+        let url = match &self.subreddit {
+            Some(subreddit) => format!("/r/{}/search.json", subreddit),
+            None => "/search.json".to_string(),
+        };
+
+        let mut query = vec![
+            ("q", self.query.clone()),
+            ("sort", self.sorting.to_string()),
+        ];
+        if let Some(time) = self.time {
+            query.push(("t", time.to_string()));
+        }
+        if self.subreddit.is_some() && self.restrict_sr {
+            query.push(("restrict_sr", "true".to_string()));
+        }
+        if !self.types.is_empty() {
+            let types = self.types.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+            query.push(("type", types.join(",")));
+        }
+        query.push(("limit", "100".to_string()));
+
+        if let Some(after) = &self.after {
+            query.push(("after", after.to_string()));
+        }
+
+        (url, query)
     }
     fn resource_name(&self) -> String {
-        format!("u/{}", self.username)
+        match &self.subreddit {
+            Some(subreddit) => format!("r/{}/search?q={}", subreddit, self.query),
+            None => format!("search?q={}", self.query),
+        }
+    }
+    fn cache_kind(&self) -> CacheKind {
+        match self.sorting {
+            SearchSorting::New => CacheKind::NewFeed,
+            _ => CacheKind::Listing,
+        }
     }
 }
 
-impl RedditRequest for PostCommentsRequest {
+impl RedditRequest for PostDetailRequest {
     fn to_request_parts(&self) -> RequestParts {
-        let url = format!(
-            "https://oauth.reddit.com/r/{}/comments/{}.json",
-            self.subreddit, self.post_id
-        );
+        let url = format!("/r/{}/comments/{}.json", self.subreddit, self.post_id);
 
         let mut query = vec![("sort", self.sorting.to_string())];
         if let Some(time) = self.sorting.time() {
             query.push(("t", time.to_string()));
         }
-        query.push(("limit", "100".to_string()));
-
-        if let Some(after) = &self.after {
-            query.push(("after", after.to_string()));
+        query.push(("limit", self.limit.unwrap_or(100).to_string()));
+        if let Some(depth) = self.depth {
+            query.push(("depth", depth.to_string()));
+        }
+        if let Some(comment) = &self.comment {
+            query.push(("comment", comment.to_string()));
         }
 
         (url, query)
@@ -165,9 +441,19 @@ impl RedditRequest for PostCommentsRequest {
     }
 }
 
+impl RedditRequest for InfoRequest {
+    fn to_request_parts(&self) -> RequestParts {
+        let url = "/api/info.json".to_string();
+        (url, vec![("id", self.fullnames.join(","))])
+    }
+    fn resource_name(&self) -> String {
+        format!("info/{}", self.fullnames.join(","))
+    }
+}
+
 impl MoreComments {
     pub fn into_request_parts(self) -> Vec<RequestParts> {
-        let url = "https://oauth.reddit.com/api/morechildren".to_string();
+        let url = "/api/morechildren".to_string();
 
         self.children
             .chunks(100)
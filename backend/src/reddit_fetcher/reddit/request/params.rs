@@ -67,3 +67,56 @@ impl FeedSorting {
         }
     }
 }
+
+/// Represents a sorting method for search results.
+///
+/// Unlike in [FeedSorting], every search sorting can be limited to a time period.
+/// * Used in [SearchRequest](super::SearchRequest)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum SearchSorting {
+    #[default]
+    Relevance,
+    Hot,
+    Top,
+    New,
+    Comments,
+}
+
+impl Display for SearchSorting {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            SearchSorting::Relevance => "relevance",
+            SearchSorting::Hot => "hot",
+            SearchSorting::Top => "top",
+            SearchSorting::New => "new",
+            SearchSorting::Comments => "comments",
+        }
+        .to_string();
+        write!(f, "{}", str)
+    }
+}
+
+/// Represents a kind of search result.
+///
+/// * Used in [SearchRequest](super::SearchRequest)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SearchType {
+    /// Posts
+    Link,
+    /// Subreddits
+    Subreddit,
+    /// Users
+    User,
+}
+
+impl Display for SearchType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            SearchType::Link => "link",
+            SearchType::Subreddit => "sr",
+            SearchType::User => "user",
+        }
+        .to_string();
+        write!(f, "{}", str)
+    }
+}
@@ -1,105 +1,260 @@
-#[cfg(test)]
-mod tests {
-    use crate::reddit_fetcher::reddit::request::{
-        params::{FeedSorting, FeedSortingTime},
-        PostCommentsRequest, RedditRequest, SubredditAboutRequest, SubredditPostsRequest,
-        UserAboutRequest, UserPostsRequest,
+use crate::reddit_fetcher::reddit::request::{
+    params::{FeedSorting, FeedSortingTime, SearchSorting, SearchType},
+    InfoRequest, PostCommentsRequest, PostDetailRequest, RedditRequest, SearchRequest,
+    SubredditAboutRequest, SubredditCommentsRequest, SubredditPostsRequest, UserAboutRequest,
+    UserCommentsRequest, UserFeedRequest, UserPostsRequest, UserSubmittedRequest,
+};
+
+fn init() {
+    let _ = env_logger::builder().is_test(true).try_init();
+}
+
+#[test]
+fn test_create_url_subreddit_posts() {
+    let req = SubredditPostsRequest {
+        subreddit: "Polska".to_string(),
+        sorting: FeedSorting::New,
+        after: None,
+    };
+    let (url, query) = req.to_request_parts();
+    assert_eq!(url, "/r/Polska/new.json");
+    assert_eq!(query, vec![("limit", "100".to_string())]);
+}
+
+#[test]
+fn test_create_url_combined_subreddit_posts() {
+    let subreddits = [
+        "Polska".to_string(),
+        "poland".to_string(),
+        "krakow".to_string(),
+    ];
+    let req = SubredditPostsRequest::combined(&subreddits, FeedSorting::Top(FeedSortingTime::Week));
+    let (url, query) = req.to_request_parts();
+    assert_eq!(url, "/r/Polska+poland+krakow/top.json");
+    assert_eq!(
+        query,
+        vec![("t", "week".to_string()), ("limit", "100".to_string())]
+    );
+    assert_eq!(req.resource_name(), "r/Polska+poland+krakow");
+}
+
+#[test]
+fn test_create_url_subreddit_comments() {
+    let req = SubredditCommentsRequest {
+        subreddit: "Polska".to_string(),
+        after: Some("t1_abc".to_string()),
+    };
+    let (url, query) = req.to_request_parts();
+    assert_eq!(url, "/r/Polska/comments.json");
+    assert_eq!(
+        query,
+        vec![
+            ("limit", "100".to_string()),
+            ("after", "t1_abc".to_string())
+        ]
+    );
+}
+
+#[test]
+fn test_create_url_subreddit_info() {
+    let req = SubredditAboutRequest {
+        subreddit: "Polska".to_string(),
+    };
+    let (url, query) = req.to_request_parts();
+    assert_eq!(url, "/r/Polska/about.json");
+    assert_eq!(query, vec![]);
+}
+
+#[test]
+fn test_create_url_user_posts() {
+    let req = UserPostsRequest {
+        username: "spez".to_string(),
+        sorting: FeedSorting::Top(FeedSortingTime::All),
+        after: None,
+    };
+    let (url, query) = req.to_request_parts();
+    assert_eq!(url, "/user/spez.json");
+    assert_eq!(
+        query,
+        vec![
+            ("sort", "top".to_string()),
+            ("t", "all".to_string()),
+            ("limit", "100".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_create_url_user_comments_and_submitted() {
+    let comments = UserFeedRequest::Comments(UserCommentsRequest {
+        username: "spez".to_string(),
+        sorting: FeedSorting::New,
+        after: None,
+    });
+    let (url, query) = comments.to_request_parts();
+    assert_eq!(url, "/user/spez/comments.json");
+    assert_eq!(
+        query,
+        vec![("sort", "new".to_string()), ("limit", "100".to_string())]
+    );
+    assert_eq!(comments.resource_name(), "u/spez/comments");
+
+    let submitted = UserFeedRequest::Submitted(UserSubmittedRequest {
+        username: "spez".to_string(),
+        sorting: FeedSorting::Top(FeedSortingTime::Year),
+        after: Some("t3_abc".to_string()),
+    });
+    let (url, query) = submitted.to_request_parts();
+    assert_eq!(url, "/user/spez/submitted.json");
+    assert_eq!(
+        query,
+        vec![
+            ("sort", "top".to_string()),
+            ("t", "year".to_string()),
+            ("limit", "100".to_string()),
+            ("after", "t3_abc".to_string())
+        ]
+    );
+}
+
+#[test]
+fn test_create_url_user_info() {
+    let req = UserAboutRequest {
+        username: "spez".to_string(),
     };
+    let (url, query) = req.to_request_parts();
+    assert_eq!(url, "/user/spez/about.json");
+    assert_eq!(query, vec![]);
+}
+
+#[test]
+fn test_create_url_post_comments() {
+    let req = PostCommentsRequest {
+        subreddit: "Polska".to_string(),
+        post_id: "abc123".to_string(),
+        sorting: FeedSorting::Controversial(FeedSortingTime::Day),
+        after: None,
+    };
+    let (url, query) = req.to_request_parts();
+    assert_eq!(url, "/r/Polska/comments/abc123.json");
+    assert_eq!(
+        query,
+        vec![
+            ("sort", "controversial".to_string()),
+            ("t", "day".to_string()),
+            ("limit", "100".to_string())
+        ]
+    );
+}
+
+#[test]
+fn test_create_default_params() {
+    let req = PostCommentsRequest {
+        subreddit: "Polska".to_string(),
+        post_id: "abc123".to_string(),
+        sorting: FeedSorting::default(),
+        after: None,
+    };
+    let (url, query) = req.to_request_parts();
+    assert_eq!(url, "/r/Polska/comments/abc123.json");
+    assert_eq!(
+        query,
+        vec![("sort", "hot".to_string()), ("limit", "100".to_string())]
+    );
+}
+
+#[test]
+fn test_create_url_search_site_wide() {
+    let req = SearchRequest {
+        query: "rust".to_string(),
+        subreddit: None,
+        restrict_sr: true,
+        sorting: SearchSorting::default(),
+        time: None,
+        types: vec![],
+        after: None,
+    };
+    let (url, query) = req.to_request_parts();
+    assert_eq!(url, "/search.json");
+    assert_eq!(
+        query,
+        vec![
+            ("q", "rust".to_string()),
+            ("sort", "relevance".to_string()),
+            ("limit", "100".to_string())
+        ]
+    );
+}
 
-    fn init() {
-        let _ = env_logger::builder().is_test(true).try_init();
-    }
-
-    #[test]
-    fn test_create_url_subreddit_posts() {
-        let req = SubredditPostsRequest {
-            subreddit: "Polska".to_string(),
-            sorting: FeedSorting::New,
-            after: None,
-        };
-        let (url, query) = req.to_request_parts();
-        assert_eq!(url, "https://oauth.reddit.com/r/Polska/new.json");
-        assert_eq!(query, vec![("limit", "100".to_string())]);
-    }
-
-    #[test]
-    fn test_create_url_subreddit_info() {
-        let req = SubredditAboutRequest {
-            subreddit: "Polska".to_string(),
-        };
-        let (url, query) = req.to_request_parts();
-        assert_eq!(url, "https://oauth.reddit.com/r/Polska/about.json");
-        assert_eq!(query, vec![]);
-    }
-
-    #[test]
-    fn test_create_url_user_posts() {
-        let req = UserPostsRequest {
-            username: "spez".to_string(),
-            sorting: FeedSorting::Top(FeedSortingTime::All),
-            after: None,
-        };
-        let (url, query) = req.to_request_parts();
-        assert_eq!(url, "https://oauth.reddit.com/user/spez.json");
-        assert_eq!(
-            query,
-            vec![
-                ("sort", "top".to_string()),
-                ("t", "all".to_string()),
-                ("limit", "100".to_string()),
-            ]
-        );
-    }
-
-    #[test]
-    fn test_create_url_user_info() {
-        let req = UserAboutRequest {
-            username: "spez".to_string(),
-        };
-        let (url, query) = req.to_request_parts();
-        assert_eq!(url, "https://oauth.reddit.com/user/spez/about.json");
-        assert_eq!(query, vec![]);
-    }
-
-    #[test]
-    fn test_create_url_post_comments() {
-        let req = PostCommentsRequest {
-            subreddit: "Polska".to_string(),
-            post_id: "abc123".to_string(),
-            sorting: FeedSorting::Controversial(FeedSortingTime::Day),
-            after: None,
-        };
-        let (url, query) = req.to_request_parts();
-        assert_eq!(
-            url,
-            "https://oauth.reddit.com/r/Polska/comments/abc123.json"
-        );
-        assert_eq!(
-            query,
-            vec![
-                ("sort", "controversial".to_string()),
-                ("t", "day".to_string()),
-                ("limit", "100".to_string())
-            ]
-        );
-    }
-
-    #[test]
-    fn test_create_default_params() {
-        let req = PostCommentsRequest {
-            subreddit: "Polska".to_string(),
-            post_id: "abc123".to_string(),
-            sorting: FeedSorting::default(),
-            after: None,
-        };
-        let (url, query) = req.to_request_parts();
-        assert_eq!(
-            url,
-            "https://oauth.reddit.com/r/Polska/comments/abc123.json"
-        );
-        assert_eq!(
-            query,
-            vec![("sort", "hot".to_string()), ("limit", "100".to_string())]
-        );
-    }
+#[test]
+fn test_create_url_search_subreddit() {
+    let req = SearchRequest {
+        query: "wybory".to_string(),
+        subreddit: Some("Polska".to_string()),
+        restrict_sr: true,
+        sorting: SearchSorting::Top,
+        time: Some(FeedSortingTime::Week),
+        types: vec![SearchType::Link, SearchType::Subreddit],
+        after: Some("t3_abc".to_string()),
+    };
+    let (url, query) = req.to_request_parts();
+    assert_eq!(url, "/r/Polska/search.json");
+    assert_eq!(
+        query,
+        vec![
+            ("q", "wybory".to_string()),
+            ("sort", "top".to_string()),
+            ("t", "week".to_string()),
+            ("restrict_sr", "true".to_string()),
+            ("type", "link,sr".to_string()),
+            ("limit", "100".to_string()),
+            ("after", "t3_abc".to_string())
+        ]
+    );
+}
+
+#[test]
+fn test_create_url_info() {
+    let req = InfoRequest {
+        fullnames: vec!["t3_abc".to_string(), "t1_def".to_string()],
+    };
+    let (url, query) = req.to_request_parts();
+    assert_eq!(url, "/api/info.json");
+    assert_eq!(query, vec![("id", "t3_abc,t1_def".to_string())]);
+}
+
+#[test]
+fn test_info_request_chunks() {
+    let fullnames = (0..250).map(|i| format!("t3_{i}")).collect::<Vec<_>>();
+    let requests = InfoRequest::chunked(&fullnames);
+
+    assert_eq!(requests.len(), 3);
+    assert_eq!(requests[0].fullnames.len(), 100);
+    assert_eq!(requests[2].fullnames.len(), 50);
+    assert_eq!(requests[2].fullnames[0], "t3_200");
+    assert!(InfoRequest::chunked(&[]).is_empty());
+}
+
+#[test]
+fn test_create_url_post_detail() {
+    let req = PostDetailRequest {
+        subreddit: "Polska".to_string(),
+        post_id: "abc123".to_string(),
+        sorting: FeedSorting::Top(FeedSortingTime::All),
+        depth: Some(3),
+        limit: Some(50),
+        comment: Some("def456".to_string()),
+    };
+    let (url, query) = req.to_request_parts();
+    assert_eq!(url, "/r/Polska/comments/abc123.json");
+    assert_eq!(
+        query,
+        vec![
+            ("sort", "top".to_string()),
+            ("t", "all".to_string()),
+            ("limit", "50".to_string()),
+            ("depth", "3".to_string()),
+            ("comment", "def456".to_string())
+        ]
+    );
 }
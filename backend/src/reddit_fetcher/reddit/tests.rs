@@ -1,25 +1,341 @@
-#[cfg(test)]
-mod tests {
-    use lazy_static::lazy_static;
-    use reqwest::{Client, ClientBuilder};
+use crate::reddit_fetcher::feed_request::{
+    DataSource, FeedCursor, FetcherFeedRequest, RedditFeedKind, RequestSize,
+};
+use crate::reddit_fetcher::fetcher::RMoodsFetcher;
+use crate::reddit_fetcher::fetcher_error::FetcherError;
+use crate::reddit_fetcher::model::post_comments::PostComments;
+use crate::reddit_fetcher::model::posts::Posts;
+use crate::reddit_fetcher::model::subreddit_comments::SubredditComments;
+use crate::reddit_fetcher::model::subreddit_info::SubredditAbout;
+use crate::reddit_fetcher::reddit::cache::{CacheStats, CacheTtls, ResponseCache};
+use crate::reddit_fetcher::reddit::cassette::Cassette;
+use crate::reddit_fetcher::reddit::connection::{RateLimit, RedditConnection};
+use crate::reddit_fetcher::reddit::error::RedditError;
+use crate::reddit_fetcher::reddit::mock::{
+    fixtures, MockReddit, MockServer, MOCK_TOKEN, RATE_LIMIT_RESET,
+};
+use crate::reddit_fetcher::reddit::model::ContentStatus;
+use crate::reddit_fetcher::reddit::request::params::FeedSorting;
+use crate::reddit_fetcher::reddit::request::{SubredditAboutRequest, SubredditPostsRequest};
+use serde_json::{json, Value};
 
-    use crate::reddit_fetcher::reddit::connection::RedditConnection;
-
-    lazy_static! {
-        static ref HTTP: Client = ClientBuilder::new().user_agent("RMoods").build().unwrap();
+fn feed_request(kind: RedditFeedKind, post_id: Option<&str>, size: u16) -> FetcherFeedRequest {
+    FetcherFeedRequest {
+        resource_kind: kind,
+        report_types: vec![],
+        data_sources: vec![DataSource {
+            name: "Polska".to_string(),
+            post_id: post_id.map(|id| id.to_string()),
+            share: 1.0,
+        }],
+        size: RequestSize::Custom(size),
+        sorting: FeedSorting::New,
+        since: None,
+        search: None,
+        user_filter: Default::default(),
     }
-    static INIT: std::sync::Once = std::sync::Once::new();
+}
+
+async fn subreddit_posts_server() -> MockServer {
+    MockReddit::new()
+        .route("/r/Polska/new.json", fixtures::subreddit_posts())
+        .route("/r/Polska/new.json", fixtures::subreddit_posts_page2())
+        .start()
+        .await
+}
+
+#[tokio::test]
+async fn test_app_can_fetch_access_token() {
+    let server = MockReddit::new().start().await;
+    let conn = server.connection().await;
+    assert_eq!(conn.access_token.token(), MOCK_TOKEN);
+
+    let token = conn
+        .client
+        .fetch_access_token(&conn.http, &conn.urls.access_token)
+        .await
+        .unwrap();
+    assert_eq!(token.token(), MOCK_TOKEN);
+}
+
+#[tokio::test]
+async fn test_fetch_feed_follows_pagination() {
+    let server = subreddit_posts_server().await;
+    let mut fetcher = RMoodsFetcher::with_connection(server.connection().await);
+
+    let request = feed_request(RedditFeedKind::SubredditPosts, None, 5);
+    let (posts, requests_made) = fetcher.fetch_feed::<Posts>(request).await.unwrap();
+
+    // The second page has no `after`, so the budget is not used up
+    assert_eq!(requests_made, 2);
+    assert_eq!(posts.list.len(), 5);
+    assert_eq!(posts.list[3].name(), "t3_1g7dw8m");
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2);
+    assert!(requests[1].contains("after=t3_1g7dz2k"));
+}
+
+#[tokio::test]
+async fn test_fetch_feed_stops_at_budget() {
+    let server = subreddit_posts_server().await;
+    let mut fetcher = RMoodsFetcher::with_connection(server.connection().await);
+
+    let request = feed_request(RedditFeedKind::SubredditPosts, None, 1);
+    let (posts, requests_made) = fetcher.fetch_feed::<Posts>(request).await.unwrap();
 
-    fn init() {
-        INIT.call_once(|| {
-            let _ = dotenvy::dotenv();
+    assert_eq!(requests_made, 1);
+    assert_eq!(posts.list.len(), 3);
+}
+
+#[tokio::test]
+async fn test_fetch_feed_stops_at_cursor() {
+    let server = subreddit_posts_server().await;
+    let mut fetcher = RMoodsFetcher::with_connection(server.connection().await);
+
+    let mut request = feed_request(RedditFeedKind::SubredditPosts, None, 5);
+    request.since = Some(FeedCursor {
+        fullname: Some("t3_1g7dz2k".to_string()),
+        created_utc: None,
+    });
+    let (posts, requests_made) = fetcher.fetch_feed::<Posts>(request).await.unwrap();
+
+    assert_eq!(requests_made, 1);
+    assert_eq!(posts.list.len(), 2);
+}
+
+fn comments_page(ids: &[&str], after: Option<&str>) -> Value {
+    let children = ids
+        .iter()
+        .map(|id| {
+            json!({
+                "kind": "t1",
+                "data": {
+                    "subreddit_id": "t5_2qh3s",
+                    "subreddit": "Polska",
+                    "replies": "",
+                    "author": "spez",
+                    "body": "Lorem ipsum",
+                    "permalink": format!("/r/Polska/comments/1g7dw8m/lorem/{id}/"),
+                    "created_utc": 1729319100.0,
+                    "score": 1,
+                    "id": id,
+                    "name": format!("t1_{id}"),
+                    "parent_id": "t3_1g7dw8m",
+                    "link_id": "t3_1g7dw8m"
+                }
+            })
         })
-    }
+        .collect::<Vec<_>>();
+    json!({
+        "kind": "Listing",
+        "data": { "after": after, "dist": ids.len(), "before": null, "children": children }
+    })
+}
 
-    #[tokio::test]
-    async fn test_app_can_fetch_access_token() {
-        init();
-        let conn = RedditConnection::new(HTTP.clone()).await.unwrap();
-        let _ = conn.client.fetch_access_token(&conn.http).await;
-    }
+#[tokio::test]
+async fn test_fetch_subreddit_comments_stops_at_cursor() {
+    let server = MockReddit::new()
+        .route(
+            "/r/Polska/comments.json",
+            comments_page(&["lsp9m3c", "lsp9k1a"], Some("t1_lsp9k1a")),
+        )
+        .route(
+            "/r/Polska/comments.json",
+            comments_page(&["lsp90aa", "lsp8x2f"], None),
+        )
+        .start()
+        .await;
+    let mut fetcher = RMoodsFetcher::with_connection(server.connection().await);
+
+    // The comments listing is chronological whatever the requested sorting
+    let mut request = feed_request(RedditFeedKind::SubredditComments, None, 5);
+    request.sorting = FeedSorting::Hot;
+    request.since = Some(FeedCursor {
+        fullname: Some("t1_lsp9k1a".to_string()),
+        created_utc: None,
+    });
+    let (comments, requests_made) = fetcher
+        .fetch_feed::<SubredditComments>(request)
+        .await
+        .unwrap();
+
+    assert_eq!(requests_made, 1);
+    assert_eq!(comments.list.len(), 1);
+    assert_eq!(comments.list[0].name(), "t1_lsp9m3c");
+}
+
+#[tokio::test]
+async fn test_fetch_more_comments() {
+    let server = MockReddit::new()
+        .route("/r/Polska/comments/1g7dw8m.json", fixtures::post_comments())
+        .route("/api/morechildren", fixtures::morechildren())
+        .start()
+        .await;
+    let mut fetcher = RMoodsFetcher::with_connection(server.connection().await);
+
+    let request = feed_request(RedditFeedKind::PostComments, Some("1g7dw8m"), 5);
+    let (comments, requests_made) = fetcher.fetch_feed::<PostComments>(request).await.unwrap();
+    assert_eq!(comments.list.len(), 2);
+    assert_eq!(comments.more.len(), 1);
+
+    let more = fetcher
+        .fetch_more_comments(&comments.more, 5 - requests_made)
+        .await
+        .unwrap();
+    assert_eq!(more.len(), 2);
+    assert_eq!(more[0].name(), "t1_lsp9k1a");
+    assert_eq!(more[1].status(), ContentStatus::Removed);
+
+    let requests = server.requests();
+    assert!(requests[1].starts_with("/api/morechildren?link_id=t3_1g7dw8m"));
+    assert!(requests[1].contains("children=lsp9k1a%2Clsp9m3c"));
+}
+
+#[tokio::test]
+async fn test_missing_resource_is_not_found() {
+    let server = MockReddit::new().start().await;
+    let mut fetcher = RMoodsFetcher::with_connection(server.connection().await);
+
+    let request = SubredditAboutRequest {
+        subreddit: "doesnotexist".to_string(),
+    };
+    let result = fetcher.fetch_about::<SubredditAbout>(request).await;
+
+    assert!(matches!(
+        result,
+        Err(FetcherError::RedditApiError(RedditError::ResourceNotFound(name))) if name == "r/doesnotexist"
+    ));
+}
+
+#[tokio::test]
+async fn test_rate_limit_headers() {
+    let server = subreddit_posts_server().await;
+    let mut conn = server.connection().await;
+    assert_eq!(conn.rate_limit(), None);
+
+    let request = || SubredditPostsRequest {
+        subreddit: "Polska".to_string(),
+        sorting: FeedSorting::New,
+        after: None,
+    };
+    conn.fetch_raw(request()).await.unwrap();
+    conn.fetch_raw(request()).await.unwrap();
+
+    assert_eq!(
+        conn.rate_limit(),
+        Some(RateLimit {
+            remaining: 998.0,
+            reset: RATE_LIMIT_RESET,
+            used: 2,
+        })
+    );
+}
+
+#[tokio::test]
+async fn test_recorded_session_replays_offline() {
+    let file = std::env::temp_dir()
+        .join(format!("rmoods-cassette-{}", std::process::id()))
+        .join("session.ndjson");
+
+    let server = subreddit_posts_server().await;
+    let connection = server
+        .connection()
+        .await
+        .with_cassette(Cassette::record(&file));
+    let mut fetcher = RMoodsFetcher::with_connection(connection);
+    let request = || feed_request(RedditFeedKind::SubredditPosts, None, 5);
+    let (recorded, _) = fetcher.fetch_feed::<Posts>(request()).await.unwrap();
+    let about = SubredditAboutRequest {
+        subreddit: "doesnotexist".to_string(),
+    };
+    assert!(fetcher.fetch_about::<SubredditAbout>(about).await.is_err());
+
+    let connection =
+        RedditConnection::replay(reqwest::Client::new(), Cassette::replay(&file).unwrap());
+    let mut fetcher = RMoodsFetcher::with_connection(connection);
+    let (replayed, requests_made) = fetcher.fetch_feed::<Posts>(request()).await.unwrap();
+
+    assert_eq!(requests_made, 2);
+    let names = |posts: &Posts| {
+        posts
+            .list
+            .iter()
+            .map(|p| p.name().clone())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(names(&replayed), names(&recorded));
+    assert_eq!(server.requests().len(), 3);
+
+    let about = SubredditAboutRequest {
+        subreddit: "doesnotexist".to_string(),
+    };
+    assert!(matches!(
+        fetcher.fetch_about::<SubredditAbout>(about).await,
+        Err(FetcherError::RedditApiError(RedditError::ResourceNotFound(
+            _
+        )))
+    ));
+    let mut unrecorded = feed_request(RedditFeedKind::SubredditPosts, None, 1);
+    unrecorded.sorting = FeedSorting::Hot;
+    assert!(matches!(
+        fetcher.fetch_feed::<Posts>(unrecorded).await,
+        Err(FetcherError::RedditApiError(RedditError::CassetteError(_)))
+    ));
+}
+
+#[tokio::test]
+async fn test_cached_responses_are_not_refetched() {
+    let server = subreddit_posts_server().await;
+    let cache = ResponseCache::new(10.try_into().unwrap(), CacheTtls::default());
+    let mut fetcher = RMoodsFetcher::with_connection(server.connection().await).with_cache(cache);
+    let request = || feed_request(RedditFeedKind::SubredditPosts, None, 5);
+
+    let (first, _) = fetcher.fetch_feed::<Posts>(request()).await.unwrap();
+    let (cached, _) = fetcher.fetch_feed::<Posts>(request()).await.unwrap();
+    assert_eq!(cached.list.len(), first.list.len());
+    assert_eq!(server.requests().len(), 2);
+
+    fetcher.bypass_cache(true);
+    fetcher.fetch_feed::<Posts>(request()).await.unwrap();
+    assert_eq!(server.requests().len(), 4);
+
+    assert_eq!(
+        fetcher.cache_stats(),
+        Some(CacheStats {
+            hits: 2,
+            misses: 2,
+            bypassed: 2,
+        })
+    );
+}
+
+#[tokio::test]
+async fn test_only_responses_from_reddit_have_fetch_time() {
+    let file = std::env::temp_dir()
+        .join(format!("rmoods-cassette-{}", std::process::id()))
+        .join("fetched_at.ndjson");
+    let server = subreddit_posts_server().await;
+    let cache = ResponseCache::new(10.try_into().unwrap(), CacheTtls::default());
+    let mut connection = server
+        .connection()
+        .await
+        .with_cache(cache)
+        .with_cassette(Cassette::record(&file));
+    let request = || SubredditPostsRequest {
+        subreddit: "Polska".to_string(),
+        sorting: FeedSorting::New,
+        after: None,
+    };
+    assert_eq!(connection.fetched_at(), None);
+
+    connection.fetch_raw(request()).await.unwrap();
+    assert!(connection.fetched_at().is_some());
+    connection.fetch_raw(request()).await.unwrap();
+    assert_eq!(connection.fetched_at(), None, "Served from the cache");
+
+    let mut replay =
+        RedditConnection::replay(reqwest::Client::new(), Cassette::replay(&file).unwrap());
+    replay.fetch_raw(request()).await.unwrap();
+    assert_eq!(replay.fetched_at(), None, "Replayed from the cassette");
 }
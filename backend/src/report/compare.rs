@@ -0,0 +1,349 @@
+use crate::reddit_fetcher::feed_request::RMoodsReportType;
+use crate::report::summary::{ReportSummary, DETECTION_THRESHOLD, NEGATIVE_SENTIMENT};
+use crate::report::{AnalyzedItem, Report};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// How many times the items are resampled to estimate a confidence interval.
+pub const BOOTSTRAP_RESAMPLES: usize = 1000;
+/// Confidence level of the bootstrap intervals.
+pub const CONFIDENCE_LEVEL: f64 = 0.95;
+
+/// A metric that can be compared between communities.
+///
+/// Every metric is a mean of a per-item value, so its uncertainty can be estimated by
+/// bootstrapping the items.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ComparedMetric {
+    MeanScore,
+    SentimentMean,
+    /// Share of items with negative sentiment
+    NegativeShare,
+    /// Share of items detected by a detection report, eg. hate speech rate
+    DetectionRate(RMoodsReportType),
+}
+
+impl ComparedMetric {
+    /// Metrics that can be compared for the given report types.
+    pub fn for_report_types(report_types: &[RMoodsReportType]) -> Vec<ComparedMetric> {
+        let mut metrics = vec![ComparedMetric::MeanScore];
+        if report_types.contains(&RMoodsReportType::Sentiment) {
+            metrics.push(ComparedMetric::SentimentMean);
+            metrics.push(ComparedMetric::NegativeShare);
+        }
+        let mut report_types = report_types.to_vec();
+        report_types.sort();
+        report_types.dedup();
+        metrics.extend(
+            report_types
+                .into_iter()
+                .filter(|&t| is_detection(t))
+                .map(ComparedMetric::DetectionRate),
+        );
+        metrics
+    }
+
+    /// The item's contribution to the metric, `None` if the item wasn't analyzed for it.
+    pub fn item_value(&self, item: &AnalyzedItem) -> Option<f64> {
+        match self {
+            ComparedMetric::MeanScore => Some(item.item.score as f64),
+            ComparedMetric::SentimentMean => item.analysis.sentiment.map(|s| s as f64),
+            ComparedMetric::NegativeShare => item
+                .analysis
+                .sentiment
+                .map(|s| indicator(s < NEGATIVE_SENTIMENT)),
+            ComparedMetric::DetectionRate(report_type) => item
+                .analysis
+                .detection(*report_type)
+                .map(|p| indicator(p >= DETECTION_THRESHOLD)),
+        }
+    }
+}
+
+fn is_detection(report_type: RMoodsReportType) -> bool {
+    !matches!(
+        report_type,
+        RMoodsReportType::Sentiment | RMoodsReportType::Keywords | RMoodsReportType::Language
+    )
+}
+
+fn indicator(condition: bool) -> f64 {
+    if condition {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+/// Bootstrap confidence interval, see [CONFIDENCE_LEVEL].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ConfidenceInterval {
+    pub low: f64,
+    pub high: f64,
+}
+
+impl ConfidenceInterval {
+    pub fn contains(&self, value: f64) -> bool {
+        self.low <= value && value <= self.high
+    }
+}
+
+/// Value of a metric in one community.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetricEstimate {
+    pub value: f64,
+    /// `None` if there were too few items to resample
+    pub ci: Option<ConfidenceInterval>,
+    /// Number of items the metric was computed from
+    pub analyzed: usize,
+}
+
+/// Difference of a metric between two communities, `a - b`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetricDifference {
+    /// Subreddit names
+    pub a: String,
+    pub b: String,
+    pub difference: f64,
+    pub ci: Option<ConfidenceInterval>,
+    /// Does the confidence interval of the difference exclude 0?
+    pub significant: bool,
+}
+
+/// One metric across all compared communities.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetricComparison {
+    pub metric: ComparedMetric,
+    /// Aligned with [Comparison::subreddits], `None` if the community had no analyzed items
+    pub values: Vec<Option<MetricEstimate>>,
+    /// Differences between every pair of communities
+    pub differences: Vec<MetricDifference>,
+}
+
+/// Side by side comparison of the same reports generated for several communities.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Comparison {
+    pub subreddits: Vec<String>,
+    pub report_types: Vec<RMoodsReportType>,
+    /// Aligned with `subreddits`
+    pub summaries: Vec<ReportSummary>,
+    pub metrics: Vec<MetricComparison>,
+}
+
+impl Comparison {
+    /// Compare the reports of the communities.
+    pub fn from_reports(
+        communities: Vec<(String, Report)>,
+        report_types: &[RMoodsReportType],
+        rng: &mut impl Rng,
+    ) -> Self {
+        let metrics = ComparedMetric::for_report_types(report_types)
+            .into_iter()
+            .map(|metric| {
+                let samples = communities
+                    .iter()
+                    .map(|(_, report)| {
+                        report
+                            .items
+                            .iter()
+                            .filter_map(|i| metric.item_value(i))
+                            .collect::<Vec<_>>()
+                    })
+                    .collect::<Vec<_>>();
+                compare_metric(metric, &communities, &samples, rng)
+            })
+            .collect();
+
+        let (subreddits, summaries) = communities
+            .into_iter()
+            .map(|(subreddit, report)| (subreddit, report.summary))
+            .unzip();
+
+        Self {
+            subreddits,
+            report_types: report_types.to_vec(),
+            summaries,
+            metrics,
+        }
+    }
+}
+
+fn compare_metric(
+    metric: ComparedMetric,
+    communities: &[(String, Report)],
+    samples: &[Vec<f64>],
+    rng: &mut impl Rng,
+) -> MetricComparison {
+    let values = samples
+        .iter()
+        .map(|s| {
+            mean(s).map(|value| MetricEstimate {
+                value,
+                ci: bootstrap_ci(rng, |rng| mean_of_resample(s, rng)),
+                analyzed: s.len(),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let mut differences = vec![];
+    for i in 0..communities.len() {
+        for j in i + 1..communities.len() {
+            let (Some(a), Some(b)) = (&values[i], &values[j]) else {
+                continue;
+            };
+            let ci = bootstrap_ci(rng, |rng| {
+                Some(mean_of_resample(&samples[i], rng)? - mean_of_resample(&samples[j], rng)?)
+            });
+            differences.push(MetricDifference {
+                a: communities[i].0.clone(),
+                b: communities[j].0.clone(),
+                difference: a.value - b.value,
+                ci,
+                significant: ci.is_some_and(|ci| !ci.contains(0.0)),
+            });
+        }
+    }
+
+    MetricComparison {
+        metric,
+        values,
+        differences,
+    }
+}
+
+fn mean(values: &[f64]) -> Option<f64> {
+    (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+}
+
+/// Mean of a sample drawn with replacement. `None` if there are too few values to resample.
+fn mean_of_resample(values: &[f64], rng: &mut impl Rng) -> Option<f64> {
+    if values.len() < 2 {
+        return None;
+    }
+    let sum = (0..values.len())
+        .map(|_| values[rng.gen_range(0..values.len())])
+        .sum::<f64>();
+    Some(sum / values.len() as f64)
+}
+
+/// Percentile bootstrap interval of a statistic computed on resampled data.
+fn bootstrap_ci<R: Rng>(
+    rng: &mut R,
+    mut statistic: impl FnMut(&mut R) -> Option<f64>,
+) -> Option<ConfidenceInterval> {
+    let mut estimates = (0..BOOTSTRAP_RESAMPLES)
+        .map(|_| statistic(rng))
+        .collect::<Option<Vec<_>>>()?;
+    estimates.sort_by(f64::total_cmp);
+
+    let tail = (1.0 - CONFIDENCE_LEVEL) / 2.0;
+    let percentile = |p: f64| estimates[((estimates.len() - 1) as f64 * p).round() as usize];
+    Some(ConfidenceInterval {
+        low: percentile(tail),
+        high: percentile(1.0 - tail),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::nlp::ItemAnalysis;
+    use crate::report::{ItemKind, ReportItem};
+    use chrono::DateTime;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn report(sentiments: &[f32]) -> Report {
+        let items = sentiments
+            .iter()
+            .map(|&s| AnalyzedItem {
+                item: ReportItem {
+                    id: "t3_abc".to_string(),
+                    kind: ItemKind::Post,
+                    subreddit: "Polska".to_string(),
+                    author: "spez".to_string(),
+                    created_utc: DateTime::UNIX_EPOCH,
+                    score: 1,
+                    text: "Lorem ipsum".to_string(),
+                    status: Default::default(),
+                },
+                analysis: ItemAnalysis {
+                    sentiment: Some(s),
+                    ..Default::default()
+                },
+            })
+            .collect::<Vec<_>>();
+        Report {
+            report_types: vec![RMoodsReportType::Sentiment],
+            summary: ReportSummary::from_items(&items),
+            items,
+        }
+    }
+
+    fn compare(a: &[f32], b: &[f32]) -> Comparison {
+        Comparison::from_reports(
+            vec![("a".to_string(), report(a)), ("b".to_string(), report(b))],
+            &[RMoodsReportType::Sentiment],
+            &mut StdRng::seed_from_u64(2137),
+        )
+    }
+
+    fn sentiment_mean(comparison: &Comparison) -> &MetricComparison {
+        comparison
+            .metrics
+            .iter()
+            .find(|m| m.metric == ComparedMetric::SentimentMean)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_metrics_for_report_types() {
+        let metrics = ComparedMetric::for_report_types(&[
+            RMoodsReportType::HateSpeech,
+            RMoodsReportType::Keywords,
+            RMoodsReportType::Sentiment,
+        ]);
+        assert_eq!(
+            metrics,
+            [
+                ComparedMetric::MeanScore,
+                ComparedMetric::SentimentMean,
+                ComparedMetric::NegativeShare,
+                ComparedMetric::DetectionRate(RMoodsReportType::HateSpeech),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_significant_difference() {
+        let comparison = compare(&[-0.6, -0.5, -0.7, -0.4, -0.5], &[0.5, 0.6, 0.4, 0.5, 0.7]);
+        assert_eq!(comparison.subreddits, ["a", "b"]);
+        assert_eq!(comparison.summaries.len(), 2);
+
+        let metric = sentiment_mean(&comparison);
+        let a = metric.values[0].as_ref().unwrap();
+        assert!((a.value + 0.54).abs() < 1e-6);
+        assert!(a.ci.unwrap().contains(a.value));
+
+        let difference = &metric.differences[0];
+        assert_eq!((difference.a.as_str(), difference.b.as_str()), ("a", "b"));
+        assert!((difference.difference + 1.08).abs() < 1e-6);
+        assert!(difference.significant);
+    }
+
+    #[test]
+    fn test_insignificant_difference() {
+        let comparison = compare(&[-0.5, 0.5, 0.1, -0.2], &[0.4, -0.6, 0.0, 0.1]);
+        assert!(!sentiment_mean(&comparison).differences[0].significant);
+    }
+
+    #[test]
+    fn test_too_few_items_for_interval() {
+        let comparison = compare(&[0.5], &[]);
+        let metric = sentiment_mean(&comparison);
+
+        assert_eq!(metric.values[0].as_ref().unwrap().ci, None);
+        assert_eq!(metric.values[1], None);
+        assert!(metric.differences.is_empty());
+    }
+}
@@ -0,0 +1,33 @@
+use thiserror::Error;
+
+/// Represents any kind of error that can occur when generating a report.
+#[derive(Debug, Error)]
+pub enum ReportError {
+    /// The request to the NLP service failed.
+    #[error("NLP service request failed: {0}")]
+    NlpHttpError(#[from] reqwest::Error),
+
+    /// The NLP service responded, but the response doesn't match the request,
+    /// eg. the number of results differs from the number of texts sent.
+    #[error("Invalid response from the NLP service: {0}")]
+    NlpResponseError(String),
+}
+
+/// Represents any kind of error that can occur when exporting the items of a report.
+#[derive(Debug, Error)]
+pub enum ExportError {
+    #[error("Select at least one column to export")]
+    NoColumns,
+
+    #[error("Failed to encode CSV: {0}")]
+    CsvError(#[from] csv::Error),
+
+    #[error("Failed to encode JSON: {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    #[error("Failed to build Arrow arrays: {0}")]
+    ArrowError(#[from] arrow_schema::ArrowError),
+
+    #[error("Failed to encode Parquet: {0}")]
+    ParquetError(#[from] parquet::errors::ParquetError),
+}
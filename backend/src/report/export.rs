@@ -0,0 +1,553 @@
+//! Export of the analyzed items of a report, one row per item.
+//!
+//! Rows are encoded in chunks of [EXPORT_CHUNK_ROWS], so a response streams the file
+//! instead of holding all of it in memory. Parquet files get a row group per chunk.
+
+use crate::reddit_fetcher::feed_request::RMoodsReportType;
+use crate::report::error::ExportError;
+use crate::report::{AnalyzedItem, ItemKind};
+use arrow_array::builder::{ListBuilder, StringBuilder};
+use arrow_array::{
+    ArrayRef, Float32Array, Int64Array, RecordBatch, StringArray, TimestampSecondArray,
+};
+use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use axum::body::Bytes;
+use futures::Stream;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::{Compression, ZstdLevel};
+use parquet::file::properties::WriterProperties;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+/// Rows encoded at once.
+pub const EXPORT_CHUNK_ROWS: usize = 1000;
+
+/// File format of an export.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    /// A JSON object per line
+    Ndjson,
+    Parquet,
+}
+
+impl ExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv",
+            ExportFormat::Ndjson => "application/x-ndjson",
+            ExportFormat::Parquet => "application/vnd.apache.parquet",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Ndjson => "ndjson",
+            ExportFormat::Parquet => "parquet",
+        }
+    }
+}
+
+/// A column of an export: a field of the item or the output of an analyzer.
+///
+/// Analyzer columns are empty for the items that weren't analyzed by it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportColumn {
+    Id,
+    Kind,
+    Subreddit,
+    Author,
+    CreatedUtc,
+    Score,
+    Text,
+    Sentiment,
+    Sarcasm,
+    /// Joined with `;` in CSV files
+    Keywords,
+    Language,
+    Spam,
+    Politics,
+    HateSpeech,
+    Clickbait,
+    Troll,
+}
+
+impl ExportColumn {
+    /// Columns of the item itself, exported for every report.
+    pub const ITEM_COLUMNS: [ExportColumn; 7] = [
+        ExportColumn::Id,
+        ExportColumn::Kind,
+        ExportColumn::Subreddit,
+        ExportColumn::Author,
+        ExportColumn::CreatedUtc,
+        ExportColumn::Score,
+        ExportColumn::Text,
+    ];
+
+    /// The item's columns followed by the output of every requested report.
+    pub fn defaults(report_types: &[RMoodsReportType]) -> Vec<ExportColumn> {
+        let mut columns = Self::ITEM_COLUMNS.to_vec();
+        for column in report_types.iter().map(|&t| ExportColumn::from(t)) {
+            if !columns.contains(&column) {
+                columns.push(column);
+            }
+        }
+        columns
+    }
+
+    /// Header of the column, the same as its serialized name.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExportColumn::Id => "id",
+            ExportColumn::Kind => "kind",
+            ExportColumn::Subreddit => "subreddit",
+            ExportColumn::Author => "author",
+            ExportColumn::CreatedUtc => "created_utc",
+            ExportColumn::Score => "score",
+            ExportColumn::Text => "text",
+            ExportColumn::Sentiment => "sentiment",
+            ExportColumn::Sarcasm => "sarcasm",
+            ExportColumn::Keywords => "keywords",
+            ExportColumn::Language => "language",
+            ExportColumn::Spam => "spam",
+            ExportColumn::Politics => "politics",
+            ExportColumn::HateSpeech => "hate_speech",
+            ExportColumn::Clickbait => "clickbait",
+            ExportColumn::Troll => "troll",
+        }
+    }
+
+    /// Probability or polarity of the item, for the columns of the numeric analyzers.
+    fn probability(&self, item: &AnalyzedItem) -> Option<f32> {
+        match self {
+            ExportColumn::Sentiment => item.analysis.sentiment,
+            ExportColumn::Sarcasm => item.analysis.sarcasm,
+            ExportColumn::Spam => item.analysis.spam,
+            ExportColumn::Politics => item.analysis.politics,
+            ExportColumn::HateSpeech => item.analysis.hate_speech,
+            ExportColumn::Clickbait => item.analysis.clickbait,
+            ExportColumn::Troll => item.analysis.troll,
+            _ => None,
+        }
+    }
+
+    /// Text of the item, for the string columns.
+    fn text<'a>(&self, item: &'a AnalyzedItem) -> Option<&'a str> {
+        match self {
+            ExportColumn::Id => Some(&item.item.id),
+            ExportColumn::Kind => Some(kind_name(item.item.kind)),
+            ExportColumn::Subreddit => Some(&item.item.subreddit),
+            ExportColumn::Author => Some(&item.item.author),
+            ExportColumn::Text => Some(&item.item.text),
+            ExportColumn::Language => item.analysis.language.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// The item's value as JSON, timestamps as RFC 3339.
+    fn json(&self, item: &AnalyzedItem) -> Value {
+        match self {
+            ExportColumn::CreatedUtc => json!(item.item.created_utc.to_rfc3339()),
+            ExportColumn::Score => json!(item.item.score),
+            ExportColumn::Keywords => json!(item.analysis.keywords),
+            column if column.is_probability() => json!(column.probability(item)),
+            column => json!(column.text(item)),
+        }
+    }
+
+    /// The item's value as a CSV field, empty if missing.
+    fn csv(&self, item: &AnalyzedItem) -> String {
+        match self {
+            ExportColumn::CreatedUtc => item.item.created_utc.to_rfc3339(),
+            ExportColumn::Score => item.item.score.to_string(),
+            ExportColumn::Keywords => item
+                .analysis
+                .keywords
+                .as_ref()
+                .map(|k| k.join(";"))
+                .unwrap_or_default(),
+            column if column.is_probability() => column
+                .probability(item)
+                .map(|p| p.to_string())
+                .unwrap_or_default(),
+            column => column.text(item).unwrap_or_default().to_string(),
+        }
+    }
+
+    fn is_probability(&self) -> bool {
+        self.data_type() == DataType::Float32
+    }
+
+    /// Type of the column in Parquet files.
+    fn data_type(&self) -> DataType {
+        match self {
+            ExportColumn::CreatedUtc => DataType::Timestamp(TimeUnit::Second, Some("UTC".into())),
+            ExportColumn::Score => DataType::Int64,
+            ExportColumn::Keywords => {
+                DataType::List(Arc::new(Field::new("item", DataType::Utf8, true)))
+            }
+            ExportColumn::Sentiment
+            | ExportColumn::Sarcasm
+            | ExportColumn::Spam
+            | ExportColumn::Politics
+            | ExportColumn::HateSpeech
+            | ExportColumn::Clickbait
+            | ExportColumn::Troll => DataType::Float32,
+            _ => DataType::Utf8,
+        }
+    }
+
+    /// Values of the column for all the items, as an Arrow array.
+    fn array(&self, items: &[AnalyzedItem]) -> ArrayRef {
+        match self.data_type() {
+            DataType::Timestamp(..) => Arc::new(
+                TimestampSecondArray::from_iter_values(
+                    items.iter().map(|i| i.item.created_utc.timestamp()),
+                )
+                .with_timezone("UTC"),
+            ),
+            DataType::Int64 => Arc::new(Int64Array::from_iter_values(
+                items.iter().map(|i| i.item.score),
+            )),
+            DataType::List(_) => {
+                let mut builder = ListBuilder::new(StringBuilder::new());
+                for item in items {
+                    match &item.analysis.keywords {
+                        Some(keywords) => {
+                            keywords
+                                .iter()
+                                .for_each(|k| builder.values().append_value(k));
+                            builder.append(true);
+                        }
+                        None => builder.append(false),
+                    }
+                }
+                Arc::new(builder.finish())
+            }
+            DataType::Float32 => Arc::new(Float32Array::from(
+                items
+                    .iter()
+                    .map(|i| self.probability(i))
+                    .collect::<Vec<_>>(),
+            )),
+            _ => Arc::new(StringArray::from(
+                items.iter().map(|i| self.text(i)).collect::<Vec<_>>(),
+            )),
+        }
+    }
+}
+
+impl From<RMoodsReportType> for ExportColumn {
+    fn from(report_type: RMoodsReportType) -> Self {
+        match report_type {
+            RMoodsReportType::Sentiment => ExportColumn::Sentiment,
+            RMoodsReportType::Sarcasm => ExportColumn::Sarcasm,
+            RMoodsReportType::Keywords => ExportColumn::Keywords,
+            RMoodsReportType::Language => ExportColumn::Language,
+            RMoodsReportType::Spam => ExportColumn::Spam,
+            RMoodsReportType::Politics => ExportColumn::Politics,
+            RMoodsReportType::HateSpeech => ExportColumn::HateSpeech,
+            RMoodsReportType::Clickbait => ExportColumn::Clickbait,
+            RMoodsReportType::Troll => ExportColumn::Troll,
+        }
+    }
+}
+
+fn kind_name(kind: ItemKind) -> &'static str {
+    match kind {
+        ItemKind::Post => "post",
+        ItemKind::Comment => "comment",
+    }
+}
+
+/// The Parquet writer writes into this buffer, which is emptied after every row group.
+#[derive(Debug, Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl SharedBuffer {
+    fn take(&self) -> Bytes {
+        Bytes::from(std::mem::take(&mut *self.0.lock().unwrap()))
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+enum Encoder {
+    Csv {
+        header_written: bool,
+    },
+    Ndjson,
+    Parquet {
+        schema: SchemaRef,
+        writer: Box<ArrowWriter<SharedBuffer>>,
+        buffer: SharedBuffer,
+    },
+}
+
+/// Encodes the items chunk by chunk, see the [module docs](self).
+pub struct Exporter {
+    columns: Vec<ExportColumn>,
+    encoder: Encoder,
+}
+
+impl Exporter {
+    pub fn new(format: ExportFormat, columns: Vec<ExportColumn>) -> Result<Self, ExportError> {
+        if columns.is_empty() {
+            return Err(ExportError::NoColumns);
+        }
+        let encoder = match format {
+            ExportFormat::Csv => Encoder::Csv {
+                header_written: false,
+            },
+            ExportFormat::Ndjson => Encoder::Ndjson,
+            ExportFormat::Parquet => {
+                let fields = columns
+                    .iter()
+                    .map(|c| Field::new(c.name(), c.data_type(), true))
+                    .collect::<Vec<_>>();
+                let schema = Arc::new(Schema::new(fields));
+                let properties = WriterProperties::builder()
+                    .set_compression(Compression::ZSTD(ZstdLevel::default()))
+                    .build();
+                let buffer = SharedBuffer::default();
+                let writer =
+                    ArrowWriter::try_new(buffer.clone(), schema.clone(), Some(properties))?;
+                Encoder::Parquet {
+                    schema,
+                    writer: Box::new(writer),
+                    buffer,
+                }
+            }
+        };
+        Ok(Self { columns, encoder })
+    }
+
+    /// Encode the next chunk of items.
+    pub fn encode(&mut self, items: &[AnalyzedItem]) -> Result<Bytes, ExportError> {
+        match &mut self.encoder {
+            Encoder::Csv { header_written } => {
+                let mut writer = csv::WriterBuilder::new().from_writer(vec![]);
+                if !*header_written {
+                    writer.write_record(self.columns.iter().map(|c| c.name()))?;
+                    *header_written = true;
+                }
+                for item in items {
+                    writer.write_record(self.columns.iter().map(|c| c.csv(item)))?;
+                }
+                let bytes = writer
+                    .into_inner()
+                    .map_err(|e| csv::Error::from(e.into_error()))?;
+                Ok(Bytes::from(bytes))
+            }
+            Encoder::Ndjson => {
+                let mut bytes = vec![];
+                for item in items {
+                    let row = self
+                        .columns
+                        .iter()
+                        .map(|c| (c.name().to_string(), c.json(item)))
+                        .collect::<Map<_, _>>();
+                    serde_json::to_writer(&mut bytes, &row)?;
+                    bytes.push(b'\n');
+                }
+                Ok(Bytes::from(bytes))
+            }
+            Encoder::Parquet {
+                schema,
+                writer,
+                buffer,
+            } => {
+                if items.is_empty() {
+                    return Ok(Bytes::new());
+                }
+                let arrays = self.columns.iter().map(|c| c.array(items)).collect();
+                let batch = RecordBatch::try_new(schema.clone(), arrays)?;
+                writer.write(&batch)?;
+                // Closes the row group, so its bytes can be sent
+                writer.flush()?;
+                Ok(buffer.take())
+            }
+        }
+    }
+
+    /// Encode the end of the file, eg. the Parquet footer.
+    pub fn finish(mut self) -> Result<Bytes, ExportError> {
+        // A file without rows still has its header
+        if let Encoder::Csv {
+            header_written: false,
+        } = self.encoder
+        {
+            return self.encode(&[]);
+        }
+        match self.encoder {
+            Encoder::Parquet { writer, buffer, .. } => {
+                writer.close()?;
+                Ok(buffer.take())
+            }
+            _ => Ok(Bytes::new()),
+        }
+    }
+
+    /// Stream the encoded items, chunk by chunk. The stream ends after the first error.
+    pub fn stream(
+        self,
+        items: Vec<AnalyzedItem>,
+    ) -> impl Stream<Item = Result<Bytes, ExportError>> + Send {
+        futures::stream::unfold(Some((self, items.into_iter())), |state| async move {
+            let (mut exporter, mut items) = state?;
+            let chunk = items.by_ref().take(EXPORT_CHUNK_ROWS).collect::<Vec<_>>();
+            if chunk.is_empty() {
+                return Some((exporter.finish(), None));
+            }
+            match exporter.encode(&chunk) {
+                Ok(bytes) => Some((Ok(bytes), Some((exporter, items)))),
+                Err(e) => Some((Err(e), None)),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::nlp::ItemAnalysis;
+    use crate::report::ReportItem;
+    use chrono::DateTime;
+    use futures::StreamExt;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    fn item(id: &str, analysis: ItemAnalysis) -> AnalyzedItem {
+        AnalyzedItem {
+            item: ReportItem {
+                id: id.to_string(),
+                kind: ItemKind::Post,
+                subreddit: "Polska".to_string(),
+                author: "spez".to_string(),
+                created_utc: DateTime::from_timestamp(1480961536, 0).unwrap(),
+                score: 12,
+                text: "Lorem, \"ipsum\"".to_string(),
+                status: Default::default(),
+            },
+            analysis,
+        }
+    }
+
+    fn items() -> Vec<AnalyzedItem> {
+        vec![
+            item(
+                "t3_a",
+                ItemAnalysis {
+                    sentiment: Some(0.5),
+                    keywords: Some(vec!["lorem".to_string(), "ipsum".to_string()]),
+                    ..Default::default()
+                },
+            ),
+            item("t3_b", ItemAnalysis::default()),
+        ]
+    }
+
+    async fn export(
+        format: ExportFormat,
+        columns: Vec<ExportColumn>,
+        items: Vec<AnalyzedItem>,
+    ) -> Vec<u8> {
+        let chunks = Exporter::new(format, columns)
+            .unwrap()
+            .stream(items)
+            .collect::<Vec<_>>()
+            .await;
+        chunks.into_iter().flat_map(|c| c.unwrap()).collect()
+    }
+
+    #[test]
+    fn test_default_columns() {
+        let columns = ExportColumn::defaults(&[RMoodsReportType::HateSpeech]);
+        assert_eq!(columns.len(), 8);
+        assert_eq!(columns.last(), Some(&ExportColumn::HateSpeech));
+    }
+
+    #[test]
+    fn test_no_columns() {
+        let result = Exporter::new(ExportFormat::Csv, vec![]);
+        assert!(matches!(result, Err(ExportError::NoColumns)));
+    }
+
+    #[tokio::test]
+    async fn test_export_csv() {
+        let columns = vec![
+            ExportColumn::Id,
+            ExportColumn::Text,
+            ExportColumn::Sentiment,
+            ExportColumn::Keywords,
+        ];
+        let csv = export(ExportFormat::Csv, columns.clone(), items()).await;
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "id,text,sentiment,keywords\n\
+             t3_a,\"Lorem, \"\"ipsum\"\"\",0.5,lorem;ipsum\n\
+             t3_b,\"Lorem, \"\"ipsum\"\"\",,\n"
+        );
+
+        let empty = export(ExportFormat::Csv, columns, vec![]).await;
+        assert_eq!(empty, b"id,text,sentiment,keywords\n");
+    }
+
+    #[tokio::test]
+    async fn test_export_ndjson() {
+        let columns = vec![
+            ExportColumn::Id,
+            ExportColumn::CreatedUtc,
+            ExportColumn::Score,
+            ExportColumn::Sentiment,
+        ];
+        let ndjson = export(ExportFormat::Ndjson, columns, items()).await;
+        let rows = String::from_utf8(ndjson)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str::<Value>(line).unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            rows[0],
+            json!({ "id": "t3_a", "created_utc": "2016-12-05T18:12:16+00:00", "score": 12, "sentiment": 0.5 })
+        );
+        assert_eq!(rows[1]["sentiment"], Value::Null);
+    }
+
+    #[tokio::test]
+    async fn test_export_parquet_in_row_groups() {
+        let items = (0..EXPORT_CHUNK_ROWS + 1)
+            .map(|i| item(&format!("t3_{i}"), ItemAnalysis::default()))
+            .collect::<Vec<_>>();
+        let columns = ExportColumn::defaults(&[RMoodsReportType::Keywords, RMoodsReportType::Spam]);
+        let parquet = export(ExportFormat::Parquet, columns.clone(), items).await;
+
+        let reader = ParquetRecordBatchReaderBuilder::try_new(Bytes::from(parquet)).unwrap();
+        assert_eq!(reader.metadata().num_row_groups(), 2);
+        let names = reader
+            .schema()
+            .fields()
+            .iter()
+            .map(|f| f.name().as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, columns.iter().map(|c| c.name()).collect::<Vec<_>>());
+
+        let rows: usize = reader.build().unwrap().map(|b| b.unwrap().num_rows()).sum();
+        assert_eq!(rows, EXPORT_CHUNK_ROWS + 1);
+    }
+}
@@ -0,0 +1,173 @@
+//! Report pipeline.
+//!
+//! Turns data fetched by the [RMoodsFetcher](crate::reddit_fetcher::fetcher::RMoodsFetcher) into
+//! report items, runs them through the NLP service and aggregates the results.
+
+use crate::metrics;
+use crate::reddit_fetcher::feed_request::RMoodsReportType;
+use crate::reddit_fetcher::reddit::model::{timestamp, ContentStatus, RawComment, RawPost};
+use chrono::{DateTime, Utc};
+use error::ReportError;
+use log::info;
+use nlp::{ItemAnalysis, NlpClient};
+use serde::{Deserialize, Serialize};
+use summary::ReportSummary;
+use tracing::instrument;
+
+pub mod compare;
+pub mod error;
+pub mod export;
+pub mod nlp;
+pub mod summary;
+pub mod user;
+
+/// What kind of Reddit item was a report item made from?
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemKind {
+    Post,
+    Comment,
+}
+
+/// A single piece of text to analyze, with the metadata needed to aggregate the results.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportItem {
+    /// Fullname of the item, eg. t3_8z1v1z for posts or t1_lsq1x0a for comments.
+    pub id: String,
+    pub kind: ItemKind,
+    /// eg. Polska
+    pub subreddit: String,
+    /// Username without `u/`, eg. spez
+    pub author: String,
+    /// Creation time, as UNIX seconds like in Reddit's responses
+    #[serde(with = "timestamp")]
+    pub created_utc: DateTime<Utc>,
+    /// Upvotes - downvotes
+    pub score: i64,
+    /// Text sent to the NLP service. For posts it's the title followed by the selftext.
+    pub text: String,
+    /// Deleted and removed items are not analyzed, only counted
+    #[serde(default)]
+    pub status: ContentStatus,
+}
+
+impl From<&RawPost> for ReportItem {
+    fn from(post: &RawPost) -> Self {
+        let text = format!("{}\n{}", post.title(), post.selftext())
+            .trim()
+            .to_string();
+        Self {
+            id: post.name().to_string(),
+            kind: ItemKind::Post,
+            subreddit: post.subreddit().to_string(),
+            author: post.author().to_string(),
+            created_utc: *post.created_utc(),
+            score: *post.score(),
+            text,
+            status: post.status(),
+        }
+    }
+}
+
+impl From<&RawComment> for ReportItem {
+    fn from(comment: &RawComment) -> Self {
+        Self {
+            id: comment.name().to_string(),
+            kind: ItemKind::Comment,
+            subreddit: comment.subreddit().to_string(),
+            author: comment.author().to_string(),
+            created_utc: *comment.created_utc(),
+            score: *comment.score(),
+            text: comment.body().to_string(),
+            status: comment.status(),
+        }
+    }
+}
+
+/// A report item together with the NLP service's verdict on it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnalyzedItem {
+    #[serde(flatten)]
+    pub item: ReportItem,
+    pub analysis: ItemAnalysis,
+}
+
+/// A generated report: every analyzed item and the metrics aggregated over them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Report {
+    pub report_types: Vec<RMoodsReportType>,
+    pub items: Vec<AnalyzedItem>,
+    pub summary: ReportSummary,
+}
+
+impl Report {
+    /// Analyze the items with the NLP service and aggregate the results.
+    ///
+    /// Deleted and removed items have no text left to analyze, they are only counted in the summary.
+    #[instrument(
+        name = "analyze",
+        skip_all,
+        fields(items = items.len(), report_types = ?report_types)
+    )]
+    pub async fn generate(
+        nlp: &NlpClient,
+        items: Vec<ReportItem>,
+        report_types: &[RMoodsReportType],
+    ) -> Result<Self, ReportError> {
+        let _job = metrics::ReportJob::start();
+        let (items, excluded): (Vec<_>, Vec<_>) = items
+            .into_iter()
+            .partition(|i| i.status == ContentStatus::Visible);
+        info!(
+            "Generating a report of {} items ({} excluded): {:?}",
+            items.len(),
+            excluded.len(),
+            report_types
+        );
+        let texts = items.iter().map(|i| i.text.clone()).collect::<Vec<_>>();
+        let analyses = nlp.analyze(&texts, report_types).await?;
+
+        let items = items
+            .into_iter()
+            .zip(analyses)
+            .map(|(item, analysis)| AnalyzedItem { item, analysis })
+            .collect::<Vec<_>>();
+        let summary = ReportSummary::from_items(&items).with_excluded(&excluded);
+
+        Ok(Self {
+            report_types: report_types.to_vec(),
+            items,
+            summary,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reddit_fetcher::reddit::mock::fixtures;
+    use crate::reddit_fetcher::reddit::model::RawContainer;
+
+    #[test]
+    fn test_items_are_identified_by_fullname() {
+        let [post, comments]: [RawContainer; 2] =
+            serde_json::from_value(fixtures::post_comments()).unwrap();
+        let RawContainer::Listing(post) = post else {
+            panic!("Expected a listing");
+        };
+        let RawContainer::Listing(comments) = comments else {
+            panic!("Expected a listing");
+        };
+        let RawContainer::Post(post) = &post.children[0] else {
+            panic!("Expected a post");
+        };
+        let RawContainer::Comment(comment) = &comments.children[0] else {
+            panic!("Expected a comment");
+        };
+
+        assert_eq!(ReportItem::from(post.as_ref()).id, "t3_1g7dw8m");
+        let item = ReportItem::from(comment.as_ref());
+        assert_eq!(item.id, "t1_lsp8x2f");
+        assert_eq!(item.kind, ItemKind::Comment);
+    }
+}
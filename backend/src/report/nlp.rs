@@ -0,0 +1,200 @@
+use crate::metrics;
+use crate::reddit_fetcher::feed_request::RMoodsReportType;
+use crate::report::error::ReportError;
+use log::debug;
+use log_derive::logfn;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::time::Instant;
+use tracing::instrument;
+
+/// How many texts are sent to the NLP service in a single request.
+const NLP_BATCH_SIZE: usize = 100;
+
+/// Results of the NLP analysis of a single text.
+///
+/// Only the fields of the requested report types are filled in.
+/// * `sentiment` is a polarity in the range of -1 (negative) to 1 (positive).
+/// * Detection reports (`sarcasm`, `spam`, `politics`, `hate_speech`, `clickbait`, `troll`)
+///   are probabilities in the range of 0 to 1.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ItemAnalysis {
+    pub sentiment: Option<f32>,
+    pub sarcasm: Option<f32>,
+    pub keywords: Option<Vec<String>>,
+    /// ISO 639-1 code, eg. pl
+    pub language: Option<String>,
+    pub spam: Option<f32>,
+    pub politics: Option<f32>,
+    pub hate_speech: Option<f32>,
+    pub clickbait: Option<f32>,
+    pub troll: Option<f32>,
+}
+
+impl ItemAnalysis {
+    /// Get the probability returned by a detection report.
+    /// Returns `None` for reports that are not probabilities, or weren't requested.
+    pub fn detection(&self, report_type: RMoodsReportType) -> Option<f32> {
+        match report_type {
+            RMoodsReportType::Sarcasm => self.sarcasm,
+            RMoodsReportType::Spam => self.spam,
+            RMoodsReportType::Politics => self.politics,
+            RMoodsReportType::HateSpeech => self.hate_speech,
+            RMoodsReportType::Clickbait => self.clickbait,
+            RMoodsReportType::Troll => self.troll,
+            RMoodsReportType::Sentiment
+            | RMoodsReportType::Keywords
+            | RMoodsReportType::Language => None,
+        }
+    }
+}
+
+/// Client of the RMoods NLP service.
+///
+/// The service is asked to analyze texts with `POST {base_url}/analyze`:
+/// ```json
+/// { "reports": ["sentiment", "hate_speech"], "texts": ["first text", "second text"] }
+/// ```
+/// It answers with a list of [ItemAnalysis] objects, one per text, in the same order.
+#[derive(Debug, Clone)]
+pub struct NlpClient {
+    http: reqwest::Client,
+    base_url: String,
+}
+
+impl NlpClient {
+    pub fn new(http: reqwest::Client, base_url: impl Into<String>) -> Self {
+        Self {
+            http,
+            base_url: base_url.into(),
+        }
+    }
+
+    /// Analyze the texts, returning one [ItemAnalysis] per text.
+    ///
+    /// Texts are sent in batches. If no report types are requested, the service isn't called at all.
+    #[logfn(err = "ERROR", fmt = "Failed to analyze texts: {0}")]
+    pub async fn analyze(
+        &self,
+        texts: &[String],
+        report_types: &[RMoodsReportType],
+    ) -> Result<Vec<ItemAnalysis>, ReportError> {
+        if report_types.is_empty() {
+            return Ok(vec![ItemAnalysis::default(); texts.len()]);
+        }
+
+        let url = format!("{}/analyze", self.base_url.trim_end_matches('/'));
+        let mut analyses = Vec::with_capacity(texts.len());
+
+        for batch in texts.chunks(NLP_BATCH_SIZE) {
+            debug!("Sending {} texts to the NLP service", batch.len());
+            let start = Instant::now();
+            let results = self.analyze_batch(&url, batch, report_types).await;
+            let outcome = if results.is_ok() { "ok" } else { "error" };
+            metrics::NLP_REQUEST_DURATION
+                .with_label_values(&[outcome])
+                .observe(start.elapsed().as_secs_f64());
+            let results = results?;
+
+            if results.len() != batch.len() {
+                return Err(ReportError::NlpResponseError(format!(
+                    "sent {} texts, received {} results",
+                    batch.len(),
+                    results.len()
+                )));
+            }
+            analyses.extend(results);
+        }
+
+        Ok(analyses)
+    }
+
+    /// Send a single batch of texts to the NLP service.
+    #[instrument(name = "nlp_request", skip_all, fields(texts = batch.len()))]
+    async fn analyze_batch(
+        &self,
+        url: &str,
+        batch: &[String],
+        report_types: &[RMoodsReportType],
+    ) -> Result<Vec<ItemAnalysis>, ReportError> {
+        Ok(self
+            .http
+            .post(url)
+            .json(&json!({
+                "reports": report_types,
+                "texts": batch,
+            }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{extract::State, routing::post, Json, Router};
+    use serde_json::Value;
+    use std::sync::{Arc, Mutex};
+
+    /// The request sent to `/analyze` and the response to it, shared with the NLP service's tests.
+    const CONTRACT: &str = include_str!("../../../nlp/tests/contract/analyze.json");
+
+    /// Start a local NLP service answering every request with the contract's response.
+    async fn start_service() -> (String, Arc<Mutex<Vec<Value>>>) {
+        let contract: Value = serde_json::from_str(CONTRACT).unwrap();
+        let received: Arc<Mutex<Vec<Value>>> = Default::default();
+        let handler = move |State(received): State<Arc<Mutex<Vec<Value>>>>,
+                            Json(body): Json<Value>| async move {
+            received.lock().unwrap().push(body);
+            Json(contract["response"].clone())
+        };
+        let app = Router::new()
+            .route("/analyze", post(handler))
+            .with_state(received.clone());
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (format!("http://{addr}/"), received)
+    }
+
+    #[tokio::test]
+    async fn test_analyze_contract() {
+        let contract: Value = serde_json::from_str(CONTRACT).unwrap();
+        let texts: Vec<String> =
+            serde_json::from_value(contract["request"]["texts"].clone()).unwrap();
+        let report_types: Vec<RMoodsReportType> =
+            serde_json::from_value(contract["request"]["reports"].clone()).unwrap();
+
+        let (url, received) = start_service().await;
+        let nlp = NlpClient::new(reqwest::Client::new(), url);
+        let analyses = nlp.analyze(&texts, &report_types).await.unwrap();
+
+        assert_eq!(
+            received.lock().unwrap().as_slice(),
+            [contract["request"].clone()]
+        );
+        assert_eq!(analyses.len(), 2);
+        assert_eq!(analyses[0].sentiment, Some(1.0));
+        assert_eq!(analyses[0].language.as_deref(), Some("pl"));
+        assert_eq!(analyses[1].hate_speech, Some(0.5));
+        // Every field the service answers with is parsed, and nothing else is expected
+        assert_eq!(
+            serde_json::to_value(&analyses[1]).unwrap(),
+            contract["response"][1]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_result_count_mismatch() {
+        let (url, _) = start_service().await;
+        let nlp = NlpClient::new(reqwest::Client::new(), url);
+        let result = nlp
+            .analyze(&["only one".to_string()], &[RMoodsReportType::Sentiment])
+            .await;
+        assert!(matches!(result, Err(ReportError::NlpResponseError(_))));
+    }
+}
@@ -0,0 +1,327 @@
+use crate::reddit_fetcher::feed_request::RMoodsReportType;
+use crate::reddit_fetcher::reddit::model::ContentStatus;
+use crate::report::{AnalyzedItem, ItemKind, ReportItem};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Sentiment below this value is considered negative.
+pub const NEGATIVE_SENTIMENT: f32 = -0.05;
+/// Sentiment above this value is considered positive.
+pub const POSITIVE_SENTIMENT: f32 = 0.05;
+/// Items with a detection probability at or above this value count as detected, eg. as hate speech.
+pub const DETECTION_THRESHOLD: f32 = 0.5;
+/// How many of the most common keywords are kept in the summary.
+pub const TOP_KEYWORDS: usize = 10;
+
+/// Aggregated sentiment of the analyzed items.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SentimentSummary {
+    /// Mean polarity, -1 to 1
+    pub mean: f64,
+    /// Share of items with negative sentiment, 0 to 1
+    pub negative_share: f64,
+    /// Share of items with positive sentiment, 0 to 1
+    pub positive_share: f64,
+    /// Number of items that had their sentiment analyzed
+    pub analyzed: usize,
+}
+
+/// Aggregated results of a detection report, eg. hate speech or spam.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DetectionSummary {
+    /// Mean probability, 0 to 1
+    pub mean: f64,
+    /// Share of items at or above the [DETECTION_THRESHOLD], 0 to 1
+    pub rate: f64,
+    /// Number of items that were analyzed
+    pub analyzed: usize,
+}
+
+/// How many times a keyword appeared in the analyzed items.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeywordCount {
+    pub keyword: String,
+    pub count: usize,
+}
+
+/// Metrics aggregated over all items of a report.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReportSummary {
+    pub item_count: usize,
+    pub post_count: usize,
+    pub comment_count: usize,
+    /// Mean score (upvotes - downvotes) of the items
+    pub mean_score: f64,
+    pub sentiment: Option<SentimentSummary>,
+    /// Summaries of the detection reports, keyed by the report type.
+    pub detections: BTreeMap<RMoodsReportType, DetectionSummary>,
+    /// Number of items per detected language
+    pub languages: BTreeMap<String, usize>,
+    /// Most common keywords, most frequent first
+    pub keywords: Vec<KeywordCount>,
+    /// Items deleted by their authors, not included in the other metrics
+    #[serde(default)]
+    pub deleted_count: usize,
+    /// Items removed by moderators or Reddit, not included in the other metrics
+    #[serde(default)]
+    pub removed_count: usize,
+}
+
+impl ReportSummary {
+    /// Aggregate the results of the analyzed items.
+    pub fn from_items(items: &[AnalyzedItem]) -> Self {
+        let item_count = items.len();
+        let post_count = items
+            .iter()
+            .filter(|i| i.item.kind == ItemKind::Post)
+            .count();
+        let mean_score = mean(items.iter().map(|i| i.item.score as f64)).unwrap_or(0.0);
+
+        let sentiments = items
+            .iter()
+            .filter_map(|i| i.analysis.sentiment)
+            .collect::<Vec<_>>();
+        let sentiment = mean(sentiments.iter().map(|&s| s as f64)).map(|mean| SentimentSummary {
+            mean,
+            negative_share: share(&sentiments, |&s| s < NEGATIVE_SENTIMENT),
+            positive_share: share(&sentiments, |&s| s > POSITIVE_SENTIMENT),
+            analyzed: sentiments.len(),
+        });
+
+        let detection_types = [
+            RMoodsReportType::Sarcasm,
+            RMoodsReportType::Spam,
+            RMoodsReportType::Politics,
+            RMoodsReportType::HateSpeech,
+            RMoodsReportType::Clickbait,
+            RMoodsReportType::Troll,
+        ];
+        let detections = detection_types
+            .into_iter()
+            .filter_map(|report_type| {
+                let scores = items
+                    .iter()
+                    .filter_map(|i| i.analysis.detection(report_type))
+                    .collect::<Vec<_>>();
+                mean(scores.iter().map(|&s| s as f64)).map(|mean| {
+                    let summary = DetectionSummary {
+                        mean,
+                        rate: share(&scores, |&s| s >= DETECTION_THRESHOLD),
+                        analyzed: scores.len(),
+                    };
+                    (report_type, summary)
+                })
+            })
+            .collect();
+
+        let mut languages = BTreeMap::new();
+        for language in items.iter().filter_map(|i| i.analysis.language.as_ref()) {
+            *languages.entry(language.clone()).or_insert(0) += 1;
+        }
+
+        Self {
+            item_count,
+            post_count,
+            comment_count: item_count - post_count,
+            mean_score,
+            sentiment,
+            detections,
+            languages,
+            keywords: top_keywords(items),
+            deleted_count: 0,
+            removed_count: 0,
+        }
+    }
+
+    /// Count the items left out of the analysis because they were taken down.
+    pub fn with_excluded(self, excluded: &[ReportItem]) -> Self {
+        let count = |status| excluded.iter().filter(|i| i.status == status).count();
+        Self {
+            deleted_count: count(ContentStatus::Deleted),
+            removed_count: count(ContentStatus::Removed),
+            ..self
+        }
+    }
+
+    /// Share of items detected as hate speech, if that report was requested.
+    pub fn toxicity_rate(&self) -> Option<f64> {
+        self.detections
+            .get(&RMoodsReportType::HateSpeech)
+            .map(|d| d.rate)
+    }
+}
+
+fn mean(values: impl Iterator<Item = f64>) -> Option<f64> {
+    let (sum, count) = values.fold((0.0, 0usize), |(sum, count), v| (sum + v, count + 1));
+    (count > 0).then(|| sum / count as f64)
+}
+
+fn share(values: &[f32], predicate: impl Fn(&f32) -> bool) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    values.iter().filter(|v| predicate(v)).count() as f64 / values.len() as f64
+}
+
+fn top_keywords(items: &[AnalyzedItem]) -> Vec<KeywordCount> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for keyword in items
+        .iter()
+        .filter_map(|i| i.analysis.keywords.as_ref())
+        .flatten()
+    {
+        *counts.entry(keyword.to_lowercase()).or_insert(0) += 1;
+    }
+
+    let mut keywords = counts
+        .into_iter()
+        .map(|(keyword, count)| KeywordCount { keyword, count })
+        .collect::<Vec<_>>();
+    // Ties are broken alphabetically, so the summary is deterministic
+    keywords.sort_by(|a, b| {
+        b.count
+            .cmp(&a.count)
+            .then_with(|| a.keyword.cmp(&b.keyword))
+    });
+    keywords.truncate(TOP_KEYWORDS);
+    keywords
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::nlp::ItemAnalysis;
+    use chrono::DateTime;
+
+    fn item(kind: ItemKind, score: i64, analysis: ItemAnalysis) -> AnalyzedItem {
+        AnalyzedItem {
+            item: ReportItem {
+                id: "t3_abc".to_string(),
+                kind,
+                subreddit: "Polska".to_string(),
+                author: "spez".to_string(),
+                created_utc: DateTime::UNIX_EPOCH,
+                score,
+                text: "Lorem ipsum".to_string(),
+                status: Default::default(),
+            },
+            analysis,
+        }
+    }
+
+    #[test]
+    fn test_summary_counts_excluded_items() {
+        let excluded = [
+            ContentStatus::Deleted,
+            ContentStatus::Removed,
+            ContentStatus::Removed,
+        ]
+        .map(|status| ReportItem {
+            status,
+            ..item(ItemKind::Comment, 1, ItemAnalysis::default()).item
+        });
+
+        let summary = ReportSummary::from_items(&[]).with_excluded(&excluded);
+        assert_eq!(summary.item_count, 0);
+        assert_eq!(summary.deleted_count, 1);
+        assert_eq!(summary.removed_count, 2);
+    }
+
+    #[test]
+    fn test_summary_empty() {
+        let summary = ReportSummary::from_items(&[]);
+        assert_eq!(summary, ReportSummary::default());
+        assert_eq!(summary.toxicity_rate(), None);
+    }
+
+    #[test]
+    fn test_summary_counts_and_sentiment() {
+        let items = [
+            item(
+                ItemKind::Post,
+                10,
+                ItemAnalysis {
+                    sentiment: Some(-0.5),
+                    ..Default::default()
+                },
+            ),
+            item(
+                ItemKind::Comment,
+                2,
+                ItemAnalysis {
+                    sentiment: Some(0.5),
+                    ..Default::default()
+                },
+            ),
+            item(
+                ItemKind::Comment,
+                0,
+                ItemAnalysis {
+                    sentiment: Some(0.0),
+                    ..Default::default()
+                },
+            ),
+            item(ItemKind::Comment, 0, ItemAnalysis::default()),
+        ];
+        let summary = ReportSummary::from_items(&items);
+
+        assert_eq!(summary.item_count, 4);
+        assert_eq!(summary.post_count, 1);
+        assert_eq!(summary.comment_count, 3);
+        assert_eq!(summary.mean_score, 3.0);
+
+        let sentiment = summary.sentiment.unwrap();
+        assert_eq!(sentiment.analyzed, 3);
+        assert_eq!(sentiment.mean, 0.0);
+        assert_eq!(sentiment.negative_share, 1.0 / 3.0);
+        assert_eq!(sentiment.positive_share, 1.0 / 3.0);
+    }
+
+    #[test]
+    fn test_summary_detections() {
+        let items = [0.9, 0.1, 0.5, 0.0].map(|p| {
+            item(
+                ItemKind::Post,
+                1,
+                ItemAnalysis {
+                    hate_speech: Some(p),
+                    ..Default::default()
+                },
+            )
+        });
+        let summary = ReportSummary::from_items(&items);
+
+        assert_eq!(summary.detections.len(), 1);
+        assert_eq!(summary.toxicity_rate(), Some(0.5));
+        let hate_speech = &summary.detections[&RMoodsReportType::HateSpeech];
+        assert!((hate_speech.mean - 0.375).abs() < 1e-6);
+        assert_eq!(hate_speech.analyzed, 4);
+    }
+
+    #[test]
+    fn test_summary_languages_and_keywords() {
+        let analysis = |language: &str, keywords: &[&str]| ItemAnalysis {
+            language: Some(language.to_string()),
+            keywords: Some(keywords.iter().map(|k| k.to_string()).collect()),
+            ..Default::default()
+        };
+        let items = [
+            item(ItemKind::Post, 1, analysis("pl", &["Sejm", "wybory"])),
+            item(ItemKind::Post, 1, analysis("pl", &["sejm"])),
+            item(ItemKind::Post, 1, analysis("en", &["election"])),
+        ];
+        let summary = ReportSummary::from_items(&items);
+
+        assert_eq!(summary.languages["pl"], 2);
+        assert_eq!(summary.languages["en"], 1);
+        assert_eq!(
+            summary.keywords[0],
+            KeywordCount {
+                keyword: "sejm".to_string(),
+                count: 2
+            }
+        );
+        assert_eq!(summary.keywords[1].keyword, "election");
+        assert_eq!(summary.keywords.len(), 3);
+    }
+}
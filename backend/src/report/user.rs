@@ -0,0 +1,196 @@
+use crate::reddit_fetcher::feed_request::RMoodsReportType;
+use crate::reddit_fetcher::reddit::model::RawUserAbout;
+use crate::report::summary::ReportSummary;
+use crate::report::{AnalyzedItem, Report};
+use chrono::{DateTime, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Reports generated for every user profile.
+pub const USER_REPORT_TYPES: [RMoodsReportType; 4] = [
+    RMoodsReportType::Sentiment,
+    RMoodsReportType::HateSpeech,
+    RMoodsReportType::Language,
+    RMoodsReportType::Keywords,
+];
+/// How many of the most active subreddits are kept in the report.
+pub const TOP_SUBREDDITS: usize = 10;
+
+/// Karma of the user, by the way it was earned.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Karma {
+    pub post: i64,
+    pub comment: i64,
+    pub awardee: i64,
+    pub awarder: i64,
+    pub total: i64,
+}
+
+/// How many of the user's items were posted in a subreddit.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubredditActivity {
+    pub subreddit: String,
+    pub count: usize,
+}
+
+/// Profile of a single Reddit account: account metadata and analysis of its posts and comments.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserReport {
+    /// Username without `u/`, eg. spez
+    pub name: String,
+    pub karma: Karma,
+    pub created_at: DateTime<Utc>,
+    /// Age of the account in days
+    pub account_age_days: i64,
+    pub is_employee: bool,
+    pub verified: bool,
+    /// Share of the analyzed items detected as hate speech
+    pub toxicity_rate: Option<f64>,
+    /// Sentiment, languages and keywords of the user's posts and comments
+    pub summary: ReportSummary,
+    /// Subreddits the user posted in the most, most active first
+    pub top_subreddits: Vec<SubredditActivity>,
+    /// Number of items posted in each hour of the day, UTC
+    pub posting_hours: [usize; 24],
+}
+
+impl UserReport {
+    /// Combine the account metadata with the report of the user's posts and comments.
+    pub fn new(about: &RawUserAbout, report: Report, now: DateTime<Utc>) -> Self {
+        let created_at = *about.created_utc();
+
+        Self {
+            name: about.name().to_string(),
+            karma: Karma {
+                post: *about.link_karma(),
+                comment: *about.comment_karma(),
+                awardee: *about.awardee_karma(),
+                awarder: *about.awarder_karma(),
+                total: *about.total_karma(),
+            },
+            created_at,
+            account_age_days: (now - created_at).num_days(),
+            is_employee: *about.is_employee(),
+            verified: *about.verified(),
+            toxicity_rate: report.summary.toxicity_rate(),
+            top_subreddits: top_subreddits(&report.items),
+            posting_hours: posting_hours(&report.items),
+            summary: report.summary,
+        }
+    }
+}
+
+fn top_subreddits(items: &[AnalyzedItem]) -> Vec<SubredditActivity> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for item in items {
+        *counts.entry(&item.item.subreddit).or_insert(0) += 1;
+    }
+
+    let mut activity = counts
+        .into_iter()
+        .map(|(subreddit, count)| SubredditActivity {
+            subreddit: subreddit.to_string(),
+            count,
+        })
+        .collect::<Vec<_>>();
+    // Ties are broken alphabetically, so the report is deterministic
+    activity.sort_by(|a, b| {
+        b.count
+            .cmp(&a.count)
+            .then_with(|| a.subreddit.cmp(&b.subreddit))
+    });
+    activity.truncate(TOP_SUBREDDITS);
+    activity
+}
+
+fn posting_hours(items: &[AnalyzedItem]) -> [usize; 24] {
+    let mut hours = [0; 24];
+    for item in items {
+        hours[item.item.created_utc.hour() as usize] += 1;
+    }
+    hours
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::nlp::ItemAnalysis;
+    use crate::report::{ItemKind, ReportItem};
+    use serde_json::json;
+
+    fn item(subreddit: &str, created_utc: i64) -> AnalyzedItem {
+        AnalyzedItem {
+            item: ReportItem {
+                id: "t3_abc".to_string(),
+                kind: ItemKind::Comment,
+                subreddit: subreddit.to_string(),
+                author: "spez".to_string(),
+                created_utc: DateTime::from_timestamp(created_utc, 0).unwrap(),
+                score: 1,
+                text: "Lorem ipsum".to_string(),
+                status: Default::default(),
+            },
+            analysis: ItemAnalysis {
+                hate_speech: Some(0.9),
+                ..Default::default()
+            },
+        }
+    }
+
+    fn about() -> RawUserAbout {
+        serde_json::from_value(json!({
+            "is_employee": true,
+            "awardee_karma": 10,
+            "id": "1w72",
+            "verified": true,
+            "awarder_karma": 5,
+            "link_karma": 100,
+            "comment_karma": 200,
+            "total_karma": 315,
+            "name": "spez",
+            "created_utc": 1118030400.0,
+            "icon_img": "",
+            "snoovatar_img": ""
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_user_report() {
+        // 2024-01-01 00:00 UTC, 03:01 UTC and 2024-01-02 03:01 UTC
+        let items = vec![
+            item("Polska", 1704067200),
+            item("reddit", 1704078080),
+            item("Polska", 1704164480),
+        ];
+        let report = Report {
+            report_types: USER_REPORT_TYPES.to_vec(),
+            summary: ReportSummary::from_items(&items),
+            items,
+        };
+        let now = DateTime::from_timestamp(1704067200, 0).unwrap();
+
+        let user = UserReport::new(&about(), report, now);
+
+        assert_eq!(user.name, "spez");
+        assert_eq!(user.karma.total, 315);
+        assert_eq!(user.account_age_days, 6782);
+        assert_eq!(user.toxicity_rate, Some(1.0));
+        assert_eq!(
+            user.top_subreddits,
+            [
+                SubredditActivity {
+                    subreddit: "Polska".to_string(),
+                    count: 2
+                },
+                SubredditActivity {
+                    subreddit: "reddit".to_string(),
+                    count: 1
+                }
+            ]
+        );
+        assert_eq!(user.posting_hours[0], 1);
+        assert_eq!(user.posting_hours[3], 2);
+        assert_eq!(user.posting_hours.iter().sum::<usize>(), 3);
+    }
+}
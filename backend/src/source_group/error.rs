@@ -0,0 +1,21 @@
+use thiserror::Error;
+
+/// Represents any kind of error that can occur when managing or resolving source groups.
+#[derive(Debug, Error)]
+pub enum SourceGroupError {
+    /// The user has no source group with that name.
+    #[error("Source group not found: {0}")]
+    GroupNotFound(String),
+
+    /// The user already has a source group with that name.
+    #[error("Source group already exists: {0}")]
+    GroupExists(String),
+
+    /// The source group can't be created as requested.
+    #[error("Invalid source group: {0}")]
+    InvalidGroup(String),
+
+    /// A query to the database failed.
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+}
@@ -0,0 +1,147 @@
+//! Named sets of subreddits, eg. "Polish news subs", defined once and reused across requests.
+//!
+//! A group is referenced as `g/<name>` wherever a subreddit data source is accepted.
+//! It resolves to a single combined listing (`r/a+b+c`), so the whole group is fetched
+//! in one paginated stream.
+
+use crate::reddit_fetcher::feed_request::DataSource;
+use crate::reddit_fetcher::reddit::request::SUBREDDIT_SEPARATOR;
+use chrono::{DateTime, Utc};
+use error::SourceGroupError;
+use serde::Serialize;
+use sqlx::{types::Json, FromRow, PgPool};
+
+pub mod error;
+pub mod store;
+
+/// Prefix of a data source name that refers to a source group, eg. `g/polish-news`.
+pub const GROUP_PREFIX: &str = "g/";
+/// How many subreddits can a group have. Reddit ignores the rest of a longer combined listing.
+pub const MAX_GROUP_SIZE: usize = 100;
+
+/// A named set of subreddits defined by a user.
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct SourceGroup {
+    pub id: i64,
+    /// Google ID of the user who defined the group
+    pub owner: String,
+    /// eg. polish-news
+    pub name: String,
+    /// Subreddit names without `r/`, eg. ["Polska", "poland"]
+    pub subreddits: Json<Vec<String>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl SourceGroup {
+    /// Combined data source fetching all subreddits of the group at once, eg. `Polska+poland`.
+    pub fn to_data_source(&self, share: f32) -> DataSource {
+        DataSource {
+            name: self.subreddits.join(SUBREDDIT_SEPARATOR),
+            post_id: None,
+            share,
+        }
+    }
+}
+
+/// Name of the group referenced by the data source name, if it is a group reference.
+pub fn group_name(source: &str) -> Option<&str> {
+    source.strip_prefix(GROUP_PREFIX)
+}
+
+/// Group names are slugs, eg. `polish-news`, so they are safe to use in paths.
+pub fn validate_group_name(name: &str) -> Result<(), SourceGroupError> {
+    let valid = !name.is_empty()
+        && name.len() <= 64
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(SourceGroupError::InvalidGroup(format!(
+            "`{name}` is not a valid group name, use letters, digits, `-` and `_`"
+        )));
+    }
+    Ok(())
+}
+
+/// Check the members of a group, they have to form a valid combined listing.
+pub fn validate_subreddits(subreddits: &[String]) -> Result<(), SourceGroupError> {
+    if subreddits.is_empty() || subreddits.len() > MAX_GROUP_SIZE {
+        return Err(SourceGroupError::InvalidGroup(format!(
+            "A group has between 1 and {MAX_GROUP_SIZE} subreddits"
+        )));
+    }
+    // Subreddit names are 2-21 letters, digits and underscores
+    let invalid = subreddits.iter().find(|s| {
+        !(2..=21).contains(&s.len()) || !s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    });
+    if let Some(subreddit) = invalid {
+        return Err(SourceGroupError::InvalidGroup(format!(
+            "`{subreddit}` is not a valid subreddit name"
+        )));
+    }
+    Ok(())
+}
+
+/// Replace a group reference with the combined data source of the group.
+/// Other data sources are returned as they are.
+///
+/// Groups belong to users, so referencing one without being logged in fails with
+/// [SourceGroupError::GroupNotFound].
+pub async fn resolve_source(
+    pool: &PgPool,
+    owner: Option<&str>,
+    source: DataSource,
+) -> Result<DataSource, SourceGroupError> {
+    let Some(name) = group_name(&source.name) else {
+        return Ok(source);
+    };
+    let group = match owner {
+        Some(owner) => store::get_source_group(pool, owner, name).await?,
+        None => None,
+    };
+    group
+        .map(|g| g.to_data_source(source.share))
+        .ok_or_else(|| SourceGroupError::GroupNotFound(name.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn group(subreddits: &[&str]) -> SourceGroup {
+        SourceGroup {
+            id: 1,
+            owner: "123".to_string(),
+            name: "polish-news".to_string(),
+            subreddits: Json(subreddits.iter().map(|s| s.to_string()).collect()),
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_group_reference() {
+        assert_eq!(group_name("g/polish-news"), Some("polish-news"));
+        assert_eq!(group_name("Polska"), None);
+    }
+
+    #[test]
+    fn test_group_to_combined_source() {
+        let source = group(&["Polska", "poland", "krakow"]).to_data_source(0.5);
+        assert_eq!(source.name, "Polska+poland+krakow");
+        assert_eq!(source.share, 0.5);
+        assert_eq!(source.post_id, None);
+    }
+
+    #[test]
+    fn test_validate_group() {
+        assert!(validate_group_name("polish-news_2").is_ok());
+        assert!(validate_group_name("polish news").is_err());
+        assert!(validate_group_name("").is_err());
+
+        let subreddits = |s: &[&str]| s.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert!(validate_subreddits(&subreddits(&["Polska", "poland"])).is_ok());
+        assert!(validate_subreddits(&subreddits(&["Polska+poland"])).is_err());
+        assert!(validate_subreddits(&subreddits(&["g/polish-news"])).is_err());
+        assert!(validate_subreddits(&[]).is_err());
+    }
+}
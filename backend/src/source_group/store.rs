@@ -0,0 +1,74 @@
+use crate::source_group::error::SourceGroupError;
+use crate::source_group::SourceGroup;
+use sqlx::{types::Json, PgPool};
+
+const SOURCE_GROUP_COLUMNS: &str = "id, owner, name, subreddits, created_at";
+
+/// Postgres error code of a unique constraint violation.
+const UNIQUE_VIOLATION: &str = "23505";
+
+/// Define a new source group. Fails with [SourceGroupError::GroupExists] if the owner
+/// already has a group with that name.
+pub async fn insert_source_group(
+    pool: &PgPool,
+    owner: &str,
+    name: &str,
+    subreddits: &[String],
+) -> Result<SourceGroup, SourceGroupError> {
+    sqlx::query_as(&format!(
+        "INSERT INTO source_groups (owner, name, subreddits)
+         VALUES ($1, $2, $3)
+         RETURNING {SOURCE_GROUP_COLUMNS}"
+    ))
+    .bind(owner)
+    .bind(name)
+    .bind(Json(subreddits))
+    .fetch_one(pool)
+    .await
+    .map_err(|e| match e.as_database_error().and_then(|d| d.code()) {
+        Some(code) if code == UNIQUE_VIOLATION => SourceGroupError::GroupExists(name.to_string()),
+        _ => e.into(),
+    })
+}
+
+/// Get a source group of the given owner by its name.
+pub async fn get_source_group(
+    pool: &PgPool,
+    owner: &str,
+    name: &str,
+) -> Result<Option<SourceGroup>, sqlx::Error> {
+    sqlx::query_as(&format!(
+        "SELECT {SOURCE_GROUP_COLUMNS} FROM source_groups WHERE owner = $1 AND name = $2"
+    ))
+    .bind(owner)
+    .bind(name)
+    .fetch_optional(pool)
+    .await
+}
+
+/// List all source groups of the given owner.
+pub async fn list_source_groups(
+    pool: &PgPool,
+    owner: &str,
+) -> Result<Vec<SourceGroup>, sqlx::Error> {
+    sqlx::query_as(&format!(
+        "SELECT {SOURCE_GROUP_COLUMNS} FROM source_groups WHERE owner = $1 ORDER BY name"
+    ))
+    .bind(owner)
+    .fetch_all(pool)
+    .await
+}
+
+/// Delete a source group. Returns `false` if the owner has no such group.
+pub async fn delete_source_group(
+    pool: &PgPool,
+    owner: &str,
+    name: &str,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM source_groups WHERE owner = $1 AND name = $2")
+        .bind(owner)
+        .bind(name)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
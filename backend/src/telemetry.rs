@@ -0,0 +1,141 @@
+//! Logs and traces of the server.
+//!
+//! Everything logged with `log` or `tracing` goes through a single `tracing` subscriber,
+//! so every line carries the fields of the spans it happened in:
+//! * `request` - the request ID and the `sub` of the signed in user
+//! * `report` and `snapshot` - the ID of the job
+//! * `fetch_feed`, `reddit_request` - the requested feed, each Reddit endpoint and its status
+//! * `analyze`, `nlp_request` - the items of a report and each batch sent to the NLP service
+//!
+//! With an OTLP endpoint configured, the spans are also exported to an OpenTelemetry collector,
+//! where a slow report can be followed from the request to every Reddit and NLP call.
+
+use crate::config::{LogFormat, ServerConfig};
+use axum::body::Body;
+use http::Request;
+use log::{error, info};
+use opentelemetry::trace::{TraceError, TracerProvider as _};
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::trace::TracerProvider;
+use opentelemetry_sdk::{runtime, Resource};
+use tracing::field::Empty;
+use tracing::{info_span, Span};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, EnvFilter, Layer};
+
+/// Name of the service in the exported traces.
+const SERVICE_NAME: &str = "rmoods-backend";
+
+/// Header with the ID of a request, set by the proxy or generated by the server and returned to the client.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// The installed subscriber. Call [Telemetry::shutdown] before exiting, or the last spans are lost.
+pub struct Telemetry {
+    provider: Option<TracerProvider>,
+}
+
+impl Telemetry {
+    /// Install the global subscriber, logging to stdout in the configured format
+    /// and exporting the traces if there's an OTLP endpoint.
+    ///
+    /// `RUST_LOG` takes precedence over the configured log level.
+    pub fn init(config: &ServerConfig) -> Self {
+        let filter =
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(&config.log_level));
+        let output = match config.log_format {
+            LogFormat::Text => fmt::layer().boxed(),
+            LogFormat::Json => fmt::layer().json().boxed(),
+        };
+
+        let (provider, export_error) = match config.otlp_endpoint.as_deref().map(tracer_provider) {
+            Some(Ok(provider)) => (Some(provider), None),
+            Some(Err(e)) => (None, Some(e)),
+            None => (None, None),
+        };
+        let export = provider.as_ref().map(|provider| {
+            tracing_opentelemetry::layer().with_tracer(provider.tracer(SERVICE_NAME))
+        });
+
+        // Also forwards the records of the `log` crate
+        tracing_subscriber::registry()
+            .with(output)
+            .with(export)
+            .with(filter)
+            .init();
+
+        if let Some(e) = export_error {
+            error!("Failed to set up the OTLP exporter, traces won't be exported: {e}");
+        } else if let Some(endpoint) = &config.otlp_endpoint {
+            info!("Exporting traces to {endpoint}");
+        }
+        Self { provider }
+    }
+
+    /// Export the spans that are still buffered.
+    pub async fn shutdown(self) {
+        let Some(provider) = self.provider else {
+            return;
+        };
+        // Flushing blocks until the exporter task on the runtime is done
+        match tokio::task::spawn_blocking(move || provider.shutdown()).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => error!("Failed to export the last traces: {e}"),
+            Err(e) => error!("Failed to export the last traces: {e}"),
+        }
+    }
+}
+
+/// Batch the spans and send them to the collector over OTLP/HTTP.
+fn tracer_provider(endpoint: &str) -> Result<TracerProvider, TraceError> {
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .with_endpoint(format!("{}/v1/traces", endpoint.trim_end_matches('/')))
+        .build()?;
+    Ok(TracerProvider::builder()
+        .with_batch_exporter(exporter, runtime::Tokio)
+        .with_resource(Resource::new([KeyValue::new("service.name", SERVICE_NAME)]))
+        .build())
+}
+
+/// Span of an HTTP request, with the request ID and the signed in user, recorded by the authorization middleware.
+///
+/// Only the path is recorded, the query can hold a JWT.
+pub fn request_span(request: &Request<Body>) -> Span {
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|id| id.to_str().ok())
+        .unwrap_or_default();
+    info_span!(
+        "request",
+        method = %request.method(),
+        path = request.uri().path(),
+        request_id,
+        user = Empty,
+    )
+}
+
+/// A new ID of a report or a snapshot, to find all of its spans.
+pub fn job_id() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_span_skips_the_query() {
+        let request = Request::get("/ws/connect?auth=secret.jwt")
+            .header(REQUEST_ID_HEADER, "abc")
+            .body(Body::empty())
+            .unwrap();
+        let span = request_span(&request);
+        // Without a subscriber the span is disabled, but its metadata is still there
+        let fields = span.metadata().unwrap().fields();
+        let names = fields.iter().map(|f| f.name()).collect::<Vec<_>>();
+        assert_eq!(names, ["method", "path", "request_id", "user"]);
+    }
+}
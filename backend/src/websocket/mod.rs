@@ -1,16 +1,20 @@
 use crate::api::auth::google::GoogleUserInfo;
+use crate::metrics;
+use crate::monitor::alert::AlertPayload;
 use crate::AppState;
-use axum::extract::ws::WebSocket;
+use axum::extract::ws::{Message, WebSocket};
 use axum::extract::{ConnectInfo, State, WebSocketUpgrade};
 use axum::response::IntoResponse;
 use axum::routing::get;
 use futures_util::StreamExt;
 use log::{error, info, warn};
 use peers::PeersMap;
+use serde::Serialize;
 use std::net::SocketAddr;
 use std::sync::atomic::AtomicUsize;
 use tokio::sync::mpsc::{Receiver, Sender};
 use tokio_util::sync::CancellationToken;
+use tracing::instrument;
 
 mod peers;
 
@@ -22,8 +26,14 @@ fn generate_user_id() -> String {
         .to_string()
 }
 
-#[derive(Debug)]
-pub struct ServiceToClientMessage;
+/// Messages that the WebSocket Service pushes to the clients.
+/// Sent as JSON text, eg. `{"type": "alert", "data": {...}}`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum ServiceToClientMessage {
+    /// One of the user's alert rules fired.
+    Alert(AlertPayload),
+}
 
 type ConnectionId = String;
 type GoogleId = String;
@@ -43,6 +53,8 @@ pub enum SystemMessage {
     ReportDone(()),
     AddPeer((WsUserId, Sender<ServiceToClientMessage>)),
     RemovePeer(ConnectionId),
+    /// Push a fired alert to all connections of the monitor's owner.
+    Alert((GoogleId, AlertPayload)),
 }
 
 /// Defines the WebSocket routes.
@@ -70,6 +82,7 @@ async fn websocket_handler(
 /// The handler can communicate with the WebSocket Service through `mpsc` channels.
 /// 1. `system_tx` channel is the app-wide channel to send messages to the WebSocket Service.
 /// 2. `service_to_client_tx` channel is the chanel where the Service sends messages to the client handlers.
+#[instrument(name = "websocket", skip_all, fields(user = user_info.sub(), peer = %socket_addr))]
 async fn handle_socket(
     mut socket: WebSocket,
     system_tx: Sender<SystemMessage>,
@@ -77,7 +90,6 @@ async fn handle_socket(
     user_info: GoogleUserInfo,
 ) {
     info!("New WebSocket connection: {:?}", socket_addr);
-    dbg!(&user_info);
 
     let user_ws_id_pair = (user_info.sub().to_string(), generate_user_id());
 
@@ -111,7 +123,7 @@ async fn handle_socket(
         tokio::select! {
             ws_msg_res = socket.next() => match ws_msg_res {
                 Some(Ok(msg)) => match msg {
-                    axum::extract::ws::Message::Close(_) => {
+                    Message::Close(_) => {
                         info!("Closing connection");
                         ask_to_remove_this_peer().await;
                         return;
@@ -134,8 +146,10 @@ async fn handle_socket(
             },
             service_msg_res = service_to_client_rx.recv() => {
                 if let Some(msg) = service_msg_res {
-                    warn!("Received message from the main service: {:?}", msg);
-                    todo!("Handle service message");
+                    let text = serde_json::to_string(&msg).expect("Service messages are always serializable");
+                    if let Err(e) = socket.send(Message::Text(text)).await {
+                        warn!("Failed to send a service message to the client: {:?}", e);
+                    }
                 } else {
                     error!("WS Service task has exited or closed the mpsc channel");
                     return;
@@ -154,6 +168,7 @@ async fn handle_socket(
 /// The service is also responsible for sending messages to the clients.
 /// * When a report request completes, the service sends the report to the client.
 /// * When the number of remaining Reddit API requests changes, the service sends the new number to all clients.
+/// * When an alert rule fires, the service sends the alert to all connections of the rule's owner.
 pub async fn start_service(
     mut system_rx: Receiver<SystemMessage>,
     cancellation_token: CancellationToken,
@@ -169,10 +184,15 @@ pub async fn start_service(
                         SystemMessage::AddPeer(ws_user_id) => {
                             peers.add_peer(ws_user_id);
                             info!("Peers number: {}", peers.len());
+                            metrics::WEBSOCKET_PEERS.set(peers.len() as i64);
                         }
                         SystemMessage::RemovePeer(connection_id) => {
                             peers.remove_peer(connection_id);
                             info!("Peers number: {}", peers.len());
+                            metrics::WEBSOCKET_PEERS.set(peers.len() as i64);
+                        }
+                        SystemMessage::Alert((google_id, payload)) => {
+                            peers.send_to_user(&google_id, ServiceToClientMessage::Alert(payload));
                         }
                         _ => {}
                     }
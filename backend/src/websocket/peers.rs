@@ -1,4 +1,4 @@
-use crate::websocket::{ConnectionId, ServiceToClientMessage, WsUserId};
+use crate::websocket::{ConnectionId, GoogleId, ServiceToClientMessage, WsUserId};
 use std::collections::HashMap;
 use tokio::sync::mpsc::Sender;
 
@@ -41,6 +41,16 @@ impl PeersMap {
         }
     }
 
+    /// Send a message to all connections of a user.
+    /// Connections whose handler can't keep up with the messages miss this one.
+    pub fn send_to_user(&self, google_id: &GoogleId, msg: ServiceToClientMessage) {
+        for ((_, conn_id), sender) in self.peers.iter().filter(|((id, _), _)| id == google_id) {
+            if let Err(e) = sender.try_send(msg.clone()) {
+                log::warn!("Failed to send a message to connection {}: {}", conn_id, e);
+            }
+        }
+    }
+
     pub fn len(&self) -> usize {
         self.peers.len()
     }
@@ -59,7 +69,7 @@ mod tests {
 
         peers.add_peer((user_id.clone(), sender.clone()));
         assert_eq!(peers.peers.len(), 1);
-        assert!(peers.peers.get(&user_id).is_some());
+        assert!(peers.peers.contains_key(&user_id));
     }
 
     #[tokio::test]
@@ -93,6 +103,40 @@ mod tests {
         assert_eq!(peers.peers.len(), 0);
     }
 
+    #[tokio::test]
+    async fn test_send_to_user() {
+        use crate::monitor::alert::{AlertCondition, AlertMetric, AlertPayload};
+
+        let mut peers = PeersMap::new();
+        let (sender_1, mut receiver_1) = channel(1);
+        let (sender_2, mut receiver_2) = channel(1);
+        let (sender_3, mut receiver_3) = channel(1);
+        peers.add_peer((("google_id".to_string(), "conn_1".to_string()), sender_1));
+        peers.add_peer((("google_id".to_string(), "conn_2".to_string()), sender_2));
+        peers.add_peer((("google_id_2".to_string(), "conn_3".to_string()), sender_3));
+
+        let payload = AlertPayload {
+            rule_id: 1,
+            monitor_id: 1,
+            subreddit: "Polska".to_string(),
+            metric: AlertMetric::ToxicityRate,
+            value: 0.3,
+            condition: AlertCondition::BaselineRatio {
+                factor: 2.0,
+                baseline_days: 7,
+            },
+            fired_at: chrono::Utc::now(),
+        };
+        peers.send_to_user(
+            &"google_id".to_string(),
+            ServiceToClientMessage::Alert(payload),
+        );
+
+        assert!(receiver_1.try_recv().is_ok());
+        assert!(receiver_2.try_recv().is_ok());
+        assert!(receiver_3.try_recv().is_err());
+    }
+
     #[tokio::test]
     async fn test_remove_peer_multiple() {
         let mut peers = PeersMap::new();
@@ -105,6 +149,6 @@ mod tests {
         assert_eq!(peers.peers.len(), 2);
         peers.remove_peer("conn_id".to_string());
         assert_eq!(peers.peers.len(), 1);
-        assert!(peers.peers.get(&user_id_2).is_some());
+        assert!(peers.peers.contains_key(&user_id_2));
     }
 }
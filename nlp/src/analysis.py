@@ -0,0 +1,162 @@
+import re
+from collections import Counter
+
+"""
+Baseline analyzers of the /analyze endpoint.
+
+They are word-list heuristics for Polish and English, so the backend gets a well-formed answer for every report type.
+Each of them will be replaced by the matching model listed in models.version.
+"""
+
+REPORT_TYPES = [
+    "sentiment",
+    "sarcasm",
+    "keywords",
+    "language",
+    "spam",
+    "politics",
+    "hate_speech",
+    "clickbait",
+    "troll",
+]
+
+WORD = re.compile(r"[^\W\d_]+", re.UNICODE)
+
+STOPWORDS = {
+    "pl": {
+        "i", "w", "na", "z", "że", "to", "nie", "się", "jest", "do", "o", "jak", "ale", "co",
+        "a", "od", "po", "tak", "za", "czy", "już", "tylko", "ten", "ta", "są", "by", "jego",
+        "mi", "mnie", "ja", "ty", "on", "ona", "my", "wy", "oni", "dla", "bo", "tym", "tego",
+    },
+    "en": {
+        "the", "a", "an", "and", "or", "but", "is", "are", "was", "were", "to", "of", "in",
+        "on", "for", "with", "it", "this", "that", "i", "you", "he", "she", "we", "they",
+        "not", "be", "at", "by", "as", "have", "has", "do", "so", "my", "your", "just",
+    },
+}
+
+POSITIVE = {
+    "good", "great", "love", "nice", "happy", "awesome", "best", "thanks", "excellent", "like",
+    "dobry", "dobrze", "super", "świetny", "świetnie", "kocham", "dzięki", "fajny", "fajnie",
+    "najlepszy", "wspaniały", "lubię",
+}
+NEGATIVE = {
+    "bad", "hate", "awful", "terrible", "worst", "sad", "angry", "horrible", "stupid", "sucks",
+    "zły", "źle", "okropny", "najgorszy", "nienawidzę", "smutny", "beznadziejny", "głupi",
+    "słaby", "tragedia",
+}
+
+# Words hinting at each detection report
+LEXICONS = {
+    "sarcasm": {"yeah", "sure", "totally", "obviously", "wow", "genius", "jasne", "oczywiście", "brawo"},
+    "spam": {"buy", "free", "discount", "promo", "click", "subscribe", "offer", "kup", "darmowy", "promocja", "zniżka"},
+    "politics": {
+        "government", "election", "president", "party", "vote", "parliament", "minister",
+        "rząd", "wybory", "prezydent", "partia", "sejm", "minister", "posłowie", "premier",
+    },
+    "hate_speech": {"idiot", "idiots", "scum", "trash", "subhuman", "idiota", "idioci", "śmieci", "debil", "debile"},
+    "clickbait": {"shocking", "unbelievable", "secret", "you", "won't", "believe", "szok", "niewiarygodne", "sekret", "zobacz"},
+    "troll": {"cope", "seethe", "ratio", "triggered", "lol", "lmao", "xd", "beka", "płacz"},
+}
+
+KEYWORDS_LIMIT = 5
+
+
+def words(text):
+    """
+    This function splits the text into lowercase words.
+
+    :param text: The text to split.
+
+    :return: The list of words.
+    """
+    return [w.lower() for w in WORD.findall(text)]
+
+
+def sentiment(tokens):
+    """
+    This function computes the polarity of the text.
+
+    :param tokens: The words of the text.
+
+    :return: The polarity in the range of -1 (negative) to 1 (positive), 0 if no word carries any.
+    """
+    positive = sum(1 for t in tokens if t in POSITIVE)
+    negative = sum(1 for t in tokens if t in NEGATIVE)
+    if positive + negative == 0:
+        return 0.0
+    return (positive - negative) / (positive + negative)
+
+
+def probability(tokens, lexicon):
+    """
+    This function estimates how likely the text belongs to a category.
+
+    :param tokens: The words of the text.
+    :param lexicon: The words hinting at the category.
+
+    :return: The probability in the range of 0 to 1.
+    """
+    hits = sum(1 for t in tokens if t in lexicon)
+    return min(1.0, 3 * hits / max(len(tokens), 1))
+
+
+def keywords(tokens):
+    """
+    This function picks the most frequent words of the text, skipping stopwords.
+
+    :param tokens: The words of the text.
+
+    :return: At most KEYWORDS_LIMIT keywords, the most frequent first.
+    """
+    stopwords = STOPWORDS["pl"] | STOPWORDS["en"]
+    counts = Counter(t for t in tokens if t not in stopwords and len(t) > 2)
+    return [word for word, _ in counts.most_common(KEYWORDS_LIMIT)]
+
+
+def language(tokens):
+    """
+    This function detects the language of the text by its stopwords.
+
+    :param tokens: The words of the text.
+
+    :return: The ISO 639-1 code, None if the text has no known stopwords.
+    """
+    scores = {code: sum(1 for t in tokens if t in stopwords) for code, stopwords in STOPWORDS.items()}
+    code, score = max(scores.items(), key=lambda item: item[1])
+    return code if score > 0 else None
+
+
+def analyze_text(text, reports):
+    """
+    This function analyzes a single text.
+
+    :param text: The text to analyze.
+    :param reports: The report types to compute.
+
+    :return: A dictionary with a key for every report type, None for the ones that weren't requested.
+    """
+    tokens = words(text)
+    result = {report: None for report in REPORT_TYPES}
+    for report in reports:
+        if report == "sentiment":
+            result[report] = sentiment(tokens)
+        elif report == "keywords":
+            result[report] = keywords(tokens)
+        elif report == "language":
+            result[report] = language(tokens)
+        else:
+            result[report] = probability(tokens, LEXICONS[report])
+    return result
+
+
+def analyze(texts, reports):
+    """
+    This function analyzes the texts.
+
+    :param texts: The texts to analyze.
+    :param reports: The report types to compute.
+
+    :return: The list of results, one per text, in the same order.
+    """
+    return [analyze_text(text, reports) for text in texts]
@@ -1,5 +1,6 @@
 from flask import Flask, request, jsonify
 from version_checker import get_version
+from analysis import REPORT_TYPES, analyze
 
 app = Flask(__name__)
 
@@ -17,6 +18,30 @@ def get_json_data():
     return jsonify(data)
 
 
+@app.route('/analyze', methods=['POST'])
+def analyze_texts():
+    """
+    This function is called when a POST request is made to the /analyze URL.
+    It analyzes the texts with the requested reports, eg.
+    {"reports": ["sentiment", "hate_speech"], "texts": ["first text", "second text"]}
+
+    :return: The list of results, one per text, in the same order, or an error with the 400 status.
+    """
+    data = request.get_json()
+    reports = data.get('reports') if isinstance(data, dict) else None
+    texts = data.get('texts') if isinstance(data, dict) else None
+
+    if not isinstance(reports, list) or not isinstance(texts, list):
+        return jsonify({'error': 'Expected "reports" and "texts" lists'}), 400
+    unknown = [r for r in reports if r not in REPORT_TYPES]
+    if unknown:
+        return jsonify({'error': f'Unknown reports: {unknown}'}), 400
+    if not all(isinstance(t, str) for t in texts):
+        return jsonify({'error': 'Every text must be a string'}), 400
+
+    return jsonify(analyze(texts, reports))
+
+
 @app.errorhandler(415)
 def unsupported_media_type(error):
     """
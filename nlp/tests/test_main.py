@@ -4,6 +4,7 @@ sys.path.insert(1, "src")
 
 from main import app
 import json 
+import os
 
 
 """
@@ -36,3 +37,38 @@ def test_get_json_data():
     )
     
     assert response.status_code == 415
+
+
+def load_contract():
+    """
+    The /analyze request the backend sends and the response it expects, shared with the backend's tests.
+    """
+    with open(os.path.join(os.path.dirname(__file__), "contract", "analyze.json")) as f:
+        return json.load(f)
+
+
+def test_analyze_contract():
+    """
+    Test function checking that /analyze answers the backend's request in the shape the backend parses.
+    """
+    contract = load_contract()
+    response = app.test_client().post('/analyze', json=contract['request'])
+
+    assert response.status_code == 200
+    data = response.get_json()
+    assert data == contract['response']
+
+
+def test_analyze_rejects_invalid_requests():
+    """
+    Test function checking that unknown reports and malformed payloads are rejected.
+    """
+    response = app.test_client().post('/analyze', json={'reports': ['mood'], 'texts': ['text']})
+    assert response.status_code == 400
+
+    response = app.test_client().post('/analyze', json={'reports': ['sentiment']})
+    assert response.status_code == 400
+
+    response = app.test_client().post('/analyze', json={'reports': [], 'texts': ['text']})
+    assert response.status_code == 200
+    assert response.get_json() == [{report: None for report in load_contract()['response'][0]}]